@@ -0,0 +1,229 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use thiserror::Error;
+
+use crate::maze::{Algorithm, Difficulty, Maze, MazeError, Opts, Position, DIRECTIONS};
+use crate::race::{Racer, Solver};
+
+#[derive(Error, Debug)]
+pub enum CalibrateError {
+    #[error("invalid maze size, expected WIDTHxHEIGHT like 20x10")]
+    SizeParseError,
+}
+
+/// Maze size in cells, written `20x10`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Size {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl FromStr for Size {
+    type Err = CalibrateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (w, h) = s.split_once('x').ok_or(CalibrateError::SizeParseError)?;
+        match (w.parse(), h.parse()) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok(Size { width, height }),
+            _ => Err(CalibrateError::SizeParseError),
+        }
+    }
+}
+
+/// How to sample the mazes and play them.
+#[derive(Clone, Debug)]
+pub struct Calibration {
+    pub sizes: Vec<Size>,
+    pub difficulties: Vec<Difficulty>,
+    pub algorithms: Vec<Algorithm>,
+    /// mazes generated for every combination
+    pub samples: usize,
+    /// chance [0, 1] of the simulated player taking the right way at each move, the
+    /// other moves are random
+    pub skill: f32,
+    /// time the simulated player takes for a move
+    pub move_time: Duration,
+    pub seed: u64,
+}
+
+/// Averages over the mazes of one combination.
+#[derive(Clone, Debug)]
+pub struct Row {
+    pub size: Size,
+    pub difficulty: Difficulty,
+    pub algorithm: Algorithm,
+    /// moves of the shortest way out
+    pub solution: f32,
+    /// wrong turns on the shortest way out, see `Maze::decisions`
+    pub decisions: f32,
+    /// cells explored by breadth first search
+    pub bfs: f32,
+    /// cells walked by the wall follower
+    pub wall: f32,
+    /// moves of the simulated player
+    pub moves: f32,
+    /// expected time of the simulated player
+    pub time: Duration,
+}
+
+impl Calibration {
+    /// Generate and play the mazes of every combination, one row each.
+    pub fn run(&self) -> Result<Vec<Row>, MazeError> {
+        let mut rng = Pcg64::seed_from_u64(self.seed);
+        let mut rows = Vec::new();
+        for size in self.sizes.iter() {
+            for difficulty in self.difficulties.iter() {
+                for algorithm in self.algorithms.iter() {
+                    let opts = Opts {
+                        difficulty: *difficulty,
+                        algorithm: *algorithm,
+                        ..Default::default()
+                    };
+                    rows.push(self.sample(*size, &opts, &mut rng)?);
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    fn sample(&self, size: Size, opts: &Opts, rng: &mut Pcg64) -> Result<Row, MazeError> {
+        let mut totals = [0.0f32; 5];
+        for _ in 0..self.samples {
+            let m = Maze::generate_with_rng(size.width, size.height, opts, rng)?;
+            totals[0] += m.solution_length().unwrap_or(0) as f32;
+            totals[1] += m.decisions() as f32;
+            totals[2] += solve(&m, Solver::Bfs) as f32;
+            totals[3] += solve(&m, Solver::Wall) as f32;
+            totals[4] += play(&m, self.skill, rng) as f32;
+        }
+        let n = self.samples.max(1) as f32;
+        let [solution, decisions, bfs, wall, moves] = totals;
+        Ok(Row {
+            size,
+            difficulty: opts.difficulty,
+            algorithm: opts.algorithm,
+            solution: solution / n,
+            decisions: decisions / n,
+            bfs: bfs / n,
+            wall: wall / n,
+            moves: moves / n,
+            time: self.move_time.mul_f32(moves / n),
+        })
+    }
+}
+
+/// cells the solver explores to find the exit
+fn solve(m: &Maze, solver: Solver) -> usize {
+    let mut r = Racer::new(solver, m);
+    while r.step(m) {}
+    r.steps()
+}
+
+/// Moves of a player taking the right way with the given chance at every move and a
+/// random one otherwise, up to 20 times the cells of the maze.
+pub fn play<R: Rng>(m: &Maze, skill: f32, rng: &mut R) -> usize {
+    let dist = m.exit_distance_map();
+    let cell = |p: Position| (p.y * m.width + p.x) as usize;
+    let mut pos = m.entrance();
+    let limit = 20 * m.width as usize * m.height as usize;
+    let mut moves = 0;
+    while !m.is_exit(pos) && moves < limit {
+        let open: Vec<Position> = DIRECTIONS
+            .iter()
+            .filter_map(|d| m.move_pos(pos, d))
+            .collect();
+        let best = open.iter().copied().min_by_key(|p| dist[cell(*p)]);
+        pos = match best {
+            Some(b) if rng.gen::<f32>() < skill => b,
+            _ => match open.choose(rng) {
+                Some(p) => *p,
+                None => break,
+            },
+        };
+        moves += 1;
+    }
+    moves
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>7} {:<10} {:<20} {:>8.1} {:>9.1} {:>6.1} {:>6.1} {:>7.1} {:>7.1}s",
+            format!("{}x{}", self.size.width, self.size.height),
+            format!("{:?}", self.difficulty),
+            format!("{:?}", self.algorithm),
+            self.solution,
+            self.decisions,
+            self.bfs,
+            self.wall,
+            self.moves,
+            self.time.as_secs_f32(),
+        )
+    }
+}
+
+/// header of the table of rows, the columns line up with the rows
+pub fn header() -> String {
+    format!(
+        "{:>7} {:<10} {:<20} {:>8} {:>9} {:>6} {:>6} {:>7} {:>8}",
+        "size", "difficulty", "algorithm", "solution", "decisions", "bfs", "wall", "moves", "time"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibrate() {
+        assert_eq!(
+            "20x10".parse::<Size>().unwrap(),
+            Size {
+                width: 20,
+                height: 10
+            }
+        );
+        assert!("20".parse::<Size>().is_err());
+        assert!("0x10".parse::<Size>().is_err());
+
+        let calibration = Calibration {
+            sizes: vec![Size {
+                width: 8,
+                height: 5,
+            }],
+            difficulties: vec![Difficulty::Hard, Difficulty::Easy],
+            algorithms: vec![Algorithm::RandomKruskal],
+            samples: 4,
+            skill: 1.0,
+            move_time: Duration::from_millis(500),
+            seed: 9,
+        };
+        let rows = calibration.run().unwrap();
+        assert_eq!(rows.len(), 2);
+        for row in rows.iter() {
+            // a perfect player walks the shortest way
+            assert_eq!(row.moves, row.solution);
+            assert!((row.time.as_secs_f32() - row.moves / 2.0).abs() < 0.01);
+            assert!(row.bfs >= row.solution);
+        }
+        // loops give shorter ways out
+        assert!(rows[1].solution <= rows[0].solution);
+        assert_eq!(header().len(), rows[0].to_string().len());
+
+        // a clumsy player takes longer
+        let opts = Opts {
+            seed: Some(2),
+            ..Default::default()
+        };
+        let m = Maze::generate(8, 5, &opts).unwrap();
+        let mut rng = Pcg64::seed_from_u64(1);
+        let len = m.solution_length().unwrap();
+        assert!((0..10).map(|_| play(&m, 0.5, &mut rng)).sum::<usize>() > 10 * len);
+    }
+}
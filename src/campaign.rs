@@ -0,0 +1,386 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::maze::Difficulty;
+use crate::records::Record;
+
+/// Cells added to or removed from the width and height of the levels per resize.
+pub const RESIZE_STEP: i16 = 2;
+
+#[derive(Error, Debug)]
+pub enum CampaignError {
+    #[error("invalid ramp, expected FROM:TO or FROM:TO:EASE with EASE one of linear, in, out")]
+    RampParseError,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+}
+
+/// How a ramp moves from its start to its end value.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Ease {
+    Linear,
+    /// slow start, steep end
+    In,
+    /// steep start, slow end
+    Out,
+}
+
+/// Value going from `from` on the first level to `to` on the last one.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ramp {
+    pub from: f32,
+    pub to: f32,
+    pub ease: Ease,
+}
+
+impl Ramp {
+    pub fn constant(v: f32) -> Ramp {
+        Ramp {
+            from: v,
+            to: v,
+            ease: Ease::Linear,
+        }
+    }
+
+    /// value at t in [0, 1]
+    pub fn at(&self, t: f32) -> f32 {
+        let t = match self.ease {
+            Ease::Linear => t,
+            Ease::In => t * t,
+            Ease::Out => 1.0 - (1.0 - t) * (1.0 - t),
+        };
+        self.from + (self.to - self.from) * t
+    }
+}
+
+impl FromStr for Ramp {
+    type Err = CampaignError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let num = |p: &str| p.parse::<f32>().map_err(|_| CampaignError::RampParseError);
+        match parts.as_slice() {
+            [v] => Ok(Ramp::constant(num(v)?)),
+            [from, to] | [from, to, _] => Ok(Ramp {
+                from: num(from)?,
+                to: num(to)?,
+                ease: match parts.get(2) {
+                    None | Some(&"linear") => Ease::Linear,
+                    Some(&"in") => Ease::In,
+                    Some(&"out") => Ease::Out,
+                    _ => return Err(CampaignError::RampParseError),
+                },
+            }),
+            _ => Err(CampaignError::RampParseError),
+        }
+    }
+}
+
+/// Target difficulty curve of a campaign, every ramp spans the whole campaign.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Curve {
+    pub levels: usize,
+    pub difficulty: Difficulty,
+    pub width: Ramp,
+    pub height: Ramp,
+    /// share of dead ends opened into loops
+    pub braid: Ramp,
+    /// how far the player sees, 0 for no fog
+    pub fog: Ramp,
+    pub enemies: Ramp,
+    /// cells the player may add to or remove from the size of the levels
+    pub resize: u16,
+}
+
+impl Curve {
+    /// Build the level pack following the curve.
+    pub fn generate(&self, name: &str) -> LevelPack {
+        let levels = (0..self.levels)
+            .map(|i| {
+                let t = if self.levels > 1 {
+                    i as f32 / (self.levels - 1) as f32
+                } else {
+                    0.0
+                };
+                Level {
+                    width: self.width.at(t).round().max(2.0) as u16,
+                    height: self.height.at(t).round().max(2.0) as u16,
+                    difficulty: self.difficulty,
+                    braid: self.braid.at(t).clamp(0.0, 1.0),
+                    fog: self.fog.at(t).round().max(0.0) as u16,
+                    enemies: self.enemies.at(t).round().max(0.0) as usize,
+                }
+            })
+            .collect();
+        LevelPack {
+            name: name.to_string(),
+            resize: self.resize,
+            levels,
+        }
+    }
+}
+
+/// Where the progress in the campaign packs is kept, `rusty-maze/progress` in the data
+/// directory of the platform next to the statistics, see `LevelPack::progress_file`. None
+/// when there is no such directory.
+pub fn progress_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("progress"))
+}
+
+/// Where the progress in the endless campaign is kept, `rusty-maze/endless.ron` in the
+/// data directory of the platform next to the statistics. None when there is no such
+/// directory.
+pub fn endless_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("endless.ron"))
+}
+
+/// Settings of a single campaign level.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub width: u16,
+    pub height: u16,
+    pub difficulty: Difficulty,
+    pub braid: f32,
+    pub fog: u16,
+    pub enemies: usize,
+}
+
+/// Shareable list of levels played in order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelPack {
+    pub name: String,
+    /// cells the player may add to or remove from the size of the levels
+    #[serde(default)]
+    pub resize: u16,
+    pub levels: Vec<Level>,
+}
+
+impl LevelPack {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<LevelPack, CampaignError> {
+        let file = File::open(path)?;
+        Ok(ron::de::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CampaignError> {
+        let out = File::create(path)?;
+        ron::ser::to_writer_pretty(out, self, Default::default())?;
+        Ok(())
+    }
+
+    /// File of the progress in the pack within `dir`, named after the pack with the
+    /// characters a file name can't take replaced.
+    pub fn progress_file(&self, dir: &Path) -> PathBuf {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect();
+        dir.join(name).with_extension("ron")
+    }
+}
+
+/// How far the player got in a pack, along with the size they picked for the levels.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub pack: String,
+    /// index of the next level to play
+    pub level: usize,
+    /// cells added to the width and height of the levels, negative when shrunk
+    pub resize: i16,
+}
+
+impl Progress {
+    /// Progress saved for the pack, a missing file or one of another pack starts over.
+    pub fn load<P: AsRef<Path>>(path: P, pack: &LevelPack) -> Progress {
+        File::open(path)
+            .ok()
+            .and_then(|f| ron::de::from_reader::<_, Progress>(BufReader::new(f)).ok())
+            .filter(|p| p.pack == pack.name)
+            .unwrap_or_else(|| Progress::new(pack))
+    }
+
+    /// start of the pack, at the first level and the size of the pack
+    pub fn new(pack: &LevelPack) -> Progress {
+        Progress {
+            pack: pack.name.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CampaignError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let out = File::create(path)?;
+        ron::ser::to_writer(out, self)?;
+        Ok(())
+    }
+
+    /// grow the levels by the given number of steps, or shrink them for negative ones,
+    /// within the bounds of the pack
+    pub fn resize(&mut self, pack: &LevelPack, steps: i16) {
+        let bound = pack.resize as i16;
+        self.resize = (self.resize + steps * RESIZE_STEP).clamp(-bound, bound);
+    }
+
+    /// next level to play with the size picked by the player, None once the pack is done
+    pub fn next_level(&self, pack: &LevelPack) -> Option<Level> {
+        let resized = |v: u16| (v as i16 + self.resize).max(2) as u16;
+        pack.levels.get(self.level).map(|l| Level {
+            width: resized(l.width),
+            height: resized(l.height),
+            ..l.clone()
+        })
+    }
+}
+
+/// How far the player got in the endless campaign, where every level is a little larger
+/// and harder than the one before.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Endless {
+    /// index of the next level to play
+    pub level: usize,
+    /// time spent on the completed levels
+    pub millis: u64,
+}
+
+impl Endless {
+    /// Saved progress, a missing or unreadable file starts over.
+    pub fn load<P: AsRef<Path>>(path: P) -> Endless {
+        File::open(path)
+            .ok()
+            .and_then(|f| ron::de::from_reader(BufReader::new(f)).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CampaignError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let out = File::create(path)?;
+        ron::ser::to_writer(out, self)?;
+        Ok(())
+    }
+
+    /// Next level to play, two cells wider and one taller than the last and no larger
+    /// than `max_width` by `max_height`, with the difficulty going up every few levels.
+    pub fn next_level(&self, max_width: u16, max_height: u16) -> Level {
+        let difficulty = match self.level {
+            0..=1 => Difficulty::Easy,
+            2..=4 => Difficulty::Normal,
+            5..=9 => Difficulty::Hard,
+            10..=14 => Difficulty::Expert,
+            _ => Difficulty::Insane,
+        };
+        let grow = |from: usize, step: usize, max: u16| {
+            (from + step * self.level).min(max.max(2) as usize) as u16
+        };
+        Level {
+            width: grow(10, 2, max_width),
+            height: grow(5, 1, max_height),
+            difficulty,
+            braid: 0.0,
+            fog: difficulty.fog(),
+            enemies: 0,
+        }
+    }
+
+    /// count the run of the level played and move on to the next one
+    pub fn complete(&mut self, run: Record) {
+        self.level += 1;
+        self.millis += run.millis;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curve() {
+        let curve = Curve {
+            levels: 5,
+            difficulty: Difficulty::Normal,
+            width: "10:30".parse().unwrap(),
+            height: "5:15:in".parse().unwrap(),
+            braid: "0.5:0".parse().unwrap(),
+            fog: "0:4:out".parse().unwrap(),
+            enemies: "2".parse().unwrap(),
+            resize: 3,
+        };
+        assert!("1:2:3".parse::<Ramp>().is_err());
+        assert!("a:2".parse::<Ramp>().is_err());
+
+        let pack = curve.generate("Test");
+        let widths: Vec<u16> = pack.levels.iter().map(|l| l.width).collect();
+        assert_eq!(widths, [10, 15, 20, 25, 30]);
+        let heights: Vec<u16> = pack.levels.iter().map(|l| l.height).collect();
+        assert_eq!(heights, [5, 6, 8, 11, 15]);
+        let fog: Vec<u16> = pack.levels.iter().map(|l| l.fog).collect();
+        assert_eq!(fog, [0, 2, 3, 4, 4]);
+        assert_eq!(pack.levels[4].braid, 0.0);
+        assert!(pack.levels.iter().all(|l| l.enemies == 2));
+
+        let saved = ron::ser::to_string(&pack).unwrap();
+        assert_eq!(ron::de::from_str::<LevelPack>(&saved).unwrap(), pack);
+
+        let mut progress = Progress {
+            pack: pack.name.clone(),
+            level: 1,
+            resize: 0,
+        };
+        progress.resize(&pack, 1);
+        assert_eq!(progress.next_level(&pack).unwrap().width, 17);
+        progress.resize(&pack, 1);
+        assert_eq!(progress.resize, 3);
+        progress.resize(&pack, -5);
+        assert_eq!(progress.next_level(&pack).unwrap().height, 3);
+        progress.level = 5;
+        assert_eq!(progress.next_level(&pack), None);
+
+        let dir = Path::new("progress");
+        assert_eq!(pack.progress_file(dir), dir.join("Test.ron"));
+        let pack = curve.generate("../My pack.v2");
+        assert_eq!(pack.progress_file(dir), dir.join("___My_pack_v2.ron"));
+    }
+
+    #[test]
+    fn test_endless() {
+        let mut endless = Endless::default();
+        let first = endless.next_level(40, 20);
+        assert_eq!((first.width, first.height), (10, 5));
+        assert_eq!(first.difficulty, Difficulty::Easy);
+        endless.complete(Record {
+            moves: 30,
+            millis: 1500,
+            score: None,
+        });
+        endless.complete(Record {
+            moves: 40,
+            millis: 2500,
+            score: None,
+        });
+        assert_eq!(endless.millis, 4000);
+        let third = endless.next_level(40, 20);
+        assert_eq!((third.width, third.height), (14, 7));
+        assert_eq!(third.difficulty, Difficulty::Normal);
+        // the size stops growing at the bound, the difficulty doesn't
+        endless.level = 20;
+        let last = endless.next_level(40, 20);
+        assert_eq!((last.width, last.height), (40, 20));
+        assert_eq!(last.difficulty, Difficulty::Insane);
+        assert_eq!(last.fog, 3);
+    }
+}
@@ -0,0 +1,151 @@
+use std::iter::FromIterator;
+
+use termion::{color, style};
+
+use crate::maze::{Joystick, Locate, Markers, MazeUI, Position, EXIT_LABEL};
+
+/// Callback receiving every rendered frame, e.g. to feed a recorder or a streaming overlay.
+pub type FrameHook = Box<dyn FnMut(&Frame)>;
+
+/// Board glyph of the player.
+const PLAYER: char = '@';
+
+/// Board glyph of the visited path.
+const TRAIL: char = '·';
+
+/// Rendered frame of the game as a grid of board cells.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub cells: Vec<Vec<char>>,
+    /// board position of the player
+    pub player: Position,
+    /// board positions of the exit labels
+    pub exit_labels: Vec<Position>,
+}
+
+impl Frame {
+    /// Capture the board with the player, its trail when shown, and the markers.
+    pub fn capture(ui: &MazeUI, j: &Joystick, show_path: bool, markers: &Markers) -> Frame {
+        Frame::capture_on(ui, ui.draw(), j, show_path, markers)
+    }
+
+    /// Like `capture` over a board drawn otherwise, e.g. through the fog.
+    pub fn capture_on(
+        ui: &MazeUI,
+        board: Vec<Vec<char>>,
+        j: &Joystick,
+        show_path: bool,
+        markers: &Markers,
+    ) -> Frame {
+        let mut cells = ui.frame_on(board, j, show_path);
+        ui.overlay(&mut cells, markers);
+        Frame {
+            cells,
+            player: ui.locate(j),
+            exit_labels: ui.exit_labels().into_iter().map(|l| l.0).collect(),
+        }
+    }
+
+    /// the frame as plain text lines
+    pub fn text(&self) -> String {
+        self.cells
+            .iter()
+            .map(String::from_iter)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// The frame with ANSI colors like the game draws it, lines end with "\r\n" so it can
+    /// be written to a terminal in raw mode.
+    pub fn styled(&self) -> String {
+        let mut out = String::new();
+        for (y, row) in self.cells.iter().enumerate() {
+            for x in 0..row.len() {
+                out += &self.styled_cell(x, y);
+            }
+            out += "\r\n";
+        }
+        out
+    }
+
+    /// the cell with its ANSI colors
+    fn styled_cell(&self, x: usize, y: usize) -> String {
+        let c = self.cells[y][x];
+        let p = Position {
+            x: x as u16,
+            y: y as u16,
+        };
+        let on_label =
+            |l: &Position| p.y == l.y && p.x >= l.x && p.x < l.x + EXIT_LABEL.len() as u16;
+        if p == self.player && c == PLAYER {
+            format!("{}{}{}", style::Bold, c, style::Reset)
+        } else if self.exit_labels.iter().any(on_label) {
+            format!("{}{}{}", color::Fg(color::Green), c, style::Reset)
+        } else if c == TRAIL {
+            format!("{} {}", color::Bg(color::Blue), style::Reset)
+        } else {
+            c.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{Marker, Maze};
+
+    #[test]
+    fn test_capture() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let ui = m.ui();
+        let mut j = m.joystick();
+        j.right();
+        let frame = Frame::capture(&ui, &j, true, &Markers::default());
+        assert_eq!(
+            frame.text(),
+            "┌───────────┐\n\
+             │ ····@     │\n\
+             ├───────╴   │\n\
+             │       Exit│\n\
+             └───────────┘"
+        );
+        let styled = frame.styled();
+        assert!(styled.contains(&format!("{}@", style::Bold)));
+        assert_eq!(styled.matches("\r\n").count(), 5);
+    }
+
+    #[test]
+    fn test_edges() {
+        // three rows walked as a snake, the exit label on the last one
+        let walls = vec![(1, 5), (2, 6), (3, 7), (4, 8), (5, 9), (6, 10)];
+        let m = Maze::create(4, 3, walls).unwrap();
+        let ui = m.ui();
+        let mut j = m.joystick();
+        let mut markers = Markers::default();
+        markers.add(Marker {
+            pos: Position { x: 2, y: 0 },
+            glyph: '◆',
+            color: None,
+        });
+
+        // the player stays bold over the exit label, the rest of it green
+        j.play_notation("D1 R3 D1").unwrap();
+        let frame = Frame::capture(&ui, &j, true, &markers);
+        assert_eq!(
+            frame.text(),
+            "┌───────────────┐\n\
+             │ ·       ◆     │\n\
+             │ · ╶───────────┤\n\
+             │ ············· │\n\
+             ├───────────╴ · │\n\
+             │           Ex@t│\n\
+             └───────────────┘"
+        );
+        let styled = frame.styled();
+        assert!(styled.contains(&format!("{}@{}", style::Bold, style::Reset)));
+        assert_eq!(
+            styled.matches(&color::Fg(color::Green).to_string()).count(),
+            3
+        );
+    }
+}
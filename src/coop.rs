@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+use rand::seq::SliceRandom;
+
+use crate::maze::{Direction, Maze, Position, DIRECTIONS};
+
+/// Two player puzzle: the door on the way to the exit only opens while someone stands
+/// on the pressure plate.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Puzzle {
+    pub plate: Position,
+    pub door: Position,
+}
+
+impl Puzzle {
+    /// Put the door on the solution where it cuts the entrance off from every exit, and the
+    /// plate on a cell reachable without going through it. None when no cell of the
+    /// solution does, e.g. when loops of a braided maze go around all of them.
+    pub fn random(maze: &Maze) -> Option<Puzzle> {
+        let solution = maze.solution()?;
+        let mut rng = rand::thread_rng();
+        let mut doors: Vec<Position> = solution
+            .iter()
+            .copied()
+            .filter(|p| *p != maze.entrance() && !maze.is_exit(*p))
+            .collect();
+        doors.shuffle(&mut rng);
+        // doors right after the entrance may leave no room for the plate
+        doors.into_iter().find_map(|door| {
+            let mut cells = reachable_without(maze, maze.entrance(), door);
+            if cells.iter().any(|p| maze.is_exit(*p)) {
+                return None;
+            }
+            cells.retain(|p| *p != maze.entrance());
+            let plate = *cells.choose(&mut rng)?;
+            Some(Puzzle { plate, door })
+        })
+    }
+
+    /// check if one of the players holds the door open
+    pub fn is_open(&self, players: &[Position]) -> bool {
+        players.contains(&self.plate)
+    }
+
+    /// check if the door keeps a player from entering the position
+    pub fn blocks(&self, to: Position, players: &[Position]) -> bool {
+        to == self.door && !self.is_open(players)
+    }
+
+    /// check if the door keeps a player at `from` from moving in the direction, `others`
+    /// are the positions of the other players
+    pub fn blocks_move(
+        &self,
+        maze: &Maze,
+        from: Position,
+        d: &Direction,
+        others: &[Position],
+    ) -> bool {
+        match maze.move_pos(from, d) {
+            Some(to) => self.blocks(to, others),
+            None => false,
+        }
+    }
+}
+
+/// cells reachable from the position without going through the blocked one
+fn reachable_without(maze: &Maze, from: Position, blocked: Position) -> Vec<Position> {
+    let mut seen = vec![from];
+    let mut queue = VecDeque::new();
+    queue.push_back(from);
+    while let Some(p) = queue.pop_front() {
+        for np in DIRECTIONS.iter().filter_map(|d| maze.move_pos(p, d)) {
+            if np != blocked && !seen.contains(&np) {
+                seen.push(np);
+                queue.push_back(np);
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_puzzle() {
+        // two corridors only joined on the right
+        let m = Maze::create(4, 2, vec![(0, 4), (1, 5), (2, 6)]).unwrap();
+        let p = Puzzle::random(&m).unwrap();
+        assert!(m.solution().unwrap().contains(&p.door));
+        assert!(!reachable_without(&m, m.entrance(), p.door).contains(&m.exit()));
+        assert_ne!(p.plate, p.door);
+
+        let partner = p.plate;
+        assert!(p.blocks(p.door, &[m.entrance()]));
+        assert!(!p.blocks(p.door, &[m.entrance(), partner]));
+        assert!(!p.blocks(m.exit(), &[m.entrance()]));
+
+        // a loop around every cell of the solution leaves no door to put
+        let m = Maze::create(2, 2, vec![]).unwrap();
+        assert_eq!(Puzzle::random(&m), None);
+    }
+}
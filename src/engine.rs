@@ -0,0 +1,591 @@
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use termion::color;
+
+use crate::capture::Frame;
+use crate::coop::Puzzle;
+use crate::entity::{Behavior, Entities, Entity, Kind};
+use crate::game::{Action, GameOpts};
+use crate::maze::{Direction, Joystick, Marker, Markers, Maze, MazeUI, Position, Rotation};
+use crate::orienteering::Course;
+use crate::overlay::{Fog, Memory, Overlay};
+use crate::records::Record;
+use crate::replay::{Replay, Step};
+use crate::treasure::{Hint, Treasure, HINT_EVERY};
+
+/// Sight radius on the way back of a return trip.
+const RETURN_SIGHT: u16 = 2;
+
+/// Fewest moves between the entrance and an enemy when it spawns.
+const ENEMY_DISTANCE: usize = 4;
+
+/// What a key press changed in the game, for the front end to draw and tell the player.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// the player moved, was sent back to the entrance or undid a move
+    Stepped(Step),
+    /// a move into a wall
+    Blocked,
+    /// first move into a hidden wall, by cell and side as seen on screen
+    Bumped(Position, Direction),
+    /// a move stopped by the closed door of the puzzle
+    DoorClosed,
+    /// the maze turned a quarter clockwise
+    Turned,
+    /// a stop of the course reached, an exit or a checkpoint, with the stops left
+    Reached {
+        exit: bool,
+        left: usize,
+    },
+    /// at an exit with stops of the course left
+    Locked,
+    TreasureFound,
+    TreasureHint(Hint),
+    /// The exit is reached. The run is over unless a return trip starts, see
+    /// `Engine::start_return`.
+    Won,
+    /// back at the entrance with the run the return trip started from
+    Back(Record),
+    ReturnAbandoned,
+    DoorOpened,
+    DoorShut,
+    /// an enemy caught the player, back to the entrance
+    Caught,
+    /// the walls or the sight changed, the board has to be drawn again
+    Redraw,
+}
+
+/// Every turn of the maze the engine may show, only the first one without rotations.
+pub fn views(maze: &Maze, opts: &GameOpts) -> Vec<Maze> {
+    let turns = if opts.rotate_every > 0 { 4 } else { 1 };
+    (0..turns).map(|r| maze.rotate(Rotation::new(r))).collect()
+}
+
+/// The rules of a game in progress, from the key presses to what changed. The interactive
+/// game and the headless driver both play through it, they only differ in how they draw
+/// and what they keep of a run.
+pub struct Engine<'a> {
+    /// turns of the maze from `views`
+    views: &'a [Maze],
+    joystick: Joystick<'a>,
+    /// the waiting player of a co-op puzzle, switched with the one being moved
+    partner: Joystick<'a>,
+    partner_marker: Option<usize>,
+    puzzle: Option<Puzzle>,
+    door_open: bool,
+    course: Course,
+    entities: Entities,
+    markers: Markers,
+    treasure: Option<Treasure>,
+    rotation: Rotation,
+    rotate_every: usize,
+    /// on the turned maze
+    fog: Fog,
+    memory: Memory,
+    /// run at the exit, scored, while on the way back of a return trip
+    returning: Option<Record>,
+    finished: bool,
+    replay: Replay,
+}
+
+impl<'a> Engine<'a> {
+    /// Set up the course, enemies, puzzle and treasure the options ask for. `views` come
+    /// from `views` and the joystick may be resumed from a saved game, the replay starts
+    /// from where it stands.
+    pub fn new(views: &'a [Maze], joystick: Joystick<'a>, opts: &GameOpts) -> Engine<'a> {
+        let maze = joystick.maze;
+        let mut entities = Entities::default();
+        let mut markers = Markers::default();
+        let mut course = Course::random(maze, opts.checkpoints);
+        for p in course.checkpoints() {
+            entities.spawn(
+                Entity::new(Kind::Checkpoint, *p, '◆', Behavior::Idle)
+                    .with_color(color::AnsiValue(3)),
+            );
+        }
+        for e in enemies(maze, opts.enemies) {
+            entities.spawn(e);
+        }
+        // the exits are stops of the course, the last one reached leads out
+        let exits = maze.exits();
+        if opts.all_exits && exits.len() > 1 {
+            for e in exits.iter() {
+                course.add(*e);
+            }
+        }
+        let puzzle = if opts.coop {
+            Puzzle::random(maze)
+        } else {
+            None
+        };
+        let mut partner = maze.joystick();
+        partner.assist = joystick.assist;
+        let mut partner_marker = None;
+        if let Some(p) = &puzzle {
+            partner_marker = Some(markers.add(Marker {
+                pos: partner.pos,
+                glyph: '&',
+                color: None,
+            }));
+            entities.spawn(
+                Entity::new(Kind::Plate, p.plate, '○', Behavior::Idle)
+                    .with_color(color::AnsiValue(6)),
+            );
+            entities.spawn(door(p.door));
+        }
+        entities.sync(&mut markers);
+        let mut treasure = if opts.treasure {
+            Treasure::random(maze)
+        } else {
+            None
+        };
+        if let Some(t) = treasure.as_mut() {
+            t.hint(maze, joystick.pos);
+        }
+        Engine {
+            views,
+            fog: Fog::new(opts.fog, &joystick),
+            replay: Replay::starting_at(&joystick),
+            joystick,
+            partner,
+            partner_marker,
+            puzzle,
+            door_open: false,
+            course,
+            entities,
+            markers,
+            treasure,
+            rotation: Rotation::default(),
+            rotate_every: opts.rotate_every,
+            memory: Memory::default(),
+            returning: None,
+            finished: false,
+        }
+    }
+
+    /// Play a key press, `at` is the time since the start of the recording. Returns what
+    /// changed, in order. Moves are over once the run is won, but on the way back of a
+    /// return trip.
+    pub fn act(&mut self, action: Option<Action>, at: Duration) -> Vec<Event> {
+        let mut events = Vec::new();
+        if self.finished && self.returning.is_none() {
+            return events;
+        }
+        // controls stay relative to the screen when the maze is turned
+        let action = action.map(|a| match a {
+            Action::Move(d) => Action::Move(self.rotation.inverse().direction(&d)),
+            a => a,
+        });
+        // a closed door stops the move before the joystick sees it
+        let door_closed = door_closed(
+            self.puzzle.as_ref(),
+            action,
+            &self.joystick,
+            self.partner.pos,
+        );
+        let stepped = match action {
+            Some(Action::Move(_)) if door_closed => None,
+            // walking the history back would make the return trip free
+            Some(Action::Reset | Action::Undo) if self.returning.is_some() => None,
+            Some(a) => step(a, &mut self.joystick),
+            None => None,
+        };
+        if let Some(step) = stepped {
+            self.replay.record(step, &self.joystick, at);
+            events.push(Event::Stepped(step));
+        }
+        match action {
+            Some(Action::Move(_)) if door_closed => events.push(Event::DoorClosed),
+            Some(Action::Move(_)) if stepped.is_some() => self.moved(&mut events),
+            Some(Action::Move(d)) => {
+                let p = self.joystick.pos;
+                if self.memory.bump(p, d) {
+                    events.push(Event::Bumped(self.turn(p), self.rotation.direction(&d)));
+                } else {
+                    events.push(Event::Blocked);
+                }
+            }
+            Some(Action::Reset | Action::Undo) if self.returning.is_some() => {
+                self.returning = None;
+                self.fog.lift();
+                events.push(Event::ReturnAbandoned);
+                events.push(Event::Redraw);
+            }
+            Some(Action::SwitchPlayer) if self.puzzle.is_some() => {
+                std::mem::swap(&mut self.joystick, &mut self.partner);
+            }
+            _ => (),
+        }
+
+        let maze = self.joystick.maze;
+        if !self.finished && is_won(&self.joystick, &self.course) {
+            self.finished = true;
+            let revealed = self.memory.reveal();
+            if self.fog.lift() || revealed {
+                events.push(Event::Redraw);
+            }
+            events.push(Event::Won);
+        }
+        if let Some(run) = self.returning {
+            if self.joystick.pos == maze.entrance() {
+                self.returning = None;
+                self.fog.lift();
+                events.push(Event::Redraw);
+                events.push(Event::Back(run));
+            }
+        }
+        if let Some(p) = &self.puzzle {
+            let open = p.is_open(&[self.joystick.pos, self.partner.pos]);
+            if open != self.door_open {
+                self.door_open = open;
+                if open {
+                    self.entities.despawn(|e| e.kind == Kind::Door);
+                    events.push(Event::DoorOpened);
+                } else {
+                    self.entities.spawn(door(p.door));
+                    events.push(Event::DoorShut);
+                }
+            }
+        }
+        if let Some(id) = self.partner_marker {
+            self.markers.mv(id, self.partner.pos);
+        }
+        let caught = |entities: &Entities, pos| entities.at(pos).any(|e| e.kind == Kind::Enemy);
+        let mut hit = caught(&self.entities, self.joystick.pos);
+        self.entities.tick(maze, self.joystick.pos);
+        hit = hit || caught(&self.entities, self.joystick.pos);
+        if hit && !self.finished && self.returning.is_none() {
+            self.joystick.reset();
+            self.replay.record(Step::Reset, &self.joystick, at);
+            events.push(Event::Caught);
+        }
+        self.entities.sync(&mut self.markers);
+        self.look(&mut events);
+        events
+    }
+
+    /// what a move that went through changed
+    fn moved(&mut self, events: &mut Vec<Event>) {
+        let maze = self.joystick.maze;
+        let p = self.joystick.pos;
+        let moves = self.joystick.move_count();
+        // 0 never turns it
+        if moves.checked_rem(self.rotate_every) == Some(0) {
+            self.rotation = self.rotation.turn();
+            events.push(Event::Turned);
+        }
+        if self.course.visit(p).is_some() {
+            self.entities
+                .despawn(|e| e.kind == Kind::Checkpoint && e.pos == p);
+            events.push(Event::Reached {
+                exit: maze.is_exit(p),
+                left: self.course.remaining(),
+            });
+        } else if self.joystick.is_exit() && !self.course.is_complete() {
+            events.push(Event::Locked);
+        }
+        let finished = self.finished;
+        if let Some(t) = self.treasure.as_mut().filter(|_| !finished) {
+            if t.dig(p) {
+                events.push(Event::TreasureFound);
+            } else if !t.is_found() && moves.checked_rem(HINT_EVERY) == Some(0) {
+                if let Some(h) = t.hint(maze, p) {
+                    events.push(Event::TreasureHint(h));
+                }
+            }
+        }
+    }
+
+    /// update the cells in sight through the fog
+    fn look(&mut self, events: &mut Vec<Event>) {
+        let radius = match self.returning {
+            Some(_) => RETURN_SIGHT,
+            None if self.finished => 0,
+            None => self.fog.radius(),
+        };
+        if self.fog.look(&self.view(), radius) {
+            events.push(Event::Redraw);
+        }
+    }
+
+    /// Walk back from the exit of the won run to the entrance through the fog, `Back`
+    /// hands the run over again once there.
+    pub fn start_return(&mut self, run: Record) {
+        self.returning = Some(run);
+        self.look(&mut Vec::new());
+    }
+
+    /// the run the return trip started from, ending the trip
+    pub fn take_return(&mut self) -> Option<Record> {
+        self.returning.take()
+    }
+
+    pub fn is_returning(&self) -> bool {
+        self.returning.is_some()
+    }
+
+    /// hide the walls in memory mode, until the run is won
+    pub fn hide_walls(&mut self) {
+        self.memory.hide();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn maze(&self) -> &'a Maze {
+        self.joystick.maze
+    }
+
+    pub fn joystick(&self) -> &Joystick<'a> {
+        &self.joystick
+    }
+
+    pub fn course(&self) -> &Course {
+        &self.course
+    }
+
+    /// checkpoints to visit before the exit ends the run
+    pub fn course_mut(&mut self) -> &mut Course {
+        &mut self.course
+    }
+
+    /// entities ticked on every key press
+    pub fn entities_mut(&mut self) -> &mut Entities {
+        &mut self.entities
+    }
+
+    /// markers drawn over the maze besides the player
+    pub fn markers_mut(&mut self) -> &mut Markers {
+        &mut self.markers
+    }
+
+    pub fn puzzle(&self) -> Option<&Puzzle> {
+        self.puzzle.as_ref()
+    }
+
+    pub fn treasure(&self) -> Option<&Treasure> {
+        self.treasure.as_ref()
+    }
+
+    /// recording of the moves, the catches of enemies included
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// the maze as turned on screen
+    pub fn view_maze(&self) -> &'a Maze {
+        &self.views[self.rotation.quarter_turns() as usize]
+    }
+
+    /// the player as seen on the turned maze
+    pub fn view(&self) -> Joystick<'a> {
+        self.rotation.joystick(self.view_maze(), &self.joystick)
+    }
+
+    /// the maze position as seen on the turned maze
+    pub fn turn(&self, p: Position) -> Position {
+        let maze = self.joystick.maze;
+        self.rotation.position(p, maze.width, maze.height)
+    }
+
+    /// the walls as seen on screen, through the fog and none once hidden, `ui` is the one
+    /// of `view_maze`
+    pub fn board(&self, ui: &MazeUI) -> Vec<Vec<char>> {
+        let overlays: [&dyn Overlay; 2] = [&self.fog, &self.memory];
+        overlays
+            .iter()
+            .fold(ui.draw(), |board, o| o.apply(ui, board))
+    }
+
+    /// hidden walls bumped into, as seen on screen
+    pub fn bumps(&self) -> Vec<(Position, Direction)> {
+        self.memory
+            .bumps()
+            .iter()
+            .map(|(p, d)| (self.turn(*p), self.rotation.direction(d)))
+            .collect()
+    }
+
+    /// the markers as seen on screen
+    pub fn turned_markers(&self) -> Markers {
+        let mut turned = Markers::default();
+        for m in self.markers.iter() {
+            turned.add(Marker {
+                pos: self.turn(m.pos),
+                ..*m
+            });
+        }
+        turned
+    }
+
+    /// cells markers moved away from since the last call, as seen on screen
+    pub fn take_vacated(&mut self) -> Vec<Position> {
+        let vacated = self.markers.take_vacated();
+        vacated.into_iter().map(|p| self.turn(p)).collect()
+    }
+
+    /// the board as seen on screen, `ui` is the one of `view_maze`
+    pub fn frame(&self, ui: &MazeUI, show_path: bool) -> Frame {
+        Frame::capture_on(
+            ui,
+            self.board(ui),
+            &self.view(),
+            show_path,
+            &self.turned_markers(),
+        )
+    }
+}
+
+/// the run is won at an exit once every checkpoint of the course was visited
+pub fn is_won(joystick: &Joystick, course: &Course) -> bool {
+    joystick.is_exit() && course.is_complete()
+}
+
+/// Play the action on the joystick. Returns the step taken for the replay, a move slid by
+/// the assist in the direction it went, and None when nothing moved.
+fn step(action: Action, joystick: &mut Joystick) -> Option<Step> {
+    match action {
+        Action::Move(d) if joystick.mv(&d) => {
+            // the assist may have slid the move to another direction
+            let d = joystick.history.last().and_then(|h| h.1).unwrap_or(d);
+            Some(Step::Move(d))
+        }
+        Action::Reset => {
+            joystick.reset();
+            Some(Step::Reset)
+        }
+        Action::Undo if joystick.undo() => Some(Step::Undo),
+        _ => None,
+    }
+}
+
+/// Enemies wandering from random cells at least `ENEMY_DISTANCE` moves away from the
+/// entrance, fewer when the maze has no room for them.
+fn enemies(maze: &Maze, count: usize) -> Vec<Entity> {
+    let dist = maze.distance_map(maze.entrance());
+    let mut cells: Vec<Position> = (0..dist.len() as u16)
+        .filter(|c| matches!(dist[*c as usize], Some(d) if d >= ENEMY_DISTANCE))
+        .map(|c| maze.cell_to_pos(c))
+        .filter(|p| !maze.is_exit(*p))
+        .collect();
+    cells.shuffle(&mut rand::thread_rng());
+    cells
+        .into_iter()
+        .take(count)
+        .map(|p| Entity::new(Kind::Enemy, p, 'X', Behavior::Wander).with_color(color::AnsiValue(1)))
+        .collect()
+}
+
+/// Check if the closed door of the puzzle stops the action of the player, a move after
+/// the assist slid it. `partner` is where the other player stands.
+fn door_closed(
+    puzzle: Option<&Puzzle>,
+    action: Option<Action>,
+    joystick: &Joystick,
+    partner: Position,
+) -> bool {
+    match (puzzle, action) {
+        (Some(p), Some(Action::Move(d))) => {
+            let d = joystick.resolve(&d).unwrap_or(d);
+            p.blocks_move(joystick.maze, joystick.pos, &d, &[partner])
+        }
+        _ => false,
+    }
+}
+
+/// closed door of a co-op puzzle
+fn door(pos: Position) -> Entity {
+    Entity::new(Kind::Door, pos, '▒', Behavior::Idle).with_color(color::AnsiValue(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Direction::{Down, Left, Right, Up};
+    use crate::maze::Opts;
+
+    #[test]
+    fn test_enemies() {
+        let maze = Maze::generate(8, 8, &Opts::default()).unwrap();
+        let dist = maze.distance_map(maze.entrance());
+        let spawned = enemies(&maze, 5);
+        assert_eq!(spawned.len(), 5);
+        for e in spawned.iter() {
+            assert_eq!(e.kind, Kind::Enemy);
+            assert!(dist[maze.pos_to_cell(e.pos) as usize].unwrap() >= ENEMY_DISTANCE);
+            assert!(!maze.is_exit(e.pos));
+        }
+
+        // fewer when the maze has no room, only the dead end past the exit is far enough
+        let tiny = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        assert_eq!(enemies(&tiny, 3).len(), 2);
+    }
+
+    #[test]
+    fn test_act() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let at = Duration::default();
+        let opts = GameOpts {
+            rotate_every: 2,
+            ..Default::default()
+        };
+        let views = views(&m, &opts);
+        let mut e = Engine::new(&views, m.joystick(), &opts);
+        let mv = |d| Some(Action::Move(d));
+        assert_eq!(e.act(mv(Right), at), [Event::Stepped(Step::Move(Right))]);
+        assert_eq!(
+            e.act(mv(Right), at),
+            [Event::Stepped(Step::Move(Right)), Event::Turned]
+        );
+        // the controls turn with the maze, down on screen is right on the maze
+        assert_eq!(e.act(mv(Down), at), [Event::Blocked]);
+        assert_eq!(
+            e.act(mv(Left), at),
+            [Event::Stepped(Step::Move(Down)), Event::Won]
+        );
+        assert!(e.is_finished() && e.act(mv(Up), at).is_empty());
+        assert_eq!(e.replay().len(), 3);
+
+        // memory mode, a hidden wall is told once
+        let mut e = Engine::new(&views[..1], m.joystick(), &GameOpts::default());
+        e.hide_walls();
+        let bumped = Event::Bumped(Position { x: 0, y: 0 }, Down);
+        assert_eq!(e.act(mv(Down), at), [bumped]);
+        assert_eq!(e.act(mv(Down), at), [Event::Blocked]);
+
+        // an enemy sends the player back, the catch is in the replay
+        let mut e = Engine::new(&views[..1], m.joystick(), &GameOpts::default());
+        e.entities_mut().spawn(Entity::new(
+            Kind::Enemy,
+            Position { x: 1, y: 0 },
+            'X',
+            Behavior::Idle,
+        ));
+        assert_eq!(
+            e.act(mv(Right), at),
+            [Event::Stepped(Step::Move(Right)), Event::Caught]
+        );
+        assert_eq!(e.joystick().pos, m.entrance());
+        assert_eq!(e.replay().steps(), [Step::Move(Right), Step::Reset]);
+
+        // the return trip goes on after the exit, through the fog
+        let mut e = Engine::new(&views[..1], m.joystick(), &GameOpts::default());
+        for d in [Right, Right, Down].iter() {
+            e.act(mv(*d), at);
+        }
+        let run = Record::new(3, at);
+        e.start_return(run);
+        for d in [Up, Left].iter() {
+            e.act(mv(*d), at);
+        }
+        assert!(e.is_returning());
+        assert_eq!(e.act(mv(Left), at).last(), Some(&Event::Back(run)));
+        assert!(!e.is_returning() && e.act(mv(Right), at).is_empty());
+    }
+}
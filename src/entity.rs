@@ -0,0 +1,212 @@
+use rand::seq::IteratorRandom;
+use termion::color::AnsiValue;
+
+use crate::maze::{Direction, Marker, Markers, Maze, Position};
+
+/// What an entity represents in the game.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Checkpoint,
+    Coin,
+    Door,
+    Enemy,
+    Ghost,
+    Plate,
+    Wall,
+}
+
+/// How an entity acts on every tick of the game loop.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Behavior {
+    /// never moves
+    Idle,
+    /// moves to a random open neighbour
+    Wander,
+    /// steps toward the player when an open move gets it closer
+    Chase,
+    /// walks the moves in order and starts over when done or blocked
+    Patrol(Vec<Direction>, usize),
+}
+
+/// Dynamic object living in the maze.
+#[derive(Clone, Debug)]
+pub struct Entity {
+    pub kind: Kind,
+    pub pos: Position,
+    pub glyph: char,
+    pub color: Option<AnsiValue>,
+    pub behavior: Behavior,
+    marker: Option<usize>,
+}
+
+impl Entity {
+    pub fn new(kind: Kind, pos: Position, glyph: char, behavior: Behavior) -> Entity {
+        Entity {
+            kind,
+            pos,
+            glyph,
+            color: None,
+            behavior,
+            marker: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: AnsiValue) -> Entity {
+        self.color = Some(color);
+        self
+    }
+
+    /// advance the entity one tick
+    fn tick(&mut self, maze: &Maze, player: Position) {
+        let next = match &mut self.behavior {
+            Behavior::Idle => None,
+            Behavior::Wander => maze
+                .movements(self.pos)
+                .into_iter()
+                .choose(&mut rand::thread_rng()),
+            Behavior::Chase => {
+                let dist = |p: Position| {
+                    (p.x as i32 - player.x as i32).abs() + (p.y as i32 - player.y as i32).abs()
+                };
+                let pos = self.pos;
+                maze.movements(pos)
+                    .into_iter()
+                    .filter(|d| matches!(maze.move_pos(pos, d), Some(n) if dist(n) < dist(pos)))
+                    .min_by_key(|d| *d as u8)
+            }
+            Behavior::Patrol(moves, i) => {
+                if moves.is_empty() {
+                    None
+                } else {
+                    let d = moves[*i % moves.len()];
+                    *i = (*i + 1) % moves.len();
+                    if maze.move_pos(self.pos, &d).is_none() {
+                        *i = 0;
+                    }
+                    Some(d)
+                }
+            }
+        };
+        if let Some(p) = next.and_then(|d| maze.move_pos(self.pos, &d)) {
+            self.pos = p;
+        }
+    }
+}
+
+/// All the entities of a game, updated once per tick of the game loop.
+#[derive(Default, Debug)]
+pub struct Entities {
+    entities: Vec<Entity>,
+    /// markers of despawned entities still to be removed
+    removed: Vec<usize>,
+}
+
+impl Entities {
+    pub fn spawn(&mut self, e: Entity) {
+        self.entities.push(e);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// entities at the given maze position
+    pub fn at(&self, pos: Position) -> impl Iterator<Item = &Entity> {
+        self.entities.iter().filter(move |e| e.pos == pos)
+    }
+
+    /// Remove and return the entities matching the predicate, e.g. coins under the player.
+    pub fn despawn<F>(&mut self, f: F) -> Vec<Entity>
+    where
+        F: Fn(&Entity) -> bool,
+    {
+        let (gone, kept) = self.entities.drain(..).partition(|e| f(e));
+        self.entities = kept;
+        self.removed
+            .extend(gone.iter().filter_map(|e: &Entity| e.marker));
+        gone
+    }
+
+    /// advance every entity one tick
+    pub fn tick(&mut self, maze: &Maze, player: Position) {
+        for e in self.entities.iter_mut() {
+            e.tick(maze, player);
+        }
+    }
+
+    /// bring the markers in line with the entities so renderers can draw them
+    pub fn sync(&mut self, markers: &mut Markers) {
+        for id in self.removed.drain(..) {
+            markers.remove(id);
+        }
+        for e in self.entities.iter_mut() {
+            match e.marker {
+                Some(id) => markers.mv(id, e.pos),
+                None => {
+                    e.marker = Some(markers.add(Marker {
+                        pos: e.pos,
+                        glyph: e.glyph,
+                        color: e.color,
+                    }))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Direction::{Left, Right};
+
+    #[test]
+    fn test_tick() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut entities = Entities::default();
+        let mut markers = Markers::default();
+        entities.spawn(Entity::new(
+            Kind::Enemy,
+            Position { x: 0, y: 1 },
+            'E',
+            Behavior::Chase,
+        ));
+        entities.spawn(Entity::new(
+            Kind::Ghost,
+            Position { x: 0, y: 0 },
+            'G',
+            Behavior::Patrol(vec![Right, Right, Left, Left], 0),
+        ));
+        entities.spawn(Entity::new(
+            Kind::Coin,
+            Position { x: 2, y: 0 },
+            '$',
+            Behavior::Idle,
+        ));
+        entities.sync(&mut markers);
+        assert_eq!(markers.iter().count(), 3);
+
+        let player = Position { x: 2, y: 1 };
+        entities.tick(&m, player);
+        entities.tick(&m, player);
+        entities.sync(&mut markers);
+        let positions: Vec<Position> = entities.iter().map(|e| e.pos).collect();
+        assert_eq!(
+            positions,
+            vec![
+                Position { x: 2, y: 1 },
+                Position { x: 2, y: 0 },
+                Position { x: 2, y: 0 },
+            ]
+        );
+
+        let coins = entities.despawn(|e| e.kind == Kind::Coin);
+        assert_eq!(coins.len(), 1);
+        entities.sync(&mut markers);
+        assert_eq!(markers.iter().count(), 2);
+        assert_eq!(entities.at(Position { x: 2, y: 1 }).count(), 1);
+    }
+}
@@ -0,0 +1,900 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::maze::Direction::{Down, Right};
+use crate::maze::{Locate, Maze, Position};
+
+/// Side of a cell in SVG user units.
+const SVG_CELL: u32 = 20;
+/// Blank space around the maze in SVG user units.
+const SVG_MARGIN: u32 = 10;
+/// Blank space around a labeled maze, room for the labels and the caption.
+const SVG_LABEL_MARGIN: u32 = 30;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("invalid export format")]
+    FormatParseError,
+    #[error("invalid tileset, expected lines, hedge, dungeon, circuit or heavy")]
+    TilesetParseError,
+    #[error("invalid color, expected #rrggbb")]
+    ColorParseError,
+    #[error("invalid page grid, expected COLUMNSxROWS")]
+    PagesParseError,
+    #[error("labels can't be drawn on {0} exports, only on square text and svg mazes")]
+    LabelsUnsupported(&'static str),
+    #[error("poster pages are split from png images, export with --format png")]
+    PagesUnsupported,
+    #[error(transparent)]
+    Png(#[from] png::EncodingError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Supported export formats.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Format {
+    Text,
+    /// vector image for printing
+    Svg,
+    /// bitmap image, see `Raster`
+    Png,
+}
+
+impl FromStr for Format {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" | "txt" => Ok(Format::Text),
+            "svg" => Ok(Format::Svg),
+            "png" => Ok(Format::Png),
+            _ => Err(ExportError::FormatParseError),
+        }
+    }
+}
+
+/// Color of a bitmap pixel.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(ExportError::ColorParseError);
+        }
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| ExportError::ColorParseError)
+        };
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// Look of bitmap exports.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Raster {
+    /// side of a cell in pixels, from one wall to the next
+    pub cell: u32,
+    /// thickness of the walls in pixels
+    pub wall: u32,
+    pub wall_color: Rgb,
+    pub background: Rgb,
+    pub path_color: Rgb,
+}
+
+impl Default for Raster {
+    fn default() -> Self {
+        Raster {
+            cell: 16,
+            wall: 2,
+            wall_color: Rgb(0, 0, 0),
+            background: Rgb(255, 255, 255),
+            path_color: Rgb(255, 0, 0),
+        }
+    }
+}
+
+/// RGB pixels of an image, row by row.
+struct Bitmap {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    fn new(width: u32, height: u32, color: Rgb) -> Bitmap {
+        let pixels = [color.0, color.1, color.2].repeat((width * height) as usize);
+        Bitmap {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// fill the rectangle, clipped to the image
+    fn fill(&mut self, x: u32, y: u32, w: u32, h: u32, color: Rgb) {
+        for py in y..(y + h).min(self.height) {
+            for px in x..(x + w).min(self.width) {
+                let i = ((py * self.width + px) * 3) as usize;
+                self.pixels[i..i + 3].copy_from_slice(&[color.0, color.1, color.2]);
+            }
+        }
+    }
+
+    /// part of the image, padded with the background past its edges
+    fn crop(&self, x: u32, y: u32, w: u32, h: u32, background: Rgb) -> Bitmap {
+        let mut part = Bitmap::new(w, h, background);
+        let cw = w.min(self.width.saturating_sub(x));
+        for py in 0..h.min(self.height.saturating_sub(y)) {
+            let from = (((y + py) * self.width + x) * 3) as usize;
+            let to = (py * w * 3) as usize;
+            let len = (cw * 3) as usize;
+            part.pixels[to..to + len].copy_from_slice(&self.pixels[from..from + len]);
+        }
+        part
+    }
+
+    fn encode<W: Write>(&self, out: W) -> Result<(), ExportError> {
+        let mut encoder = png::Encoder::new(out, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+}
+
+/// Grid of pages a poster is printed on.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Pages {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl FromStr for Pages {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s
+            .split('x')
+            .map(|n| n.parse::<u32>().ok().filter(|n| *n > 0));
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Some(columns)), Some(Some(rows)), None) => Ok(Pages { columns, rows }),
+            _ => Err(ExportError::PagesParseError),
+        }
+    }
+}
+
+/// Size of the pages along one side of the poster and where each one starts, so they
+/// cover `total` pixels sharing `overlap` with the next one.
+fn page_starts(total: u32, count: u32, overlap: u32) -> (u32, Vec<u32>) {
+    // rounded up so the pages cover everything
+    let size = (total + (count - 1) * overlap).saturating_sub(1) / count + 1;
+    let overlap = overlap.min(size / 2);
+    let starts = (0..count).map(|i| i * (size - overlap)).collect();
+    (size, starts)
+}
+
+/// Look of the walls: every corner, straight and junction piece is swapped for a tile.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Tileset {
+    /// thin box drawing lines as in game
+    Lines,
+    Hedge,
+    Dungeon,
+    /// double lines like traces on a board
+    Circuit,
+    /// bold lines, easier to make out
+    Heavy,
+}
+
+impl FromStr for Tileset {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lines" => Ok(Tileset::Lines),
+            "hedge" => Ok(Tileset::Hedge),
+            "dungeon" => Ok(Tileset::Dungeon),
+            "circuit" => Ok(Tileset::Circuit),
+            "heavy" => Ok(Tileset::Heavy),
+            _ => Err(ExportError::TilesetParseError),
+        }
+    }
+}
+
+impl Tileset {
+    /// tile drawn for the wall piece, anything else is kept as is
+    pub fn tile(&self, c: char) -> char {
+        let wall = "┌┐└┘─│├┤┬┴┼╴╵╶╷".contains(c);
+        match self {
+            Tileset::Lines => c,
+            Tileset::Hedge if wall => '♣',
+            Tileset::Dungeon if wall => '#',
+            Tileset::Circuit => match c {
+                '┌' => '╔',
+                '┐' => '╗',
+                '└' => '╚',
+                '┘' => '╝',
+                '─' | '╴' | '╶' => '═',
+                '│' | '╵' | '╷' => '║',
+                '├' => '╠',
+                '┤' => '╣',
+                '┬' => '╦',
+                '┴' => '╩',
+                '┼' => '╬',
+                c => c,
+            },
+            Tileset::Heavy => match c {
+                '┌' => '┏',
+                '┐' => '┓',
+                '└' => '┗',
+                '┘' => '┛',
+                '─' => '━',
+                '│' => '┃',
+                '├' => '┣',
+                '┤' => '┫',
+                '┬' => '┳',
+                '┴' => '┻',
+                '┼' => '╋',
+                '╴' => '╸',
+                '╵' => '╹',
+                '╶' => '╺',
+                '╷' => '╻',
+                c => c,
+            },
+            _ => c,
+        }
+    }
+
+    /// Walls of images drawn with the tileset: their color, their thickness as a multiple
+    /// of the thin line and whether they are two parallel lines.
+    fn look(&self) -> (Rgb, u32, bool) {
+        match self {
+            Tileset::Lines => (Rgb(0, 0, 0), 1, false),
+            Tileset::Hedge => (Rgb(46, 125, 50), 3, false),
+            Tileset::Dungeon => (Rgb(96, 96, 96), 4, false),
+            Tileset::Circuit => (Rgb(0, 0, 0), 3, true),
+            Tileset::Heavy => (Rgb(0, 0, 0), 2, false),
+        }
+    }
+
+    /// wall color of images unless another one is asked for
+    pub fn color(&self) -> Rgb {
+        self.look().0
+    }
+}
+
+/// What to draw over the walls of an exported maze.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Marks {
+    /// the shortest way out
+    pub solution: bool,
+    /// column letters and row numbers around the maze, and a caption naming the cells
+    /// of the entrance and the exit
+    pub labels: bool,
+}
+
+/// Write the maze in the given format with the walls drawn from the tileset and the
+/// marks over it. Images ignore the colors.
+pub fn export<W: Write>(
+    out: &mut W,
+    maze: &Maze,
+    format: Format,
+    tileset: Tileset,
+    color: bool,
+    marks: Marks,
+    raster: &Raster,
+) -> Result<(), ExportError> {
+    let path = if marks.solution {
+        maze.solution().unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    match format {
+        Format::Text => {
+            let mut text: String = maze
+                .ui()
+                .render_path(&path, color)
+                .chars()
+                .map(|c| tileset.tile(c))
+                .collect();
+            if marks.labels {
+                text = label_text(maze, &text);
+            }
+            out.write_all(text.as_bytes())?
+        }
+        Format::Svg => out.write_all(svg(maze, &path, marks.labels, tileset).as_bytes())?,
+        Format::Png if marks.labels => return Err(ExportError::LabelsUnsupported("png")),
+        Format::Png => png(out, maze, &path, raster, tileset)?,
+    }
+    Ok(())
+}
+
+/// Letters of a column on a labeled maze, A to Z then AA, AB and on.
+pub fn column_name(x: u16) -> String {
+    let mut letters = Vec::new();
+    let mut n = x as u32 + 1;
+    while n > 0 {
+        n -= 1;
+        letters.push((b'A' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+/// Name of a cell on a labeled maze, its column letters then its row number from 1,
+/// like B3.
+pub fn cell_name(p: Position) -> String {
+    format!("{}{}", column_name(p.x), p.y + 1)
+}
+
+/// "start at A1, exit at T18"
+pub fn caption(maze: &Maze) -> String {
+    format!(
+        "start at {}, exit at {}",
+        cell_name(maze.entrance()),
+        cell_name(maze.exit())
+    )
+}
+
+/// Put column letters over the rendered maze, row numbers left of it and the caption
+/// under it.
+fn label_text(maze: &Maze, text: &str) -> String {
+    let ui = maze.ui();
+    // room for the row numbers and a space
+    let indent = maze.height.to_string().len() + 1;
+    let mut header = Vec::new();
+    for x in 0..maze.width {
+        let name: Vec<char> = column_name(x).chars().collect();
+        let center = indent + ui.locate(&Position { x, y: 0 }).x as usize;
+        let start = center - (name.len() - 1) / 2;
+        if header.len() < start + name.len() {
+            header.resize(start + name.len(), ' ');
+        }
+        header[start..start + name.len()].copy_from_slice(&name);
+    }
+    let mut out: String = header.into_iter().collect();
+    out.push('\n');
+    for (i, line) in text.lines().enumerate() {
+        let row = (0..maze.height).find(|&y| ui.locate(&Position { x: 0, y }).y as usize == i);
+        match row {
+            Some(y) => out.push_str(&format!("{:>1$} ", y + 1, indent - 1)),
+            None => out.push_str(&" ".repeat(indent)),
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(&caption(maze));
+    out.push('\n');
+    out
+}
+
+/// Write the maze as a PNG image with the path drawn from cell center to cell center.
+pub fn png<W: Write>(
+    out: W,
+    maze: &Maze,
+    path: &[Position],
+    raster: &Raster,
+    tileset: Tileset,
+) -> Result<(), ExportError> {
+    rasterize(maze, path, &[], raster, tileset).encode(out)
+}
+
+/// Write the maze as a PNG image with every cell shaded from the background to the path
+/// color by its heat, one value in [0, 1] per cell.
+pub fn heat_png<W: Write>(
+    out: W,
+    maze: &Maze,
+    heat: &[f32],
+    raster: &Raster,
+) -> Result<(), ExportError> {
+    rasterize(maze, &[], heat, raster, Tileset::Lines).encode(out)
+}
+
+/// Split the PNG image of the maze into pages to print and assemble into a poster. Pages
+/// share `overlap` pixels with their neighbours and crosses drawn in the middle of the
+/// shared strips line up when the pages are laid over each other. Returns the PNG data
+/// of every page, row by row.
+pub fn poster(
+    maze: &Maze,
+    path: &[Position],
+    raster: &Raster,
+    tileset: Tileset,
+    pages: Pages,
+    overlap: u32,
+) -> Result<Vec<Vec<u8>>, ExportError> {
+    let mut bitmap = rasterize(maze, path, &[], raster, tileset);
+    let (width, columns) = page_starts(bitmap.width, pages.columns, overlap);
+    let (height, rows) = page_starts(bitmap.height, pages.rows, overlap);
+
+    // registration marks where the seams cross each other and the middle of the pages
+    let seams = |starts: &[u32], size: u32| -> Vec<u32> {
+        starts
+            .windows(2)
+            .map(|w| (w[0] + size + w[1]) / 2)
+            .collect()
+    };
+    let (seams_x, seams_y) = (seams(&columns, width), seams(&rows, height));
+    let middles =
+        |starts: &[u32], size: u32| -> Vec<u32> { starts.iter().map(|s| s + size / 2).collect() };
+    let mut marks = Vec::new();
+    for x in seams_x.iter() {
+        for y in seams_y.iter().chain(middles(&rows, height).iter()) {
+            marks.push((*x, *y));
+        }
+    }
+    for y in seams_y.iter() {
+        for x in middles(&columns, width) {
+            marks.push((x, *y));
+        }
+    }
+    let arm = (overlap / 4).max(2);
+    let thickness = (raster.wall / 2).max(1);
+    for (x, y) in marks {
+        let (left, top) = (x.saturating_sub(arm), y.saturating_sub(arm));
+        bitmap.fill(left, y, 2 * arm + 1, thickness, raster.path_color);
+        bitmap.fill(x, top, thickness, 2 * arm + 1, raster.path_color);
+    }
+
+    let mut out = Vec::new();
+    for y in rows.iter() {
+        for x in columns.iter() {
+            let mut page = Vec::new();
+            bitmap
+                .crop(*x, *y, width, height, raster.background)
+                .encode(&mut page)?;
+            out.push(page);
+        }
+    }
+    Ok(out)
+}
+
+/// Draw the maze with the cells shaded by their heat, if any, and the path over them. The
+/// tileset makes the walls thicker than `raster.wall` or hollow, the color is the one of
+/// the raster.
+fn rasterize(
+    maze: &Maze,
+    path: &[Position],
+    heat: &[f32],
+    raster: &Raster,
+    tileset: Tileset,
+) -> Bitmap {
+    let (_, weight, double) = tileset.look();
+    let (cell, wall) = (raster.cell.max(1), raster.wall * weight);
+    let (w, h) = (maze.width as u32, maze.height as u32);
+    let mut bitmap = Bitmap::new(w * cell + wall, h * cell + wall, raster.background);
+
+    let (from, to) = (raster.background, raster.path_color);
+    let mix = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    for (i, t) in heat.iter().enumerate().filter(|(_, t)| **t > 0.0) {
+        let (x, y) = (i as u32 % w, i as u32 / w);
+        let t = t.min(1.0);
+        let color = Rgb(
+            mix(from.0, to.0, t),
+            mix(from.1, to.1, t),
+            mix(from.2, to.2, t),
+        );
+        bitmap.fill(x * cell, y * cell, cell + wall, cell + wall, color);
+    }
+
+    let line = (cell / 4).max(1);
+    let center = |p: &Position| {
+        let c = (cell + wall - line) / 2;
+        (p.x as u32 * cell + c, p.y as u32 * cell + c)
+    };
+    for pair in path.windows(2) {
+        let ((x0, y0), (x1, y1)) = (center(&pair[0]), center(&pair[1]));
+        let (x, y) = (x0.min(x1), y0.min(y1));
+        let (dx, dy) = (x0.max(x1) - x, y0.max(y1) - y);
+        bitmap.fill(x, y, dx + line, dy + line, raster.path_color);
+    }
+
+    let color = raster.wall_color;
+    // walls as x, y, width and height
+    let mut walls = vec![
+        (0, 0, bitmap.width, wall),
+        (0, h * cell, bitmap.width, wall),
+        (0, 0, wall, bitmap.height),
+        (w * cell, 0, wall, bitmap.height),
+    ];
+    for y in 0..h {
+        for x in 0..w {
+            let p = Position {
+                x: x as u16,
+                y: y as u16,
+            };
+            if maze.is_blocked(p) {
+                bitmap.fill(x * cell, y * cell, cell + wall, cell + wall, color);
+            }
+            if x + 1 < w && maze.move_pos(p, &Right).is_none() {
+                walls.push(((x + 1) * cell, y * cell, wall, cell + wall));
+            }
+            if y + 1 < h && maze.move_pos(p, &Down).is_none() {
+                walls.push((x * cell, (y + 1) * cell, cell + wall, wall));
+            }
+        }
+    }
+    for (x, y, width, height) in walls.iter() {
+        bitmap.fill(*x, *y, *width, *height, color);
+    }
+    if double {
+        // the middle third of the walls is cleared back, the channels join at the corners
+        let inner = (wall / 3).max(1);
+        let d = (wall - inner) / 2;
+        for (x, y, width, height) in walls {
+            let (width, height) = if width == wall {
+                (inner, height - 2 * d)
+            } else {
+                (width - 2 * d, inner)
+            };
+            bitmap.fill(x + d, y + d, width, height, raster.background);
+        }
+    }
+    bitmap
+}
+
+/// Draw the maze as an SVG image, walls are lines in the look of the tileset and the path
+/// is a line through the centers of its cells. Labels go in a wider margin, see `Marks`.
+pub fn svg(maze: &Maze, path: &[Position], labels: bool, tileset: Tileset) -> String {
+    svg_shaded(maze, path, labels, &[], tileset)
+}
+
+/// Draw the maze as an SVG image with every cell shaded red by its heat, one value in
+/// [0, 1] per cell.
+pub fn heat_svg(maze: &Maze, heat: &[f32]) -> String {
+    svg_shaded(maze, &[], false, heat, Tileset::Lines)
+}
+
+fn svg_shaded(
+    maze: &Maze,
+    path: &[Position],
+    labels: bool,
+    heat: &[f32],
+    tileset: Tileset,
+) -> String {
+    let (color, weight, double) = tileset.look();
+    let (w, h) = (maze.width as u32, maze.height as u32);
+    let margin = if labels { SVG_LABEL_MARGIN } else { SVG_MARGIN };
+    let corner = |x: u32, y: u32| (margin + x * SVG_CELL, margin + y * SVG_CELL);
+    let mut walls = Vec::new();
+    // cells left out by a mask
+    let mut blocks = Vec::new();
+    let (x0, y0) = corner(0, 0);
+    let (x1, y1) = corner(w, h);
+    walls.push(format!("M{},{}H{}V{}H{}Z", x0, y0, x1, y1, x0));
+    for y in 0..h {
+        for x in 0..w {
+            let p = Position {
+                x: x as u16,
+                y: y as u16,
+            };
+            if maze.is_blocked(p) {
+                let (bx, by) = corner(x, y);
+                blocks.push(format!(
+                    "M{},{}h{}v{}h-{}Z",
+                    bx, by, SVG_CELL, SVG_CELL, SVG_CELL
+                ));
+            }
+            if x + 1 < w && maze.move_pos(p, &Right).is_none() {
+                let (wx, wy) = corner(x + 1, y);
+                walls.push(format!("M{},{}v{}", wx, wy, SVG_CELL));
+            }
+            if y + 1 < h && maze.move_pos(p, &Down).is_none() {
+                let (wx, wy) = corner(x, y + 1);
+                walls.push(format!("M{},{}h{}", wx, wy, SVG_CELL));
+            }
+        }
+    }
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        x1 + margin,
+        y1 + margin
+    );
+    out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+    for (i, t) in heat.iter().enumerate().filter(|(_, t)| **t > 0.0) {
+        let (x, y) = corner(i as u32 % w, i as u32 / w);
+        out.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"red\" fill-opacity=\"{3:.2}\"/>\n",
+            x,
+            y,
+            SVG_CELL,
+            t.min(1.0)
+        ));
+    }
+    if !blocks.is_empty() {
+        out.push_str(&format!(
+            "<path d=\"{}\" fill=\"{}\"/>\n",
+            blocks.join(""),
+            color
+        ));
+    }
+    let cap = match tileset {
+        Tileset::Hedge => "round",
+        _ => "square",
+    };
+    let walls = walls.join("");
+    out.push_str(&format!(
+        "<path d=\"{}\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"{}\" fill=\"none\"/>\n",
+        walls,
+        color,
+        2 * weight,
+        cap
+    ));
+    if double {
+        // a thin white line down the middle splits the walls in two
+        out.push_str(&format!(
+            "<path d=\"{}\" stroke=\"white\" stroke-width=\"{}\" stroke-linecap=\"{}\" fill=\"none\"/>\n",
+            walls,
+            2 * weight / 3,
+            cap
+        ));
+    }
+    if !path.is_empty() {
+        let points: Vec<String> = path
+            .iter()
+            .map(|p| {
+                let (x, y) = corner(p.x as u32, p.y as u32);
+                format!("{},{}", x + SVG_CELL / 2, y + SVG_CELL / 2)
+            })
+            .collect();
+        out.push_str(&format!(
+            "<polyline points=\"{}\" stroke=\"red\" stroke-width=\"4\" fill=\"none\"/>\n",
+            points.join(" ")
+        ));
+    }
+    if labels {
+        let text = |x: u32, y: u32, anchor: &str, s: &str| {
+            format!(
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"{}\" font-family=\"sans-serif\" font-size=\"12\">{}</text>\n",
+                x, y, anchor, s
+            )
+        };
+        for x in 0..maze.width {
+            let (cx, _) = corner(x as u32, 0);
+            out.push_str(&text(cx + SVG_CELL / 2, y0 - 8, "middle", &column_name(x)));
+        }
+        for y in 0..maze.height {
+            let (_, cy) = corner(0, y as u32);
+            out.push_str(&text(
+                x0 - 6,
+                cy + SVG_CELL / 2 + 4,
+                "end",
+                &(y + 1).to_string(),
+            ));
+        }
+        out.push_str(&text(x0, y1 + 20, "start", &caption(maze)));
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tileset() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut out = Vec::new();
+        export(
+            &mut out,
+            &m,
+            Format::Text,
+            "circuit".parse().unwrap(),
+            false,
+            Marks::default(),
+            &Raster::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "╔═══════════╗\n\
+             ║           ║\n\
+             ╠════════   ║\n\
+             ║       Exit║\n\
+             ╚═══════════╝\n"
+        );
+        // the solution without colors leaves the exit label readable
+        let mut out = Vec::new();
+        let marks = Marks {
+            solution: true,
+            ..Marks::default()
+        };
+        export(
+            &mut out,
+            &m,
+            Format::Text,
+            Tileset::Lines,
+            false,
+            marks,
+            &Raster::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "┌───────────┐\n\
+             │ ········· │\n\
+             ├───────╴ · │\n\
+             │       Exit│\n\
+             └───────────┘\n"
+        );
+        assert_eq!(Tileset::Hedge.tile('┼'), '♣');
+        assert_eq!(Tileset::Dungeon.tile('E'), 'E');
+        assert!("brick".parse::<Tileset>().is_err());
+    }
+
+    #[test]
+    fn test_svg() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut out = Vec::new();
+        export(
+            &mut out,
+            &m,
+            Format::Svg,
+            Tileset::Lines,
+            false,
+            Marks {
+                solution: true,
+                labels: false,
+            },
+            &Raster::default(),
+        )
+        .unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"80\" height=\"60\"")
+        );
+        assert!(svg.contains("d=\"M10,10H70V50H10ZM10,30h20M30,30h20\""));
+        assert!(svg.contains("points=\"20,20 40,20 60,20 60,40\""));
+
+        let svg = super::svg(&m, &[], false, Tileset::Hedge);
+        assert!(svg.contains("stroke=\"#2e7d32\" stroke-width=\"6\" stroke-linecap=\"round\""));
+        let svg = super::svg(&m, &[], false, Tileset::Circuit);
+        assert_eq!(
+            svg.matches("d=\"M10,10H70V50H10ZM10,30h20M30,30h20\"")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(27), "AB");
+        assert_eq!(cell_name(Position { x: 19, y: 17 }), "T18");
+        assert_eq!(caption(&m), "start at A1, exit at C2");
+
+        let mut out = Vec::new();
+        let marks = Marks {
+            solution: false,
+            labels: true,
+        };
+        export(
+            &mut out,
+            &m,
+            Format::Text,
+            Tileset::Lines,
+            false,
+            marks,
+            &Raster::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "    A   B   C\n\
+             \x20 ┌───────────┐\n\
+             1 │           │\n\
+             \x20 ├───────╴   │\n\
+             2 │       Exit│\n\
+             \x20 └───────────┘\n\
+             start at A1, exit at C2\n"
+        );
+        let svg = svg(&m, &[], true, Tileset::Lines);
+        assert!(svg.contains(">B</text>"));
+        assert!(svg.contains(">start at A1, exit at C2</text>"));
+        let mut png = Vec::new();
+        assert!(export(
+            &mut png,
+            &m,
+            Format::Png,
+            Tileset::Lines,
+            false,
+            marks,
+            &Raster::default()
+        )
+        .is_err());
+    }
+
+    fn pixel(b: &Bitmap, x: u32, y: u32) -> Rgb {
+        let i = ((y * b.width + x) * 3) as usize;
+        Rgb(b.pixels[i], b.pixels[i + 1], b.pixels[i + 2])
+    }
+
+    #[test]
+    fn test_png() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let raster = Raster {
+            cell: 4,
+            wall: 1,
+            wall_color: "#000000".parse().unwrap(),
+            background: "ffffff".parse().unwrap(),
+            path_color: Rgb(255, 0, 0),
+        };
+        let bitmap = rasterize(&m, &m.solution().unwrap(), &[], &raster, Tileset::Lines);
+        assert_eq!((bitmap.width, bitmap.height), (13, 9));
+        assert_eq!(pixel(&bitmap, 0, 0), raster.wall_color);
+        // the wall between the rows stops before the last cell
+        assert_eq!(pixel(&bitmap, 6, 4), raster.wall_color);
+        assert_eq!(pixel(&bitmap, 10, 4), raster.path_color);
+        assert_eq!(pixel(&bitmap, 1, 6), raster.background);
+        assert!("#12345".parse::<Rgb>().is_err());
+
+        let mut out = Vec::new();
+        png(&mut out, &m, &[], &raster, Tileset::Lines).unwrap();
+        assert!(out.starts_with(b"\x89PNG"));
+
+        // walls three times as thick with their middle cleared
+        let bitmap = rasterize(&m, &[], &[], &raster, Tileset::Circuit);
+        assert_eq!((bitmap.width, bitmap.height), (15, 11));
+        assert_eq!(pixel(&bitmap, 6, 0), raster.wall_color);
+        assert_eq!(pixel(&bitmap, 6, 1), raster.background);
+        assert_eq!(pixel(&bitmap, 6, 2), raster.wall_color);
+        assert_eq!(pixel(&bitmap, 1, 6), raster.background);
+        assert_eq!(pixel(&bitmap, 0, 6), raster.wall_color);
+        let bitmap = rasterize(&m, &[], &[], &raster, Tileset::Dungeon);
+        assert_eq!(pixel(&bitmap, 6, 3), raster.wall_color);
+    }
+
+    #[test]
+    fn test_poster() {
+        assert_eq!(page_starts(100, 1, 10), (100, vec![0]));
+        // 3 pages of 40 cover 100 pixels with 10 shared between neighbours
+        assert_eq!(page_starts(100, 3, 10), (40, vec![0, 30, 60]));
+        assert_eq!(
+            "3x2".parse::<Pages>().unwrap(),
+            Pages {
+                columns: 3,
+                rows: 2
+            }
+        );
+        assert!("3x0".parse::<Pages>().is_err());
+        assert!("3".parse::<Pages>().is_err());
+
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let bitmap = rasterize(&m, &[], &[], &Raster::default(), Tileset::Lines);
+        let page = bitmap.crop(40, 0, 20, 10, Rgb(1, 2, 3));
+        assert_eq!(pixel(&page, 9, 0), pixel(&bitmap, 49, 0));
+        // past the right edge
+        assert_eq!(pixel(&page, 19, 0), Rgb(1, 2, 3));
+
+        let pages = poster(
+            &m,
+            &[],
+            &Raster::default(),
+            Tileset::Lines,
+            Pages {
+                columns: 2,
+                rows: 2,
+            },
+            8,
+        )
+        .unwrap();
+        assert_eq!(pages.len(), 4);
+        assert!(pages.iter().all(|p| p.starts_with(b"\x89PNG")));
+    }
+}
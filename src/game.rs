@@ -1,26 +1,431 @@
+use std::convert::TryFrom;
 use std::fmt;
-use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 use termion::cursor::Goto;
 use termion::event::Key;
 use termion::{clear, color, cursor, style};
+use thiserror::Error;
 
-use crate::game::GameCommand::{NewGame, Quit};
-use crate::maze::{Difficulty, Direction, Joystick, Locate, Maze, MazeUI, Opts, Position};
+use crate::campaign::{Endless, Level, LevelPack, Progress};
+use crate::capture::{Frame, FrameHook};
+use crate::engine::{self, Engine, Event as EngineEvent};
+use crate::export::Tileset;
+use crate::game::GameCommand::{Load, NewGame, Quit};
+use crate::habits::Habits;
+use crate::heatmap::Heatmap;
+use crate::hooks::{Event, Hooks};
+use crate::keymap::KeyMap;
+use crate::leaderboard::{Entry, Leaderboard, TOP};
+use crate::maze::{
+    CellSize, Difficulty, Direction, Joystick, Locate, Marker, Markers, Maze, MazeSeed, MazeUI,
+    Opts, PackedMaze, PathError, Position,
+};
+use crate::messages::MessageLog;
+use crate::orienteering::Course;
+use crate::palette;
+use crate::pool::MazePool;
+use crate::race::{Race, Racer, Solver};
+use crate::records::{Rating, Record, Records};
+use crate::replay::{Playback, Replay, Step};
+use crate::save::{self, MazeEncoding, SaveError, SaveFormat};
+use crate::sound::{Cue, Sound};
+use crate::stats::Stats;
+use crate::teach::{Lesson, Union};
+use crate::terminal::Background;
+use crate::tournament::{self, RunResult, Tournament};
+use crate::treasure;
+use crate::world::WorldMap;
+
+/// Default file of saved games.
+const SAVE_FILE: &str = "maze.ron";
+/// Numbered save slots, on keys 1 to 9.
+const SLOTS: u8 = 9;
+
+/// Delay between two polls of the keyboard while playing a replay back.
+const PLAYBACK_TICK: Duration = Duration::from_millis(30);
+
+/// Delay between two polls of the keyboard while playing when no frame rate is set, the
+/// clock is redrawn as often.
+const INPUT_TICK: Duration = Duration::from_millis(100);
+
+/// Lines of the message pane below the status line.
+pub const MESSAGE_LINES: u16 = 2;
 
 enum GameCommand {
     Quit,
     NewGame,
+    /// play a saved game instead
+    Load(Box<GameState>),
+}
+
+/// Player action bound to a key.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Action {
+    Move(Direction),
+    Reset,
+    /// take back the last move
+    Undo,
+    Save,
+    /// save to the numbered slot
+    SaveSlot(u8),
+    /// pick a slot to load
+    LoadSlot,
+    /// type a command, see `palette::Command`
+    Command,
+    /// watch the run so far played back
+    Replay,
+    TogglePath,
+    ToggleSolution,
+    Hint,
+    /// show the statistics over every session
+    Stats,
+    /// show the way back to the last junction with a passage not taken yet
+    Breadcrumbs,
+    Messages,
+    SwitchPlayer,
+    /// pause and list the keys
+    Help,
+    NewGame,
+    Quit,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Saved game whose moves don't lead to the saved position.
+#[derive(Error, Debug, PartialEq)]
+pub enum StateError {
+    #[error("the saved moves break: {0}")]
+    Moves(#[from] PathError),
+    #[error("the saved moves end at {end:?} instead of the saved position {pos:?}")]
+    Position { end: Position, pos: Position },
+}
+
+/// Version of the saves written by this build. Bump it when `GameState` changes and
+/// bring older saves up to date in `GameState::migrate`.
+pub const SAVE_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(try_from = "SavedState", into = "SavedState")]
 pub struct GameState {
+    /// saves from before versions were kept are version 0
+    #[serde(default)]
+    version: u32,
     maze: Maze,
     difficulty: Difficulty,
     pos: Position,
     moves: Vec<(Position, Option<Direction>)>,
+    /// Shortest path from the entrance to the exit, computed once when the maze is created.
+    #[serde(default)]
+    solution: Option<Vec<Position>>,
+    /// Name of the maze, for people browsing their saves.
+    #[serde(default)]
+    name: String,
+    /// what the maze is generated from when it is seeded, saved instead of the maze
+    seed: Option<MazeSeed>,
+}
+
+/// `GameState` as written in the save file, with the maze in the smallest encoding.
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    #[serde(default)]
+    version: u32,
+    #[serde(default = "walls_encoding")]
+    encoding: MazeEncoding,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "bare")]
+    maze: Option<Maze>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "bare")]
+    packed: Option<PackedMaze>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "bare")]
+    seed: Option<MazeSeed>,
+    /// fingerprint of the maze saved as a seed, to tell whether another generator still
+    /// makes it
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "bare")]
+    fingerprint: Option<u64>,
+    difficulty: Difficulty,
+    pos: Position,
+    moves: Vec<(Position, Option<Direction>)>,
+    #[serde(default)]
+    solution: Option<Vec<Position>>,
+    #[serde(default)]
+    name: String,
+}
+
+impl From<GameState> for SavedState {
+    fn from(gs: GameState) -> SavedState {
+        // a seed is smaller than any maze, the solution is found again with the maze. One
+        // of an older generator may not make this maze anymore, it is packed instead.
+        if let Some(seed) = gs.seed.clone().filter(MazeSeed::is_current) {
+            return SavedState {
+                version: gs.version,
+                encoding: MazeEncoding::Seed,
+                maze: None,
+                packed: None,
+                seed: Some(seed),
+                fingerprint: Some(gs.maze.fingerprint()),
+                difficulty: gs.difficulty,
+                pos: gs.pos,
+                moves: gs.moves,
+                solution: None,
+                name: gs.name,
+            };
+        }
+        let packed = gs.maze.pack().filter(|p| ron_len(p) < ron_len(&gs.maze));
+        let (encoding, maze) = match packed {
+            Some(_) => (MazeEncoding::Packed, None),
+            None => (MazeEncoding::Walls, Some(gs.maze)),
+        };
+        SavedState {
+            version: gs.version,
+            encoding,
+            maze,
+            packed,
+            seed: None,
+            fingerprint: None,
+            difficulty: gs.difficulty,
+            pos: gs.pos,
+            moves: gs.moves,
+            solution: gs.solution,
+            name: gs.name,
+        }
+    }
+}
+
+/// Optional fields written as their bare value, RON would wrap them in `Some` and
+/// older saves have the maze without it.
+mod bare {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize>(
+        value: &Option<T>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(v) => v.serialize(s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        d: D,
+    ) -> Result<Option<T>, D::Error> {
+        T::deserialize(d).map(Some)
+    }
+}
+
+/// encoding of the saves from before encodings were picked
+fn walls_encoding() -> MazeEncoding {
+    MazeEncoding::Walls
+}
+
+/// Length of the value written in RON, JSON sizes compare alike.
+fn ron_len<T: Serialize>(value: &T) -> usize {
+    ron::ser::to_string(value).map_or(usize::MAX, |s| s.len())
+}
+
+impl TryFrom<SavedState> for GameState {
+    type Error = SaveError;
+
+    fn try_from(saved: SavedState) -> Result<GameState, SaveError> {
+        let maze = match saved.encoding {
+            MazeEncoding::Walls => saved.maze,
+            MazeEncoding::Packed => saved.packed.map(|p| p.unpack()).transpose()?,
+            MazeEncoding::Seed => match &saved.seed {
+                Some(seed) => {
+                    let maze = seed.generate()?;
+                    if !seed.is_current() && saved.fingerprint != Some(maze.fingerprint()) {
+                        return Err(SaveError::Generator(seed.generator));
+                    }
+                    Some(maze)
+                }
+                None => None,
+            },
+        }
+        .ok_or(SaveError::MissingMaze(saved.encoding))?;
+        Ok(GameState {
+            version: saved.version,
+            solution: match saved.encoding {
+                MazeEncoding::Seed => maze.solution(),
+                _ => saved.solution,
+            },
+            maze,
+            difficulty: saved.difficulty,
+            pos: saved.pos,
+            moves: saved.moves,
+            name: saved.name,
+            seed: saved.seed,
+        })
+    }
+}
+
+impl GameState {
+    /// Load a saved game, in the given format or the one of the file extension.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        format: Option<SaveFormat>,
+    ) -> Result<GameState, SaveError> {
+        save::load::<_, GameState>(path, format)?.migrate()
+    }
+
+    /// Bring a save written by an older version up to date, one version at a time.
+    pub fn migrate(mut self) -> Result<GameState, SaveError> {
+        if self.version > SAVE_VERSION {
+            return Err(SaveError::NewerVersion(self.version));
+        }
+        while self.version < SAVE_VERSION {
+            // each step brings a save of that version to the next one
+            if self.version == 0 {
+                // the solution and name were added before the version
+                if self.solution.is_none() {
+                    self.solution = self.maze.solution();
+                }
+                if self.name.is_empty() {
+                    self.name = self.maze.name();
+                }
+            }
+            // version 1 saves need nothing more, version 2 only added packed mazes
+            self.version += 1;
+        }
+        Ok(self)
+    }
+
+    /// state of a fresh game in the given maze
+    pub fn new(maze: Maze, difficulty: Difficulty) -> GameState {
+        GameState {
+            version: SAVE_VERSION,
+            pos: maze.entrance(),
+            moves: vec![(maze.entrance(), None)],
+            solution: maze.solution(),
+            name: maze.name(),
+            maze,
+            difficulty,
+            seed: None,
+        }
+    }
+
+    /// Replay the saved moves from the entrance and check they end at the saved position,
+    /// an edited or damaged save could put the player inside a wall.
+    pub fn verify(&self) -> Result<(), StateError> {
+        let moves: Vec<Direction> = self.moves.iter().filter_map(|m| m.1).collect();
+        let end = self.maze.verify_path(&moves)?;
+        if end != self.pos {
+            return Err(StateError::Position { end, pos: self.pos });
+        }
+        Ok(())
+    }
+
+    /// Put the joystick of the saved maze back where the game was left, along with the
+    /// moves that got it there so undo and the next save go on from them. Fails like
+    /// `verify`, leaving the joystick as it was.
+    pub fn resume(&self, j: &mut Joystick) -> Result<(), StateError> {
+        self.verify()?;
+        j.pos = self.pos;
+        j.history = self.moves.clone();
+        Ok(())
+    }
+
+    /// the saved maze
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// the maze name
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The cached shortest path from the entrance to the exit. None when it wasn't cached
+    /// or doesn't walk the maze from the entrance to an exit, like in an edited save.
+    pub fn solution(&self) -> Option<&[Position]> {
+        let path = self.solution.as_deref()?;
+        let moves: Option<Vec<Direction>> =
+            path.windows(2).map(|w| w[0].direction_to(&w[1])).collect();
+        let end = self.maze.verify_path(&moves?).ok()?;
+        let walks = path.first() == Some(&self.maze.entrance()) && self.maze.is_exit(end);
+        Some(path).filter(|_| walks)
+    }
+}
+
+/// Game settings.
+#[derive(Clone, Debug, Default)]
+pub struct GameOpts {
+    /// maze generation options
+    pub maze: Opts,
+    /// checkpoints to visit before the exit unlocks
+    pub checkpoints: usize,
+    /// enemies wandering the maze, running into one sends the player back to the entrance
+    pub enemies: usize,
+    /// hide a treasure hinted by warmer/colder messages
+    pub treasure: bool,
+    /// play the tournament seed and sign the result
+    pub tournament: Option<Tournament>,
+    /// hot-seat puzzle for two players with a pressure plate and a door
+    pub coop: bool,
+    /// turn the maze a quarter clockwise every so many moves, 0 never turns it
+    pub rotate_every: usize,
+    /// show the maze for so many seconds then hide the walls
+    pub memory: Option<u64>,
+    /// show every new maze with its metrics until the player accepts or rerolls it
+    pub preview: bool,
+    /// blocked moves slide along the only open side, see `Joystick::resolve`
+    pub assist: bool,
+    /// huge cells, heavy walls and a blinking player for low vision players
+    pub large_print: bool,
+    /// volume of the sound cues from 0, muted, to 1
+    pub volume: f32,
+    /// sight radius, only cells this close or already visited are drawn, 0 for no fog
+    pub fog: u16,
+    /// format of the default save file, RON when None
+    pub save_format: Option<SaveFormat>,
+    /// directory of the save files, the current one when empty
+    pub save_dir: PathBuf,
+    /// directory the replays and signed results of the won runs are written to, under
+    /// a name of their own, the current one when empty, see `replay::runs_dir`
+    pub runs_dir: PathBuf,
+    /// file the game in progress is saved to when quitting, see `save::last_game`
+    pub autosave: Option<PathBuf>,
+    /// file of the personal bests, see `records::records_file`, none kept when None
+    pub records_file: Option<PathBuf>,
+    /// file of the statistics over every session, see `stats::stats_file`, none kept
+    /// when None
+    pub stats_file: Option<PathBuf>,
+    /// file of the habits over the won runs, see `habits::habits_file`, none kept when None
+    pub habits_file: Option<PathBuf>,
+    /// file of the leaderboards of seeded mazes, see `leaderboard::leaderboard_file`, none
+    /// kept when None
+    pub leaderboard: Option<PathBuf>,
+    /// file of the world map, see `world::world_file`, none kept when None
+    pub world_file: Option<PathBuf>,
+    /// directory of the progress in the campaign packs, see `campaign::progress_dir`, none
+    /// kept when None
+    pub progress_dir: Option<PathBuf>,
+    /// file of the progress in the endless campaign, see `campaign::endless_file`, none
+    /// kept when None
+    pub endless_file: Option<PathBuf>,
+    /// name of the player on the leaderboards
+    pub player: String,
+    /// frames drawn per second at most while waiting for keys, 0 for `INPUT_TICK`
+    pub fps: u16,
+    /// colors of every maze, by difficulty when None
+    pub theme: Option<Theme>,
+    /// show the bearing to the exit on the status line, see `compass`
+    pub compass: bool,
+    /// after the exit, walk back to the entrance through the fog without the trail to
+    /// double the score
+    pub return_trip: bool,
+    /// no blinking or animations for players sensitive to motion, changes show at once
+    pub reduce_motion: bool,
+    /// every exit has to be reached, in any order, instead of any one of them
+    pub all_exits: bool,
+    /// actions of the keys
+    pub keys: KeyMap,
+    /// commands run on game events
+    pub hooks: Hooks,
 }
 
 /// The game state.
@@ -31,13 +436,127 @@ pub struct Game<R, W: Write> {
     stdin: R,
     width: u16,
     height: u16,
-    difficulty: Difficulty,
+    opts: GameOpts,
+    theme: Theme,
     show_path: bool,
     path_visible: bool,
+    /// Personal best per maze.
+    records: Records,
+    /// Game event messages shown below the maze.
+    messages: MessageLog,
+    /// Called with every frame drawn.
+    hook: Option<FrameHook>,
+    /// Next maze, generated in the background.
+    pool: MazePool,
+    /// Board positions drawn for a single frame, like hints.
+    transient: Vec<Position>,
+    /// Audio output, None when muted or without the `sound` feature.
+    sound: Option<Sound>,
+    /// Short fingerprint of the maze being played, for racers to check they're on the
+    /// same one.
+    maze_id: String,
+    /// Progress of the endless campaign being played, shown in the status line.
+    endless: Option<Endless>,
+    /// Games played over every session.
+    stats: Stats,
+}
+
+/// Colors used to draw the game.
+#[derive(Copy, Clone, Debug)]
+pub struct Theme {
+    pub exit: color::AnsiValue,
+    pub trail: color::AnsiValue,
+}
+
+impl Theme {
+    pub fn classic() -> Theme {
+        Theme {
+            exit: color::AnsiValue(2),
+            trail: color::AnsiValue(4),
+        }
+    }
+
+    /// bright colors for the Easy difficulty
+    pub fn friendly() -> Theme {
+        Theme {
+            exit: color::AnsiValue(11),
+            trail: color::AnsiValue(13),
+        }
+    }
+
+    /// green on black
+    pub fn matrix() -> Theme {
+        Theme {
+            exit: color::AnsiValue(10),
+            trail: color::AnsiValue(22),
+        }
+    }
+
+    /// deep colors that stand out on a white background
+    pub fn light() -> Theme {
+        Theme {
+            exit: color::AnsiValue(28),
+            trail: color::AnsiValue(19),
+        }
+    }
+
+    /// bright colors that stand out on a black background
+    pub fn dark() -> Theme {
+        Theme {
+            exit: color::AnsiValue(10),
+            trail: color::AnsiValue(12),
+        }
+    }
+
+    /// theme by name, None for unknown ones
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "classic" => Some(Theme::classic()),
+            "friendly" => Some(Theme::friendly()),
+            "matrix" => Some(Theme::matrix()),
+            "light" => Some(Theme::light()),
+            "dark" => Some(Theme::dark()),
+            _ => None,
+        }
+    }
+}
+
+impl From<Background> for Theme {
+    fn from(b: Background) -> Self {
+        match b {
+            Background::Light => Theme::light(),
+            Background::Dark => Theme::dark(),
+        }
+    }
+}
+
+impl From<Difficulty> for Theme {
+    fn from(d: Difficulty) -> Self {
+        match d {
+            Difficulty::Easy => Theme::friendly(),
+            _ => Theme::classic(),
+        }
+    }
+}
+
+/// Arrow pointing from one position to the other as the crow flies, walls aside. Screen
+/// rows grow downwards so up is towards the first row.
+pub fn compass(from: Position, to: Position) -> char {
+    if from == to {
+        return '•';
+    }
+    let (dx, dy) = (to.x as f32 - from.x as f32, from.y as f32 - to.y as f32);
+    // eighths of a turn counterclockwise from the right
+    let sector = (dy.atan2(dx) / std::f32::consts::FRAC_PI_4).round() as i32;
+    ['→', '↗', '↑', '↖', '←', '↙', '↓', '↘'][sector.rem_euclid(8) as usize]
 }
 
 impl<R, W: Write> Drop for Game<R, W> {
     fn drop(&mut self) {
+        // the moves since the last game begun or won
+        if let Some(path) = &self.opts.stats_file {
+            let _ = self.stats.save(path);
+        }
         // When done, restore the defaults to avoid messing with the terminal.
         write!(
             self.stdout,
@@ -59,234 +578,1903 @@ impl fmt::Display for Position {
 impl fmt::Display for Difficulty {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Difficulty::Easy => write!(f, "EASY"),
             Difficulty::Normal => write!(f, "NORMAL"),
             Difficulty::Hard => write!(f, "HARD"),
+            Difficulty::Expert => write!(f, "EXPERT"),
+            Difficulty::Insane => write!(f, "INSANE"),
         }
     }
 }
 
 impl<R: Iterator<Item = Result<Key, std::io::Error>>, W: Write> Game<R, W> {
-    pub fn init(mut stdout: W, stdin: R, width: u16, height: u16, difficulty: Difficulty) {
-        write!(stdout, "{}", clear::All).unwrap();
-        println!("generating {}x{} maze...", width, height);
-        let mut game = Game {
+    /// Set up a game without starting it, see `run`.
+    pub fn new(stdout: W, stdin: R, width: u16, height: u16, opts: GameOpts) -> Game<R, W> {
+        Game {
             stdin,
             stdout,
             width,
             height,
-            difficulty,
+            theme: opts
+                .theme
+                .unwrap_or_else(|| Theme::from(opts.maze.difficulty)),
             show_path: false,
             path_visible: false,
+            records: opts
+                .records_file
+                .as_ref()
+                .map(Records::load)
+                .unwrap_or_default(),
+            maze_id: String::new(),
+            endless: None,
+            stats: opts
+                .stats_file
+                .as_ref()
+                .map(Stats::load_or_recover)
+                .unwrap_or_default(),
+            messages: MessageLog::default(),
+            hook: None,
+            pool: MazePool::default(),
+            transient: Vec::new(),
+            sound: if opts.volume > 0.0 {
+                Sound::new(opts.volume)
+            } else {
+                None
+            },
+            opts,
+        }
+    }
+
+    pub fn init(mut stdout: W, stdin: R, width: u16, height: u16, opts: GameOpts) {
+        write!(stdout, "{}", clear::All).unwrap();
+        println!("generating {}x{} maze...", width, height);
+        Game::new(stdout, stdin, width, height, opts).run(None);
+    }
+
+    /// Play the saved game, the options apply to the games after it.
+    pub fn restore(mut stdout: W, stdin: R, gs: &GameState, opts: GameOpts) {
+        write!(stdout, "{}", clear::All).unwrap();
+        println!("restoring maze...");
+        let opts = GameOpts {
+            // a seeded maze keeps its seed for the next save
+            maze: match &gs.seed {
+                Some(seed) => seed.opts(),
+                None => Opts {
+                    difficulty: gs.difficulty,
+                    ..opts.maze
+                },
+            },
+            ..opts
         };
+        Game::new(stdout, stdin, gs.maze.width, gs.maze.height, opts).run(Some(gs));
+    }
 
-        // Start the event loop.
-        loop {
-            match game.start(None) {
-                Quit => return,
-                NewGame => continue,
-            };
+    /// Hub of connected mazes, completing one unlocks its neighbours on the map. The map
+    /// and best times persist between sessions.
+    pub fn world(mut stdout: W, stdin: R, width: u16, height: u16, opts: GameOpts) {
+        write!(stdout, "{}", clear::All).unwrap();
+        let mut map = opts
+            .world_file
+            .as_ref()
+            .and_then(WorldMap::load)
+            .unwrap_or_else(|| WorldMap::new(5, 5));
+        let mut game = Game::new(stdout, stdin, width, height, opts);
+        while let Some(p) = game.pick_node(&mut map) {
+            game.opts.maze.seed = Some(map.node(p).seed);
+            if let (_, Some(run)) = game.start(None) {
+                map.complete(p, run);
+                if let Some(path) = &game.opts.world_file {
+                    let _ = map.save(path);
+                }
+            }
         }
     }
 
-    pub fn restore(mut stdout: W, stdin: R, gs: &GameState) {
+    /// Play the levels of a pack in order, picking up where the last session stopped.
+    /// Between levels the player can grow or shrink the next mazes.
+    pub fn campaign(mut stdout: W, stdin: R, pack: &LevelPack, opts: GameOpts) {
         write!(stdout, "{}", clear::All).unwrap();
-        println!("restoring maze...");
-        let mut game = Game {
-            stdin,
-            stdout,
-            width: gs.maze.width,
-            height: gs.maze.height,
-            difficulty: gs.difficulty,
-            show_path: false,
-            path_visible: false,
+        let mut progress = match &opts.progress_dir {
+            Some(dir) => Progress::load(pack.progress_file(dir), pack),
+            None => Progress::new(pack),
+        };
+        let asked = opts.clone();
+        let mut game = Game::new(stdout, stdin, 0, 0, opts);
+        while let Some(level) = game.pick_size(pack, &mut progress) {
+            game.apply_level(&level, &asked);
+            if let (_, Some(_)) = game.start(None) {
+                progress.level += 1;
+                game.save_progress(pack, &progress);
+            }
+        }
+    }
+
+    /// Endless campaign, every maze completed is followed by a slightly larger and harder
+    /// one, up to the given size. The level reached and the time spent persist between
+    /// sessions.
+    pub fn endless(mut stdout: W, stdin: R, width: u16, height: u16, opts: GameOpts) {
+        write!(stdout, "{}", clear::All).unwrap();
+        let mut progress = opts
+            .endless_file
+            .as_ref()
+            .map(Endless::load)
+            .unwrap_or_default();
+        let asked = opts.clone();
+        let mut game = Game::new(stdout, stdin, width, height, opts);
+        loop {
+            let level = progress.next_level(width, height);
+            game.apply_level(&level, &asked);
+            game.endless = Some(progress.clone());
+            let (command, completed) = game.start(None);
+            if let Some(run) = completed {
+                progress.complete(run);
+                if let Some(path) = &game.opts.endless_file {
+                    let _ = progress.save(path);
+                }
+            }
+            // a new game without reaching the exit plays the level again on another maze
+            if let Quit = command {
+                return;
+            }
+        }
+    }
+
+    /// Set the next mazes up for the level, shrunk to fit the terminal. `asked` are the
+    /// options of the command line, their fog and braid are kept by levels without any.
+    fn apply_level(&mut self, level: &Level, asked: &GameOpts) {
+        self.opts.maze.difficulty = level.difficulty;
+        // the cells drawn, and so the size that fits, depend on the difficulty
+        let (width, height) = self.fit(level.width, level.height);
+        self.width = width;
+        self.height = height;
+        self.theme = self
+            .opts
+            .theme
+            .unwrap_or_else(|| Theme::from(level.difficulty));
+        self.opts.fog = if level.fog > 0 { level.fog } else { asked.fog };
+        self.opts.maze.braid = if level.braid > 0.0 {
+            level.braid
+        } else {
+            asked.maze.braid
         };
+        self.opts.enemies = level.enemies;
+    }
 
-        // Start the event loop.
-        let mut state = Some(gs);
+    /// Show the next level of the pack until enter plays it, + and - resize it and q
+    /// quits with None. The size picked is kept with the progress.
+    fn pick_size(&mut self, pack: &LevelPack, progress: &mut Progress) -> Option<Level> {
         loop {
-            match game.start(state) {
+            write!(self.stdout, "{}{}", clear::All, Goto(1, 1)).unwrap();
+            let level = match progress.next_level(pack) {
+                Some(l) => l,
+                None => {
+                    write!(self.stdout, "{} complete! (press any key)", pack.name).unwrap();
+                    self.stdout.flush().unwrap();
+                    self.wait_key();
+                    return None;
+                }
+            };
+            write!(
+                self.stdout,
+                "{} level {}/{}: {}x{} {}",
+                pack.name,
+                progress.level + 1,
+                pack.levels.len(),
+                level.width,
+                level.height,
+                level.difficulty
+            )
+            .unwrap();
+            if pack.resize > 0 {
+                write!(self.stdout, "{}+/-: resize", Goto(1, 3)).unwrap();
+            }
+            write!(self.stdout, "{}enter: play, q: quit", Goto(1, 4)).unwrap();
+            self.stdout.flush().unwrap();
+
+            let k = self.wait_key()?;
+            match (k, self.opts.keys.action(&k)) {
+                (Key::Char('\n'), _) => return Some(level),
+                (Key::Char('+'), _) => progress.resize(pack, 1),
+                (Key::Char('-'), _) => progress.resize(pack, -1),
+                (_, Some(Action::Quit)) => return None,
+                _ => continue,
+            }
+            self.save_progress(pack, progress);
+        }
+    }
+
+    fn save_progress(&self, pack: &LevelPack, progress: &Progress) {
+        if let Some(dir) = &self.opts.progress_dir {
+            let _ = progress.save(pack.progress_file(dir));
+        }
+    }
+
+    /// Show the world map until an unlocked node is picked with enter, None on q.
+    fn pick_node(&mut self, map: &mut WorldMap) -> Option<Position> {
+        loop {
+            write!(
+                self.stdout,
+                "{}{}World map (enter: play, q: quit)",
+                clear::All,
+                Goto(1, 1)
+            )
+            .unwrap();
+            for (i, line) in map.render().iter().enumerate() {
+                write!(self.stdout, "{}{}", Goto(1, i as u16 + 3), line).unwrap();
+            }
+            let node = map.node(map.cursor);
+            write!(
+                self.stdout,
+                "{}{}",
+                Goto(1, map.height + 4),
+                match (node.best, node.unlocked) {
+                    (Some(b), _) =>
+                        format!("best: {} moves {:.1}s", b.moves, b.time().as_secs_f32()),
+                    (None, true) => "unsolved".to_string(),
+                    (None, false) => "locked".to_string(),
+                }
+            )
+            .unwrap();
+            self.stdout.flush().unwrap();
+
+            let k = self.wait_key()?;
+            match (k, self.opts.keys.action(&k)) {
+                (Key::Char('\n'), _) if map.node(map.cursor).unlocked => return Some(map.cursor),
+                (_, Some(Action::Move(d))) => map.move_cursor(&d),
+                (_, Some(Action::Quit)) => return None,
+                _ => (),
+            }
+        }
+    }
+
+    /// Wait for a key press, None when the input fails. The input may not block, so it
+    /// is polled until a key comes in.
+    fn wait_key(&mut self) -> Option<Key> {
+        loop {
+            match self.stdin.next() {
+                Some(k) => return k.ok(),
+                None => std::thread::sleep(self.frame_time()),
+            }
+        }
+    }
+
+    /// Ask a yes or no question on a cleared screen, true when answered with y.
+    fn confirm(&mut self, question: String) -> bool {
+        write!(
+            self.stdout,
+            "{}{}{} (y/n)",
+            clear::All,
+            Goto(1, 1),
+            question
+        )
+        .unwrap();
+        self.stdout.flush().unwrap();
+        let yes = self.wait_key() == Some(Key::Char('y'));
+        write!(self.stdout, "{}", clear::All).unwrap();
+        yes
+    }
+
+    /// time between two polls of the keyboard, see `GameOpts::fps`
+    fn frame_time(&self) -> Duration {
+        match self.opts.fps {
+            0 => INPUT_TICK,
+            fps => Duration::from_secs(1) / fps as u32,
+        }
+    }
+
+    /// call the hook with every frame drawn from now on
+    pub fn on_frame<F: FnMut(&Frame) + 'static>(&mut self, hook: F) {
+        self.hook = Some(Box::new(hook));
+    }
+
+    /// Start the event loop, from the saved state if any, until the player quits.
+    pub fn run(&mut self, state: Option<&GameState>) {
+        let mut state = state;
+        let mut loaded: Option<GameState> = None;
+        loop {
+            match self.start(loaded.as_ref().or(state)).0 {
                 Quit => return,
                 NewGame => {
                     state = None;
+                    loaded = None;
+                }
+                Load(gs) => {
+                    state = None;
+                    self.width = gs.maze.width;
+                    self.height = gs.maze.height;
+                    self.opts.maze.difficulty = gs.difficulty;
+                    write!(self.stdout, "{}", clear::All).unwrap();
+                    loaded = Some(*gs);
                 }
             };
         }
     }
 
     fn draw_maze(&mut self, maze: &MazeUI) {
+        self.draw_board(maze, maze.draw());
+    }
+
+    /// draw the walls of the game as seen on screen, through the fog or hidden
+    fn draw_walls(&mut self, ui: &MazeUI, engine: &Engine) {
+        self.draw_board(ui, engine.board(ui));
+        for (p, d) in engine.bumps() {
+            self.draw_bump(ui, p, &d);
+        }
+    }
+
+    fn draw_board(&mut self, maze: &MazeUI, board: Vec<Vec<char>>) {
         // Reset the cursor.
         write!(self.stdout, "{}", cursor::Goto(1, 1)).unwrap();
 
-        for r in maze.draw() {
+        for r in board {
             for c in r {
-                self.stdout.write(c.to_string().as_bytes()).unwrap();
+                let c = if self.opts.large_print {
+                    Tileset::Heavy.tile(c)
+                } else {
+                    c
+                };
+                self.stdout.write_all(c.to_string().as_bytes()).unwrap();
             }
-            self.stdout.write(b"\n\r").unwrap();
+            self.stdout.write_all(b"\n\r").unwrap();
+        }
+
+        for (label, text) in maze.exit_labels() {
+            write!(
+                self.stdout,
+                "{}{}{}{}",
+                label,
+                color::Fg(self.theme.exit),
+                text,
+                style::Reset
+            )
+            .unwrap();
         }
+        self.stdout.flush().unwrap();
+    }
 
-        let exit = maze.exit().mv(&Direction::Left, 2);
+    /// draw a bumped hidden wall as a faint mark, by cell and side on screen
+    fn draw_bump(&mut self, ui: &MazeUI, p: Position, d: &Direction) {
+        let p = ui.locate(&p);
+        let (wall, glyph) = match *d {
+            d @ Direction::Left | d @ Direction::Right => (p.mv(&d, ui.cell_width / 2), '│'),
+            d => (p.mv(&d, ui.cell_height / 2), '─'),
+        };
         write!(
             self.stdout,
-            "{}{}Exit{}",
-            exit,
-            color::Fg(color::Green),
+            "{}{}{}{}",
+            wall,
+            style::Faint,
+            glyph,
             style::Reset
         )
         .unwrap();
+    }
+
+    /// draw the status line with the run progress compared to the optimal moves and the
+    /// personal best
+    fn draw_status(
+        &mut self,
+        ui: &MazeUI,
+        j: &Joystick,
+        par: usize,
+        elapsed: Duration,
+        best: Option<Record>,
+    ) {
+        let hints = self.opts.keys.hints(&[
+            (Action::Help, "help"),
+            (Action::NewGame, "new"),
+            (Action::TogglePath, "path"),
+            (Action::Quit, "exit"),
+            (Action::Save, "save"),
+        ]);
         write!(
             self.stdout,
-            "{}n: new, p: path, q: exit, e: save | {}{}",
-            Goto(1, maze.dimensions().1 + 2),
-            self.difficulty,
-            style::Reset
+            "{}{}{} | {} #{} | moves: {}/{}",
+            Goto(1, ui.dimensions().1 + 2),
+            clear::CurrentLine,
+            hints,
+            self.opts.maze.difficulty,
+            self.maze_id,
+            j.move_count(),
+            par
         )
         .unwrap();
-        self.stdout.flush().unwrap();
+        if let Some(b) = best {
+            let dm = j.move_count() as i64 - b.moves as i64;
+            let dt = elapsed.as_secs_f32() - b.time().as_secs_f32();
+            write!(
+                self.stdout,
+                " ({:+}) time: {:.1}s ({:+.1}s) | best: {} moves {:.1}s",
+                dm,
+                elapsed.as_secs_f32(),
+                dt,
+                b.moves,
+                b.time().as_secs_f32()
+            )
+            .unwrap();
+        } else {
+            write!(self.stdout, " time: {:.1}s", elapsed.as_secs_f32()).unwrap();
+        }
+        if let Some(e) = &self.endless {
+            let total = Duration::from_millis(e.millis) + elapsed;
+            write!(
+                self.stdout,
+                " | level {} total: {:.1}s",
+                e.level + 1,
+                total.as_secs_f32()
+            )
+            .unwrap();
+        }
+        if self.opts.compass {
+            // the exit closest as the crow flies, on the maze as turned on screen
+            let exit = j
+                .maze
+                .exits()
+                .into_iter()
+                .min_by_key(|e| {
+                    let (dx, dy) = (e.x as i32 - j.pos.x as i32, e.y as i32 - j.pos.y as i32);
+                    dx * dx + dy * dy
+                })
+                .unwrap_or_else(|| j.maze.exit());
+            write!(self.stdout, " | exit: {}", compass(j.pos, exit)).unwrap();
+        }
+        write!(self.stdout, "{}", style::Reset).unwrap();
     }
 
     fn draw_path(&mut self, ui: &MazeUI, j: &Joystick, show: bool) {
+        // hints only last a frame, the trail is drawn back over them
+        for p in std::mem::take(&mut self.transient) {
+            write!(self.stdout, "{} ", p).unwrap();
+        }
         if !self.path_visible && !show {
             return;
         }
-        let mut last: Option<Position> = None;
-        let pad = " ".repeat(ui.cell_width as usize);
-        for p in j.history.iter() {
+        for p in ui.trail(j) {
             if show {
                 write!(
                     self.stdout,
                     "{}{} {}",
-                    ui.locate(&p.0),
-                    color::Bg(color::Blue),
+                    p,
+                    color::Bg(self.theme.trail),
                     style::Reset
                 )
                 .unwrap();
             } else {
-                write!(self.stdout, "{} {}", ui.locate(&p.0), style::Reset).unwrap();
-            }
-            if let Some(l) = last {
-                if let Some(d) = p.1 {
-                    match d {
-                        Direction::Left => {
-                            let lp = ui.locate(&l).mv(&d, ui.cell_width);
-                            if show {
-                                write!(
-                                    self.stdout,
-                                    "{}{}{}{}",
-                                    lp,
-                                    color::Bg(color::Blue),
-                                    pad,
-                                    style::Reset
-                                )
-                                .unwrap();
-                            } else {
-                                write!(self.stdout, "{}{}{}", lp, pad, style::Reset).unwrap();
-                            }
-                        }
-                        Direction::Right => {
-                            let lp = ui.locate(&l);
-                            if show {
-                                write!(
-                                    self.stdout,
-                                    "{}{}{}{}",
-                                    lp,
-                                    color::Bg(color::Blue),
-                                    pad,
-                                    style::Reset
-                                )
-                                .unwrap();
-                            } else {
-                                write!(self.stdout, "{}{}{}", lp, pad, style::Reset).unwrap();
-                            }
-                        }
-                        _ => {
-                            let lp = ui.locate(&l).mv(&d, 1);
-                            if show {
-                                write!(
-                                    self.stdout,
-                                    "{}{} {}",
-                                    lp,
-                                    color::Bg(color::Blue),
-                                    style::Reset
-                                )
-                                .unwrap();
-                            } else {
-                                write!(self.stdout, "{} {}", lp, style::Reset).unwrap();
-                            }
-                        }
-                    }
-                }
+                write!(self.stdout, "{} {}", p, style::Reset).unwrap();
             }
-            last = Some(p.0);
         }
         self.path_visible = show;
     }
 
-    fn save(&self, m: &Maze, j: &Joystick) {
+    /// highlight the cell of the maze until the next frame
+    fn draw_hint(&mut self, ui: &MazeUI, p: Position) {
+        let board = ui.locate(&p);
+        write!(
+            self.stdout,
+            "{}{} {}",
+            board,
+            color::Bg(color::Yellow),
+            style::Reset
+        )
+        .unwrap();
+        self.transient.push(board);
+    }
+
+    /// highlight the way through the cells of the maze until the next frame
+    fn draw_breadcrumbs(&mut self, ui: &MazeUI, path: &[Position]) {
+        for board in ui.trail_of(path) {
+            write!(
+                self.stdout,
+                "{}{} {}",
+                board,
+                color::Bg(color::Yellow),
+                style::Reset
+            )
+            .unwrap();
+            self.transient.push(board);
+        }
+    }
+
+    /// draw a blinking player for large print, the cursor alone is easy to lose, a bold
+    /// one with reduced motion
+    fn draw_player(&mut self, ui: &MazeUI, view: &Joystick) {
+        let p = ui.locate(view);
+        let blink = if self.opts.reduce_motion {
+            ""
+        } else {
+            style::Blink.as_ref()
+        };
+        write!(
+            self.stdout,
+            "{}{}{}@{}",
+            p,
+            blink,
+            style::Bold,
+            style::Reset
+        )
+        .unwrap();
+        self.transient.push(p);
+    }
+
+    fn cue(&self, cue: Cue) {
+        if let Some(s) = &self.sound {
+            s.play(cue);
+        }
+    }
+
+    /// size of the cells on screen
+    fn cell_size(&self) -> CellSize {
+        if self.opts.large_print {
+            CellSize::Huge
+        } else {
+            self.opts.maze.difficulty.cell_size()
+        }
+    }
+
+    /// The size shrunk to the largest maze of the cells drawn that fits the terminal,
+    /// unchanged when the size of the terminal is unknown.
+    fn fit(&self, width: u16, height: u16) -> (u16, u16) {
+        match termion::terminal_size() {
+            Ok((w, h)) => {
+                let size = self.cell_size();
+                // room for the status line and message pane below the maze
+                let rows = (h.saturating_sub(MESSAGE_LINES) / size.height()).saturating_sub(1);
+                (width.min(w / size.width()).max(2), height.min(rows).max(2))
+            }
+            Err(_) => (width, height),
+        }
+    }
+
+    /// draw the most recent messages below the status line
+    fn draw_messages(&mut self, ui: &MazeUI) {
+        let top = ui.dimensions().1 + 3;
+        let lines: Vec<String> = self
+            .messages
+            .recent(MESSAGE_LINES as usize)
+            .cloned()
+            .collect();
+        for i in 0..MESSAGE_LINES {
+            write!(self.stdout, "{}{}", Goto(1, top + i), clear::CurrentLine).unwrap();
+            if let Some(m) = lines.get(i as usize) {
+                write!(self.stdout, "{}", m).unwrap();
+            }
+        }
+    }
+
+    /// add a message to the log and show it
+    fn message<S: Into<String>>(&mut self, ui: &MazeUI, msg: S) {
+        self.messages.push(msg);
+        self.draw_messages(ui);
+    }
+
+    /// Show the whole message history over the maze until a key is pressed.
+    fn show_history(&mut self, ui: &MazeUI, engine: &Engine) {
+        let (width, height) = ui.dimensions();
+        let lines: Vec<String> = self
+            .messages
+            .recent(height.saturating_sub(2) as usize)
+            .map(|m| m.chars().take(width as usize - 2).collect())
+            .collect();
+        write!(self.stdout, "{}{}", Goto(1, 1), clear::AfterCursor).unwrap();
+        write!(self.stdout, "{}Messages (press any key)", Goto(2, 1)).unwrap();
+        for (i, m) in lines.iter().enumerate() {
+            write!(self.stdout, "{}{}", Goto(2, i as u16 + 2), m).unwrap();
+        }
+        self.stdout.flush().unwrap();
+        self.wait_key();
+        self.redraw(ui, engine);
+    }
+
+    /// Show the statistics over the maze until a key is pressed.
+    fn show_stats(&mut self, ui: &MazeUI, engine: &Engine) {
+        write!(self.stdout, "{}{}", Goto(1, 1), clear::AfterCursor).unwrap();
+        write!(self.stdout, "{}Statistics (press any key)", Goto(2, 1)).unwrap();
+        for (i, line) in self.stats.to_string().lines().enumerate() {
+            write!(self.stdout, "{}{}", Goto(2, i as u16 + 3), line).unwrap();
+        }
+        self.stdout.flush().unwrap();
+        self.wait_key();
+        self.redraw(ui, engine);
+    }
+
+    /// Victory screen, the summary of the run in a banner over the maze. Moves are over,
+    /// only the keys of a new game and quit are taken.
+    fn victory(&mut self, ui: &MazeUI, summary: &[String]) -> GameCommand {
+        let mut lines = summary.to_vec();
+        lines.push(String::new());
+        let hints = [(Action::NewGame, "new game"), (Action::Quit, "quit")];
+        lines.push(self.opts.keys.hints(&hints));
+        self.draw_banner(ui, &lines);
+        loop {
+            match self
+                .wait_key()
+                .as_ref()
+                .and_then(|k| self.opts.keys.action(k))
+            {
+                Some(Action::NewGame) => return NewGame,
+                Some(Action::Quit) => return Quit,
+                _ => (),
+            }
+        }
+    }
+
+    /// Pause the game with the keys listed over the maze until esc or a help key resumes
+    /// it.
+    /// Returns how long the game was paused.
+    fn show_help(&mut self, ui: &MazeUI, engine: &Engine) -> Duration {
+        let paused = Instant::now();
+        let help = self.opts.keys.help();
+        let width = help.iter().map(|h| h.0.chars().count()).max().unwrap_or(0);
+        let mut lines = vec!["Paused".to_string(), String::new()];
+        lines.extend(help.iter().map(|(keys, what)| {
+            let pad = " ".repeat(width - keys.chars().count());
+            format!("{}{}  {}", keys, pad, what)
+        }));
+        self.draw_banner(ui, &lines);
+        loop {
+            match self.wait_key() {
+                Some(Key::Esc) => break,
+                Some(k) if self.opts.keys.action(&k) == Some(Action::Help) => break,
+                _ => (),
+            }
+        }
+        // the banner may cover any part of the board
+        self.redraw(ui, engine);
+        paused.elapsed()
+    }
+
+    /// lines in a bold box in the middle of the maze
+    fn draw_banner(&mut self, ui: &MazeUI, lines: &[String]) {
+        let inner = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let (width, height) = ui.dimensions();
+        let left = (width as usize).saturating_sub(inner + 4) / 2 + 1;
+        let top = (height as usize).saturating_sub(lines.len() + 2) / 2 + 1;
+        let border = "━".repeat(inner + 2);
+        write!(
+            self.stdout,
+            "{}{}┏{}┓",
+            Goto(left as u16, top as u16),
+            style::Bold,
+            border
+        )
+        .unwrap();
+        for (i, line) in lines.iter().enumerate() {
+            let pad = inner - line.chars().count();
+            write!(
+                self.stdout,
+                "{}┃ {}{} ┃",
+                Goto(left as u16, (top + i + 1) as u16),
+                line,
+                " ".repeat(pad)
+            )
+            .unwrap();
+        }
+        write!(
+            self.stdout,
+            "{}┗{}┛{}",
+            Goto(left as u16, (top + lines.len() + 1) as u16),
+            border,
+            style::Reset
+        )
+        .unwrap();
+        self.stdout.flush().unwrap();
+    }
+
+    /// Keep a won run in the stats, records and leaderboard when it is counted. Returns the
+    /// lines of the victory summary it adds, None when it isn't counted.
+    fn keep_run(
+        &mut self,
+        ui: &MazeUI,
+        maze: &Maze,
+        run: Record,
+        counted: bool,
+        course: &Course,
+    ) -> Option<Vec<String>> {
+        // like the records, peeked and co-op runs count as neither wins nor best times, nor
+        // do they complete a level
+        if !counted {
+            return None;
+        }
+        let mut summary = Vec::new();
+        self.stats.win(maze.width, maze.height, run);
+        self.save_stats();
+        let fingerprint = maze.fingerprint();
+        if course.checkpoints().is_empty() && self.records.update(fingerprint, run) {
+            self.message(ui, "New best!");
+            if let Some(path) = &self.opts.records_file {
+                let _ = self.records.save(path);
+            }
+        }
+        if let (Some(seed), Some(path)) = (self.opts.maze.seed, self.opts.leaderboard.clone()) {
+            let mut board = Leaderboard::load_or_recover(&path);
+            let place = board.submit(fingerprint, Entry::new(&self.opts.player, run));
+            let _ = board.save(&path);
+            summary.push(String::new());
+            summary.push(format!("Top {} of seed {}:", TOP, seed));
+            for (i, entry) in board.top(fingerprint).iter().enumerate() {
+                let mark = if place == Some(i) { '>' } else { ' ' };
+                summary.push(format!("{}{:>2}. {}", mark, i + 1, entry));
+            }
+            if place.is_none() {
+                summary.push(format!("Your run didn't make the top {}", TOP));
+            }
+        }
+        Some(summary)
+    }
+
+    /// keep the statistics if there is a file for them
+    fn save_stats(&self) {
+        if let Some(path) = &self.opts.stats_file {
+            let _ = self.stats.save(path);
+        }
+    }
+
+    /// bring the board back after another screen
+    fn redraw(&mut self, ui: &MazeUI, engine: &Engine) {
+        write!(self.stdout, "{}", clear::All).unwrap();
+        self.draw_walls(ui, engine);
+        self.draw_messages(ui);
+        self.draw_path(ui, &engine.view(), self.path_visible);
+        self.draw_markers(ui, &engine.turned_markers());
+    }
+
+    /// Screen listing the save slots with their mazes, returns the game of the slot picked.
+    fn pick_slot(&mut self) -> Option<GameState> {
+        write!(self.stdout, "{}{}", Goto(1, 1), clear::AfterCursor).unwrap();
+        write!(
+            self.stdout,
+            "{}Load which slot? (1-{}, any other key goes back)",
+            Goto(2, 1),
+            SLOTS
+        )
+        .unwrap();
+        let mut saves = Vec::new();
+        for n in 1..=SLOTS {
+            let save = GameState::load(self.slot_file(n), self.opts.save_format).ok();
+            let line = match &save {
+                Some(gs) => format!(
+                    "{}  {}  {}x{}  {}",
+                    n,
+                    gs.name(),
+                    gs.maze.width,
+                    gs.maze.height,
+                    gs.difficulty
+                ),
+                None => format!("{}  empty", n),
+            };
+            write!(self.stdout, "{}{}", Goto(2, n as u16 + 2), line).unwrap();
+            saves.push(save);
+        }
+        self.stdout.flush().unwrap();
+        match self.wait_key() {
+            Some(Key::Char(c @ '1'..='9')) => saves[(c as u8 - b'1') as usize].take(),
+            _ => None,
+        }
+    }
+
+    /// clear the cells markers moved away from
+    fn erase_markers(&mut self, ui: &MazeUI, vacated: &[Position]) {
+        for p in vacated {
+            write!(self.stdout, "{} ", ui.locate(p)).unwrap();
+        }
+    }
+
+    fn draw_markers(&mut self, ui: &MazeUI, markers: &Markers) {
+        for m in markers.iter() {
+            let p = ui.locate(&m.pos);
+            match m.color {
+                Some(c) => write!(
+                    self.stdout,
+                    "{}{}{}{}",
+                    p,
+                    color::Fg(c),
+                    m.glyph,
+                    style::Reset
+                ),
+                None => write!(self.stdout, "{}{}", p, m.glyph),
+            }
+            .unwrap();
+        }
+    }
+
+    fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+        m: &Maze,
+        j: &Joystick,
+        solution: &[Position],
+    ) -> Result<(), SaveError> {
         let state = GameState {
+            version: SAVE_VERSION,
             maze: m.clone(),
-            difficulty: self.difficulty,
+            difficulty: self.opts.maze.difficulty,
             pos: j.pos,
             moves: j.history.clone(),
+            solution: Some(solution.to_vec()),
+            name: m.name(),
+            // only when the seed makes this very maze, a rolled or rotated one is saved whole
+            seed: MazeSeed::of(m.width, m.height, &self.opts.maze)
+                .filter(|s| matches!(s.generate(), Ok(g) if g.fingerprint() == m.fingerprint())),
+        };
+        save::save(path, &state, self.opts.save_format)
+    }
+
+    /// default save file, in the format asked for
+    fn save_file(&self) -> PathBuf {
+        let format = self.opts.save_format.unwrap_or(SaveFormat::Ron);
+        self.opts
+            .save_dir
+            .join(SAVE_FILE)
+            .with_extension(format.extension())
+    }
+
+    /// File of a won run on the maze with the fingerprint, without the extension. Named
+    /// after the maze and the time of the win so no run overwrites another.
+    fn run_file(&self, fingerprint: u64) -> PathBuf {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        self.opts
+            .runs_dir
+            .join(format!("{:016x}-{}", fingerprint, millis))
+    }
+
+    /// file of the numbered save slot
+    fn slot_file(&self, n: u8) -> PathBuf {
+        let format = self.opts.save_format.unwrap_or(SaveFormat::Ron);
+        self.opts
+            .save_dir
+            .join(format!("slot{}", n))
+            .with_extension(format.extension())
+    }
+
+    /// Save the game and tell how it went, the saved hook runs on success.
+    fn save_game(
+        &mut self,
+        ui: &MazeUI,
+        path: &Path,
+        m: &Maze,
+        j: &Joystick,
+        solution: &[Position],
+    ) {
+        if let Err(e) = self.save(path, m, j, solution) {
+            self.message(ui, format!("Couldn't save: {}", e));
+            return;
+        }
+        self.message(ui, format!("Saved {}", path.display()));
+        let event = Event::Saved {
+            path: path.display().to_string(),
         };
-        let out = File::create("maze.ron").unwrap();
-        ron::ser::to_writer(out, &state).unwrap();
+        if let Err(e) = self.opts.hooks.run(&event) {
+            self.message(ui, format!("Couldn't run the saved hook: {}", e));
+        }
+    }
+
+    /// Read a command line typed after `:` on the message pane, None when cancelled with
+    /// escape.
+    fn prompt(&mut self, ui: &MazeUI) -> Option<String> {
+        let top = ui.dimensions().1 + 3;
+        let mut line = String::new();
+        loop {
+            write!(
+                self.stdout,
+                "{}{}:{}",
+                Goto(1, top),
+                clear::CurrentLine,
+                line
+            )
+            .unwrap();
+            self.stdout.flush().unwrap();
+            match self.wait_key()? {
+                Key::Char('\n') => break,
+                Key::Esc => {
+                    line.clear();
+                    break;
+                }
+                Key::Backspace => {
+                    line.pop();
+                }
+                Key::Char(c) => line.push(c),
+                _ => (),
+            }
+        }
+        self.draw_messages(ui);
+        Some(line).filter(|l| !l.trim().is_empty())
+    }
+
+    /// The next maze of the current size, None after showing why it can't be generated.
+    fn take_maze(&mut self, opts: &Opts) -> Option<Maze> {
+        match self.pool.take(self.width, self.height, opts) {
+            Ok(m) => Some(m),
+            Err(e) => {
+                write!(
+                    self.stdout,
+                    "{}{}{} (press any key)",
+                    clear::All,
+                    Goto(1, 1),
+                    e
+                )
+                .unwrap();
+                self.stdout.flush().unwrap();
+                self.wait_key();
+                None
+            }
+        }
     }
 
-    /// generate maze and start game loop
-    fn start(&mut self, state: Option<&GameState>) -> GameCommand {
+    /// Show generated mazes with their metrics until one is accepted with enter, n rerolls
+    /// and q quits with None.
+    fn preview(&mut self) -> Option<Maze> {
+        // seeded rerolls move on to the next seed
+        let opts = self.opts.maze.clone();
+        let roll_opts = |roll: u64| Opts {
+            seed: opts.seed.map(|s| s.wrapping_add(roll)),
+            ..opts.clone()
+        };
+        for roll in 0.. {
+            let maze = self.take_maze(&roll_opts(roll))?;
+            self.pool
+                .prefetch(self.width, self.height, &roll_opts(roll + 1));
+            let ui = maze.ui_sized(self.cell_size());
+            write!(self.stdout, "{}", clear::All).unwrap();
+            self.draw_maze(&ui);
+            self.message(
+                &ui,
+                format!(
+                    "{} {}x{}: difficulty {}, shortest path {} moves",
+                    maze.name(),
+                    maze.width,
+                    maze.height,
+                    maze.decisions(),
+                    maze.solution_length().unwrap_or(0)
+                ),
+            );
+            self.message(&ui, "Enter: play, n: reroll, q: quit");
+            self.stdout.flush().unwrap();
+            loop {
+                let k = self.wait_key()?;
+                match (k, self.opts.keys.action(&k)) {
+                    (Key::Char('\n'), _) => {
+                        write!(self.stdout, "{}", clear::All).unwrap();
+                        return Some(maze);
+                    }
+                    (_, Some(Action::NewGame)) => break,
+                    (_, Some(Action::Quit)) => return None,
+                    _ => (),
+                }
+            }
+        }
+        None
+    }
+
+    /// Generate the maze and start the game loop. Returns what the player picked once done
+    /// with the maze and the run kept, None when the exit wasn't reached or the run isn't
+    /// counted.
+    fn start(&mut self, state: Option<&GameState>) -> (GameCommand, Option<Record>) {
+        let mut kept = None;
+        let command = self.play(state, &mut kept);
+        (command, kept)
+    }
+
+    fn play(&mut self, state: Option<&GameState>, kept: &mut Option<Record>) -> GameCommand {
         let maze = match state {
             Some(gs) => gs.maze.clone(),
-            None => Maze::generate(
-                self.width,
-                self.height,
-                &Opts {
-                    difficulty: self.difficulty,
-                },
-            ),
+            None if self.opts.preview => match self.preview() {
+                Some(m) => m,
+                None => return Quit,
+            },
+            None => {
+                let m = match self.take_maze(&self.opts.maze.clone()) {
+                    Some(m) => m,
+                    None => return Quit,
+                };
+                // a seeded maze would only come out the same again
+                if self.opts.maze.seed.is_none() {
+                    self.pool.prefetch(self.width, self.height, &self.opts.maze);
+                }
+                m
+            }
         };
+        // saves made before the solution was cached, or with one that doesn't check out, solve
+        // it once
+        let solution = state
+            .and_then(|gs| gs.solution().map(|s| s.to_vec()))
+            .or_else(|| maze.solution())
+            .unwrap_or_default();
+        // moves of the shortest path
+        let par = solution.len().saturating_sub(1);
         let mut joystick = maze.joystick();
+        joystick.assist = self.opts.assist;
         if let Some(gs) = state {
-            joystick.pos = gs.pos;
+            let inside = gs.pos.x < maze.width && gs.pos.y < maze.height;
+            match gs.resume(&mut joystick) {
+                Ok(()) => (),
+                Err(e) if inside && !self.confirm(format!("{}, start from the entrance?", e)) => {
+                    joystick.pos = gs.pos
+                }
+                Err(_) => (),
+            }
         }
-        let ui = maze.ui();
-        self.draw_maze(&ui);
-        write!(self.stdout, "{}", ui.locate(&joystick)).unwrap();
+        let fingerprint = maze.fingerprint();
+        self.maze_id = maze.short_fingerprint();
+        let best = self.records.get(fingerprint).copied();
+        let mut started: Option<Instant> = None;
+        let views = engine::views(&maze, &self.opts);
+        let uis: Vec<MazeUI> = views.iter().map(|v| v.ui_sized(self.cell_size())).collect();
+        // a restored game is recorded from where it was left
+        let mut engine = Engine::new(&views, joystick, &self.opts);
+        let mut ui = &uis[0];
+        self.draw_walls(ui, &engine);
+        self.draw_status(ui, &engine.view(), par, Duration::default(), best);
+        let name = maze.name();
+        self.message(ui, format!("Welcome to {}", name));
+        match best {
+            Some(_) => self.message(ui, "Welcome back, beat your best!"),
+            None => self.message(
+                ui,
+                format!(
+                    "New {}x{} maze, shortest path {} moves",
+                    maze.width, maze.height, par
+                ),
+            ),
+        }
+        if !engine.course().is_complete() {
+            self.message(
+                ui,
+                format!(
+                    "Visit the {} checkpoints to unlock the exit",
+                    engine.course().remaining()
+                ),
+            );
+        }
+        if engine.puzzle().is_some() {
+            self.message(
+                ui,
+                "Co-op: hold the plate ○ to open the door ▒ for your partner, Tab switches player",
+            );
+        } else if self.opts.coop {
+            self.message(
+                ui,
+                "Co-op: loops go around every door of this maze, no puzzle",
+            );
+        }
+        let mut recording = Instant::now();
+        // a restored game was counted when it was begun
+        let mut begun = engine.joystick().move_count() > 0;
+        // lines of the victory screen, shown once the run is over
+        let mut summary: Vec<String> = Vec::new();
+        // markers of the solution while it is shown, peeking at it or at a hint keeps the
+        // run off the records
+        let mut solution_markers: Vec<usize> = Vec::new();
+        let mut peeked = false;
+        if engine.treasure().is_some() {
+            self.message(
+                ui,
+                "A treasure is hidden somewhere, find it before the exit",
+            );
+        }
+        write!(self.stdout, "{}", ui.locate(engine.joystick())).unwrap();
         self.stdout.flush().unwrap();
+        if let Some(secs) = self.opts.memory {
+            std::thread::sleep(Duration::from_secs(secs));
+            engine.hide_walls();
+            self.draw_walls(ui, &engine);
+            self.message(ui, "The walls are gone, find your way from memory");
+            write!(self.stdout, "{}", ui.locate(engine.joystick())).unwrap();
+            self.stdout.flush().unwrap();
+        }
+        // start of the last idle frame and the clock it showed, in tenths of a second
+        let mut frame = Instant::now();
+        let mut shown = None;
         loop {
             // Read a single byte from stdin.
-            let b = self.stdin.next().unwrap().unwrap();
-            use termion::event::Key::*;
-            match b {
-                Char('h' | 'a') | Left => {
-                    joystick.left();
+            let b = match self.stdin.next() {
+                Some(Ok(k)) => k,
+                Some(Err(_)) => continue,
+                None => {
+                    // no key yet, keep the clock running but only draw it when it changes
+                    if let (Some(s), false) = (started, engine.is_finished()) {
+                        let tenths = s.elapsed().as_millis() / 100;
+                        if shown != Some(tenths) {
+                            shown = Some(tenths);
+                            let view = engine.view();
+                            self.draw_status(ui, &view, par, s.elapsed(), best);
+                            write!(self.stdout, "{}", ui.locate(&view)).unwrap();
+                            self.stdout.flush().unwrap();
+                        }
+                    }
+                    // sleep what is left of the frame so drawing doesn't add up
+                    std::thread::sleep(self.frame_time().saturating_sub(frame.elapsed()));
+                    frame = Instant::now();
+                    continue;
                 }
-                Char('j' | 's') | Down => {
-                    joystick.down();
+            };
+            let action = self.opts.keys.action(&b);
+            let mut hint = None;
+            let mut breadcrumbs = Vec::new();
+            let before = match action {
+                Some(Action::Undo) => ui.trail(&engine.view()),
+                _ => Vec::new(),
+            };
+            let events = engine.act(action, recording.elapsed());
+            ui = &uis[engine.rotation().quarter_turns() as usize];
+            // the clock starts with the first move
+            if started.is_none() && engine.joystick().move_count() > 0 {
+                started = Some(Instant::now());
+            }
+            let elapsed = started.map(|s| s.elapsed()).unwrap_or_default();
+            let mut redraw = false;
+            for event in events {
+                match event {
+                    EngineEvent::Stepped(Step::Move(_)) => {
+                        if !begun {
+                            begun = true;
+                            self.stats.begin();
+                            self.save_stats();
+                        }
+                        self.stats.moves += 1;
+                        self.cue(Cue::Step);
+                    }
+                    EngineEvent::Stepped(Step::Undo) => {
+                        // erase the undone segment, the rest of the trail is drawn back below
+                        let after = ui.trail(&engine.view());
+                        self.transient
+                            .extend(before.iter().filter(|p| !after.contains(p)));
+                    }
+                    EngineEvent::Stepped(Step::Reset) => (),
+                    EngineEvent::Blocked => self.cue(Cue::Bump),
+                    EngineEvent::Bumped(p, d) => {
+                        self.draw_bump(ui, p, &d);
+                        self.cue(Cue::Bump);
+                    }
+                    EngineEvent::DoorClosed => {
+                        self.cue(Cue::Bump);
+                        self.message(ui, "The door is closed, someone has to stand on the plate");
+                    }
+                    EngineEvent::Turned => {
+                        write!(self.stdout, "{}", clear::All).unwrap();
+                        self.path_visible = false;
+                        redraw = true;
+                        self.message(ui, "The maze turns!");
+                    }
+                    EngineEvent::Reached { exit, left } => {
+                        self.cue(Cue::Pickup);
+                        let stop = if exit { "Exit" } else { "Checkpoint" };
+                        self.message(ui, format!("{} reached, {} left", stop, left));
+                    }
+                    EngineEvent::Locked => {
+                        let locked = if self.opts.all_exits {
+                            "Every exit has to be reached, and the checkpoints visited"
+                        } else {
+                            "The exit is locked, visit all checkpoints first"
+                        };
+                        self.message(ui, locked);
+                    }
+                    EngineEvent::TreasureFound => {
+                        self.cue(Cue::Pickup);
+                        self.message(ui, "You found the treasure! Your score is doubled");
+                    }
+                    EngineEvent::TreasureHint(h) => self.message(ui, h.to_string()),
+                    EngineEvent::ReturnAbandoned => self.message(ui, "Return trip abandoned"),
+                    EngineEvent::Won => {
+                        let mut run = Record::new(engine.joystick().move_count(), elapsed);
+                        self.cue(Cue::Victory);
+                        self.message(
+                            ui,
+                            format!(
+                                "You escaped {} #{} in {} moves, {:.1}s",
+                                name,
+                                self.maze_id,
+                                run.moves,
+                                run.time().as_secs_f32()
+                            ),
+                        );
+                        self.message(
+                            ui,
+                            format!(
+                                "Rating: {} ({}/{} moves)",
+                                Rating::of(run.moves, par),
+                                run.moves,
+                                par
+                            ),
+                        );
+                        summary.push(format!("You escaped {} #{}!", name, self.maze_id));
+                        summary.push(format!(
+                            "{} moves in {:.1}s, the shortest way is {}: {}",
+                            run.moves,
+                            run.time().as_secs_f32(),
+                            par,
+                            Rating::of(run.moves, par)
+                        ));
+                        if let Some(b) = best {
+                            summary.push(format!(
+                                "Best before: {} moves in {:.1}s",
+                                b.moves,
+                                b.time().as_secs_f32()
+                            ));
+                        }
+                        // swapping players makes the steps of a co-op run meaningless
+                        let run_file = self.run_file(fingerprint);
+                        if engine.puzzle().is_none() {
+                            let path = run_file.with_extension("rmz");
+                            match engine.replay().save(&path) {
+                                Ok(()) => {
+                                    self.message(ui, format!("Replay saved to {}", path.display()))
+                                }
+                                Err(e) => {
+                                    self.message(ui, format!("Couldn't save the replay: {}", e))
+                                }
+                            }
+                            if let Some(path) = &self.opts.habits_file {
+                                let mut habits = Habits::load_or_recover(path);
+                                habits.record(&maze, &engine.joystick().history);
+                                let _ = habits.save(path);
+                            }
+                        }
+                        if let Some(t) = &self.opts.tournament {
+                            if peeked {
+                                self.message(
+                                    ui,
+                                    "A hint or the solution was shown, the result isn't signed",
+                                );
+                            } else {
+                                let signed = t.sign(RunResult {
+                                    seed: t.seed,
+                                    fingerprint,
+                                    moves: run.moves,
+                                    millis: run.millis,
+                                    replay_hash: tournament::replay_hash(engine.replay()),
+                                });
+                                let path = run_file.with_extension("ron");
+                                match signed.save(&path) {
+                                    Ok(()) => self.message(
+                                        ui,
+                                        format!("Signed result saved to {}", path.display()),
+                                    ),
+                                    Err(e) => {
+                                        self.message(ui, format!("Couldn't save the result: {}", e))
+                                    }
+                                }
+                            }
+                        }
+                        let event = Event::Won {
+                            maze: name.clone(),
+                            fingerprint,
+                            moves: run.moves,
+                            millis: run.millis,
+                            par,
+                        };
+                        if let Err(e) = self.opts.hooks.run(&event) {
+                            self.message(ui, format!("Couldn't run the won hook: {}", e));
+                        }
+                        if engine.treasure().is_some() || self.opts.return_trip {
+                            let found = matches!(engine.treasure(), Some(t) if t.is_found());
+                            let score = treasure::score(par, run.moves, found);
+                            run = run.scored(score);
+                            self.message(ui, format!("Score: {}", score));
+                            summary.push(format!("Score: {}", score));
+                        }
+                        if !engine.course().checkpoints().is_empty() {
+                            if let Some(optimal) = engine.course().optimal_tour(&maze) {
+                                self.message(ui, format!("Optimal tour is {} moves", optimal));
+                            }
+                        }
+                        if self.opts.return_trip && engine.puzzle().is_none() {
+                            // the run is kept once back, with its score doubled
+                            engine.start_return(run);
+                            redraw = true;
+                            self.show_path = false;
+                            self.message(
+                                ui,
+                                "Return trip: find the entrance again through the fog, without your trail, to double it",
+                            );
+                        } else {
+                            let counted = !peeked && engine.puzzle().is_none();
+                            if let Some(lines) =
+                                self.keep_run(ui, &maze, run, counted, engine.course())
+                            {
+                                *kept = Some(run);
+                                summary.extend(lines);
+                            }
+                        }
+                    }
+                    EngineEvent::Back(run) => {
+                        self.cue(Cue::Victory);
+                        let run = run.scored(run.score.unwrap_or(0) * 2);
+                        let back = format!(
+                            "Back at the entrance in {} moves, score doubled: {}",
+                            engine.joystick().move_count() - run.moves,
+                            run.score.unwrap_or(0)
+                        );
+                        self.message(ui, back.clone());
+                        summary.push(back);
+                        if let Some(lines) = self.keep_run(ui, &maze, run, !peeked, engine.course())
+                        {
+                            *kept = Some(run);
+                            summary.extend(lines);
+                        }
+                    }
+                    EngineEvent::DoorOpened => self.message(ui, "The door opens"),
+                    EngineEvent::DoorShut => self.message(ui, "The door closes"),
+                    EngineEvent::Caught => {
+                        self.cue(Cue::Bump);
+                        self.message(ui, "Caught by an enemy, back to the entrance");
+                    }
+                    EngineEvent::Redraw => redraw = true,
                 }
-                Char('k' | 'w') | Up => {
-                    joystick.up();
+            }
+            if redraw {
+                self.draw_walls(ui, &engine);
+            }
+            match action {
+                Some(Action::Save) => {
+                    let path = self.save_file();
+                    self.save_game(ui, &path, &maze, engine.joystick(), &solution)
                 }
-                Char('l' | 'd') | Right => {
-                    joystick.right();
+                Some(Action::SaveSlot(n)) => {
+                    let path = self.slot_file(n);
+                    self.save_game(ui, &path, &maze, engine.joystick(), &solution)
                 }
-                Char('r') => {
-                    joystick.reset();
+                Some(Action::LoadSlot) => match self.pick_slot() {
+                    Some(gs) => return Load(Box::new(gs)),
+                    None => self.redraw(ui, &engine),
+                },
+                Some(Action::Replay) => {
+                    play_replay(
+                        &mut self.stdout,
+                        &mut self.stdin,
+                        engine.replay(),
+                        None,
+                        self.opts.reduce_motion,
+                    );
+                    self.redraw(ui, &engine);
                 }
-                Char('e') => {
-                    self.save(&maze, &joystick);
+                Some(Action::Command) => {
+                    let command = match self.prompt(ui) {
+                        Some(line) => line.parse::<palette::Command>(),
+                        None => continue,
+                    };
+                    match command {
+                        Ok(palette::Command::Save(path)) => {
+                            let path = path.unwrap_or_else(|| self.save_file());
+                            self.save_game(ui, &path, &maze, engine.joystick(), &solution);
+                        }
+                        Ok(palette::Command::Seed(None)) => match self.opts.maze.seed {
+                            Some(seed) => self.message(ui, format!("Seed: {}", seed)),
+                            None => self.message(ui, "No seed, :seed NUMBER plays a seeded maze"),
+                        },
+                        Ok(palette::Command::Seed(Some(_)) | palette::Command::Size(..))
+                            if self.opts.tournament.is_some() =>
+                        {
+                            self.message(ui, "The maze of a tournament can't be changed");
+                        }
+                        Ok(palette::Command::Seed(seed)) => {
+                            self.opts.maze.seed = seed;
+                            return NewGame;
+                        }
+                        Ok(palette::Command::Theme(name)) => match Theme::named(&name) {
+                            Some(theme) => {
+                                self.theme = theme;
+                                self.draw_walls(ui, &engine);
+                                self.draw_path(ui, &engine.view(), self.path_visible);
+                            }
+                            None => self.message(ui, format!("Unknown theme {}", name)),
+                        },
+                        Ok(palette::Command::Size(w, h)) => {
+                            let (w, h) = self.fit(w, h);
+                            self.width = w;
+                            self.height = h;
+                            write!(self.stdout, "{}", clear::All).unwrap();
+                            return NewGame;
+                        }
+                        Err(e) => self.message(ui, e.to_string()),
+                    }
                 }
-                Char('p') => {
+                Some(Action::Stats) => self.show_stats(ui, &engine),
+                Some(Action::Help) => {
+                    // the clocks stop while paused
+                    let paused = self.show_help(ui, &engine);
+                    started = started.map(|s| s + paused);
+                    recording += paused;
+                }
+                Some(Action::Messages) => self.show_history(ui, &engine),
+                Some(Action::TogglePath) => {
                     self.show_path = !self.show_path;
                 }
-                Char('n') => return NewGame,
-                Char('q') => return Quit,
+                Some(Action::ToggleSolution) if solution_markers.is_empty() => {
+                    if !peeked {
+                        peeked = true;
+                        self.message(ui, "Solution shown, this run won't set a record");
+                    }
+                    // every cell of the way but the exit
+                    for &p in solution.iter().take(solution.len().saturating_sub(1)) {
+                        solution_markers.push(engine.markers_mut().add(Marker {
+                            pos: p,
+                            glyph: '∙',
+                            color: Some(color::AnsiValue(5)),
+                        }));
+                    }
+                }
+                Some(Action::Hint) => {
+                    if !peeked {
+                        peeked = true;
+                        self.message(ui, "Hint shown, this run won't set a record");
+                    }
+                    let pos = engine.joystick().pos;
+                    hint = maze
+                        .next_move(pos)
+                        .and_then(|d| maze.move_pos(pos, &d))
+                        .map(|p| engine.turn(p));
+                }
+                Some(Action::Breadcrumbs) => match engine.joystick().breadcrumbs() {
+                    Some(path) if path.len() > 1 => {
+                        breadcrumbs = path.into_iter().map(|p| engine.turn(p)).collect()
+                    }
+                    Some(_) => self.message(ui, "There is a way not taken yet right here"),
+                    None => self.message(ui, "Every way off the visited cells was taken"),
+                },
+                Some(Action::ToggleSolution) => {
+                    for id in solution_markers.drain(..) {
+                        engine.markers_mut().remove(id);
+                    }
+                }
+                Some(Action::NewGame | Action::Quit) if engine.is_returning() => {
+                    // leaving on the way back keeps the run with the score of the exit
+                    if let Some(run) = engine.take_return() {
+                        if self
+                            .keep_run(ui, &maze, run, !peeked, engine.course())
+                            .is_some()
+                        {
+                            *kept = Some(run);
+                        }
+                    }
+                    return if action == Some(Action::Quit) {
+                        Quit
+                    } else {
+                        NewGame
+                    };
+                }
+                Some(Action::NewGame) => return NewGame,
+                Some(Action::Quit) => {
+                    if let (Some(path), false) = (self.opts.autosave.clone(), engine.is_finished())
+                    {
+                        // quitting must work even when the game can't be kept
+                        let _ = self.save(path, &maze, engine.joystick(), &solution);
+                    }
+                    return Quit;
+                }
                 _ => (),
             }
 
-            self.draw_path(&ui, &joystick, joystick.is_exit() || self.show_path);
+            if !engine.is_finished() {
+                self.draw_status(ui, &engine.view(), par, elapsed, best);
+            }
+            let vacated = engine.take_vacated();
+            self.erase_markers(ui, &vacated);
+            // the trail would show the way back
+            let show = (engine.is_finished() || self.show_path) && !engine.is_returning();
+            let view = engine.view();
+            self.draw_path(ui, &view, show);
+            self.draw_markers(ui, &engine.turned_markers());
+            if let Some(p) = hint {
+                self.draw_hint(ui, p);
+            }
+            self.draw_breadcrumbs(ui, &breadcrumbs);
+            if self.opts.large_print {
+                self.draw_player(ui, &view);
+            }
+            if let Some(hook) = self.hook.as_mut() {
+                hook(&engine.frame(ui, show));
+            }
             // Make sure the cursor is placed on the current position.
-            write!(self.stdout, "{}", ui.locate(&joystick)).unwrap();
+            write!(self.stdout, "{}", ui.locate(&view)).unwrap();
             self.stdout.flush().unwrap();
+            // the return trip goes on after the exit
+            if engine.is_finished() && !engine.is_returning() {
+                return self.victory(ui, &summary);
+            }
         }
     }
 }
+
+/// Play a replay back, `keys` must not block when no key is pressed. Space pauses, l/→
+/// fast-forwards, h/← rewinds to the previous keyframe, +/- change the speed, 0-9 jump
+/// to 0%-90% and q quits.
+pub fn play_replay<R, W>(
+    mut stdout: W,
+    mut keys: R,
+    replay: &Replay,
+    heat: Option<&Heatmap>,
+    reduce_motion: bool,
+) where
+    R: Iterator<Item = Result<Key, std::io::Error>>,
+    W: Write,
+{
+    let ui = replay.maze().ui();
+    let (width, height) = ui.dimensions();
+    let mut playback = Playback::new(replay);
+    if reduce_motion {
+        // the whole run at once and paused, h/l and the digits step through it
+        playback.jump(100);
+        playback.toggle_pause();
+    }
+    // where the players of the heatmap went, shown under the run
+    let mut overlay = heat.is_some();
+    let mut last = Instant::now();
+    let mut redraw = true;
+    write!(stdout, "{}{}", clear::All, cursor::Hide).unwrap();
+    loop {
+        while let Some(Ok(k)) = keys.next() {
+            match k {
+                Key::Char(' ') => playback.toggle_pause(),
+                Key::Char('g') => overlay = heat.is_some() && !overlay,
+                Key::Char('l') | Key::Right => playback.fast_forward(),
+                Key::Char('h') | Key::Left => playback.rewind(replay),
+                Key::Char('+') => playback.faster(),
+                Key::Char('-') => playback.slower(),
+                Key::Char(c @ '0'..='9') => playback.jump((c as usize - '0' as usize) * 10),
+                Key::Char('q') => {
+                    write!(stdout, "{}{}{}", clear::All, cursor::Show, Goto(1, 1)).unwrap();
+                    return;
+                }
+                _ => (),
+            }
+            redraw = true;
+        }
+        let now = Instant::now();
+        redraw |= playback.tick(now - last);
+        last = now;
+
+        if redraw {
+            let j = replay.joystick_at(playback.step());
+            write!(stdout, "{}", Goto(1, 1)).unwrap();
+            let frame = ui.frame(&j, true);
+            let rows = match heat {
+                Some(heat) if overlay => heat.overlay(frame, true),
+                _ => frame.into_iter().map(|r| r.into_iter().collect()).collect(),
+            };
+            for row in rows {
+                write!(stdout, "{}\n\r", row).unwrap();
+            }
+            write!(
+                stdout,
+                "{}{}{}{}{}space: pause, h/l: rewind/forward, +/-: speed, 0-9: jump, {}q: quit",
+                Goto(1, height + 2),
+                clear::CurrentLine,
+                playback.progress_bar(width.saturating_sub(24) as usize),
+                Goto(1, height + 3),
+                clear::CurrentLine,
+                if heat.is_some() { "g: heatmap, " } else { "" },
+            )
+            .unwrap();
+            stdout.flush().unwrap();
+            redraw = false;
+        }
+        std::thread::sleep(PLAYBACK_TICK);
+    }
+}
+
+/// Show two solvers searching the maze side by side, `delay` apart. With reduced motion
+/// only the finished race is shown.
+pub fn play_race<R, W>(
+    mut stdout: W,
+    mut keys: R,
+    maze: &Maze,
+    solvers: (Solver, Solver),
+    delay: Duration,
+    reduce_motion: bool,
+) where
+    R: Iterator<Item = Result<Key, std::io::Error>>,
+    W: Write,
+{
+    let ui = maze.ui();
+    let (_, height) = ui.dimensions();
+    let mut race = Race::new(maze, solvers.0, solvers.1);
+    while reduce_motion && !race.is_done() {
+        race.step();
+    }
+    let mut paused = false;
+    let mut last = Instant::now();
+    let mut redraw = true;
+    write!(stdout, "{}{}", clear::All, cursor::Hide).unwrap();
+    loop {
+        while let Some(Ok(k)) = keys.next() {
+            match k {
+                Key::Char(' ') => paused = !paused,
+                Key::Char('q') => {
+                    write!(stdout, "{}{}{}", clear::All, cursor::Show, Goto(1, 1)).unwrap();
+                    return;
+                }
+                _ => (),
+            }
+            redraw = true;
+        }
+        if !paused && !race.is_done() && last.elapsed() >= delay {
+            race.step();
+            last = Instant::now();
+            redraw = true;
+        }
+        if !redraw {
+            std::thread::sleep(PLAYBACK_TICK);
+            continue;
+        }
+
+        write!(stdout, "{}", Goto(1, 1)).unwrap();
+        for row in race.render(&ui) {
+            write!(stdout, "{}\n\r", row).unwrap();
+        }
+        // legend with the colors of the racers
+        let status = |r: &Racer, bg: &str| {
+            format!(
+                "{}  {} {}: {} cells{}",
+                bg,
+                style::Reset,
+                r.solver.name(),
+                r.steps(),
+                if r.is_done() { ", done" } else { "" }
+            )
+        };
+        write!(
+            stdout,
+            "{}{}{}   {}{}{}space: pause, q: quit",
+            Goto(1, height + 2),
+            clear::CurrentLine,
+            status(&race.racers[0], color::Blue.bg_str()),
+            status(&race.racers[1], color::Red.bg_str()),
+            Goto(1, height + 3),
+            clear::CurrentLine,
+        )
+        .unwrap();
+        stdout.flush().unwrap();
+        redraw = false;
+        std::thread::sleep(PLAYBACK_TICK);
+    }
+}
+
+/// Step through the generation of a maze one union of the disjoint set at a time.
+pub fn play_lesson<R, W>(mut stdout: W, mut keys: R, width: u16, height: u16, opts: &Opts)
+where
+    R: Iterator<Item = Result<Key, std::io::Error>>,
+    W: Write,
+{
+    let mut lesson = Lesson::new(width, height, opts);
+    let mut union: Option<Union> = None;
+    let mut done = false;
+    let mut count = 0;
+    let mut redraw = true;
+    write!(stdout, "{}{}", clear::All, cursor::Hide).unwrap();
+    loop {
+        while let Some(Ok(k)) = keys.next() {
+            match k {
+                Key::Char(' ') | Key::Char('\n') | Key::Char('n') if !done => {
+                    union = lesson.next_union();
+                    done = union.is_none();
+                    count += union.is_some() as usize;
+                    redraw = true;
+                }
+                Key::Char('q') => {
+                    write!(stdout, "{}{}{}", clear::All, cursor::Show, Goto(1, 1)).unwrap();
+                    return;
+                }
+                _ => (),
+            }
+        }
+        if !redraw {
+            std::thread::sleep(INPUT_TICK);
+            continue;
+        }
+
+        let maze = lesson.maze();
+        let ui = maze.ui_sized(opts.difficulty.cell_size());
+        let (_, board_height) = ui.dimensions();
+        write!(stdout, "{}", Goto(1, 1)).unwrap();
+        for row in lesson.render(&ui, union.as_ref()) {
+            write!(stdout, "{}\n\r", row).unwrap();
+        }
+        let step = match &union {
+            Some(u) => format!(
+                "union {}: wall {}-{} joins {}  {} {} cells and {}  {} {} cells",
+                count,
+                u.wall.0,
+                u.wall.1,
+                color::Blue.bg_str(),
+                style::Reset,
+                u.sets.0.len(),
+                color::Red.bg_str(),
+                style::Reset,
+                u.sets.1.len(),
+            ),
+            None if done => "the maze is done, every cell is in one set".to_string(),
+            None => "every cell starts in a set of its own".to_string(),
+        };
+        write!(
+            stdout,
+            "{}{}{}{}{}distinct_sets: {}{}, space: next union, q: quit",
+            Goto(1, board_height + 2),
+            clear::CurrentLine,
+            step,
+            Goto(1, board_height + 3),
+            clear::CurrentLine,
+            lesson.distinct_sets(),
+            if lesson.is_connected() {
+                ", entrance and exit joined"
+            } else {
+                ""
+            },
+        )
+        .unwrap();
+        stdout.flush().unwrap();
+        redraw = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::GENERATOR_VERSION;
+
+    #[test]
+    fn test_compass() {
+        let p = |x, y| Position { x, y };
+        assert_eq!(compass(p(3, 3), p(3, 3)), '•');
+        assert_eq!(compass(p(3, 3), p(9, 3)), '→');
+        assert_eq!(compass(p(3, 3), p(9, 9)), '↘');
+        assert_eq!(compass(p(3, 3), p(3, 0)), '↑');
+        assert_eq!(compass(p(3, 3), p(0, 4)), '←');
+        assert_eq!(compass(p(3, 3), p(0, 0)), '↖');
+    }
+
+    #[test]
+    fn test_state_solution() {
+        let maze = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let state = GameState {
+            version: SAVE_VERSION,
+            solution: maze.solution(),
+            maze,
+            difficulty: Difficulty::Hard,
+            pos: Position { x: 0, y: 0 },
+            moves: vec![],
+            name: String::new(),
+            seed: None,
+        };
+        let saved = ron::ser::to_string(&state).unwrap();
+        let restored: GameState = ron::de::from_str(&saved).unwrap();
+        assert_eq!(restored.solution().map(|s| s.len()), Some(4));
+        // an edited solution through a wall or off the entrance isn't trusted
+        let p = |x, y| Position { x, y };
+        let through = GameState {
+            solution: Some(vec![p(0, 0), p(0, 1), p(1, 1), p(2, 1)]),
+            ..GameState::new(state.maze.clone(), Difficulty::Hard)
+        };
+        assert_eq!(through.solution(), None);
+        let off = GameState {
+            solution: Some(vec![p(1, 0), p(2, 0), p(2, 1)]),
+            ..GameState::new(state.maze.clone(), Difficulty::Hard)
+        };
+        assert_eq!(off.solution(), None);
+
+        // saves from before the solution was cached, versions were kept and mazes were
+        // packed still load, and are brought up to date
+        let old = format!(
+            "(maze:{},difficulty:Hard,pos:(y:0,x:0),moves:[])",
+            ron::ser::to_string(&state.maze).unwrap()
+        );
+        let restored: GameState = ron::de::from_str(&old).unwrap();
+        assert_eq!(restored.solution(), None);
+        let migrated = restored.migrate().unwrap();
+        assert_eq!(migrated.version, SAVE_VERSION);
+        assert_eq!(migrated.solution().map(|s| s.len()), Some(4));
+        assert_eq!(migrated.name(), migrated.maze().name());
+        let version = format!("version:{},", SAVE_VERSION);
+        let newer = saved.replace(&version, "version:99,");
+        let restored: GameState = ron::de::from_str(&newer).unwrap();
+        assert!(matches!(
+            restored.migrate(),
+            Err(SaveError::NewerVersion(99))
+        ));
+
+        assert_eq!(state.verify(), Ok(()));
+        let moved = GameState {
+            moves: vec![
+                (Position { x: 0, y: 0 }, None),
+                (Position { x: 1, y: 0 }, Some(Direction::Right)),
+            ],
+            pos: Position { x: 1, y: 0 },
+            ..GameState::new(state.maze.clone(), Difficulty::Hard)
+        };
+        assert_eq!(moved.verify(), Ok(()));
+        // a resumed game saved again after more moves still checks out, undo goes back
+        // through the restored moves
+        let mut j = moved.maze.joystick();
+        moved.resume(&mut j).unwrap();
+        assert!(j.mv(&Direction::Right));
+        let again = GameState {
+            pos: j.pos,
+            moves: j.history.clone(),
+            ..GameState::new(state.maze.clone(), Difficulty::Hard)
+        };
+        assert_eq!(again.verify(), Ok(()));
+        assert!(j.undo() && j.undo());
+        assert_eq!(j.pos, Position { x: 0, y: 0 });
+        assert_eq!(j.move_count(), 0);
+        let warped = GameState {
+            pos: Position { x: 2, y: 1 },
+            ..GameState::new(state.maze.clone(), Difficulty::Hard)
+        };
+        assert_eq!(
+            warped.verify(),
+            Err(StateError::Position {
+                end: Position { x: 0, y: 0 },
+                pos: Position { x: 2, y: 1 }
+            })
+        );
+        let walled = GameState {
+            moves: vec![(Position { x: 0, y: 1 }, Some(Direction::Down))],
+            pos: Position { x: 0, y: 1 },
+            ..GameState::new(state.maze.clone(), Difficulty::Hard)
+        };
+        assert!(matches!(
+            walled.verify(),
+            Err(StateError::Moves(PathError::Wall { index: 0, .. }))
+        ));
+
+        let mut json = Vec::new();
+        SaveFormat::Json.write(&mut json, &state).unwrap();
+        let restored: GameState = SaveFormat::Json.read(&json[..]).unwrap();
+        assert_eq!(restored.solution().map(|s| s.len()), Some(4));
+    }
+
+    #[test]
+    fn test_state_encoding() {
+        let opts = Opts {
+            seed: Some(2),
+            ..Default::default()
+        };
+        let state = GameState::new(Maze::generate(40, 30, &opts).unwrap(), Difficulty::Hard);
+        let walls = ron::ser::to_string(&state.maze).unwrap().len();
+        let saved = ron::ser::to_string(&state).unwrap();
+        assert!(saved.contains("encoding:Packed,packed:"));
+        assert!(!saved.contains("walls:["));
+        assert!(saved.len() < walls);
+        let restored: GameState = ron::de::from_str(&saved).unwrap();
+        assert_eq!(restored.maze().fingerprint(), state.maze().fingerprint());
+
+        // a seeded maze is saved as its seed and found again
+        let seeded = GameState {
+            seed: MazeSeed::of(40, 30, &opts),
+            ..state.clone()
+        };
+        let saved_seed = ron::ser::to_string(&seeded).unwrap();
+        assert!(saved_seed.contains(&format!(
+            "encoding:Seed,seed:(generator:{},seed:2,",
+            GENERATOR_VERSION
+        )));
+        assert!(saved_seed.len() < 350);
+        let restored: GameState = ron::de::from_str(&saved_seed).unwrap();
+        assert_eq!(restored.maze().fingerprint(), state.maze().fingerprint());
+        assert_eq!(restored.solution(), state.solution());
+
+        // a seed of another generator is only trusted when it still makes the saved maze,
+        // and is saved packed from then on
+        let older = saved_seed.replace(
+            &format!("generator:{},", GENERATOR_VERSION),
+            &format!("generator:{},", GENERATOR_VERSION - 1),
+        );
+        let restored: GameState = ron::de::from_str(&older).unwrap();
+        assert_eq!(restored.maze().fingerprint(), state.maze().fingerprint());
+        let resaved = ron::ser::to_string(&restored).unwrap();
+        assert!(resaved.contains("encoding:Packed,packed:"));
+        let fingerprint = format!("fingerprint:{},", state.maze().fingerprint());
+        let changed = older.replace(&fingerprint, "fingerprint:1,");
+        assert!(matches!(
+            ron::de::from_str::<GameState>(&changed),
+            Err(e) if e.to_string().contains("generator 1")
+        ));
+
+        let missing = saved.replace(
+            &saved[saved.find("packed:").unwrap()..saved.find("difficulty:").unwrap()],
+            "",
+        );
+        assert!(ron::de::from_str::<GameState>(&missing).is_err());
+    }
+}
@@ -0,0 +1,476 @@
+use std::collections::{HashSet, VecDeque};
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use thiserror::Error;
+
+use crate::disjset::DisjSet;
+use crate::disjset::Roots::DisJoint;
+use crate::maze::{Difficulty, Opts};
+
+#[derive(Error, Debug)]
+pub enum GridError {
+    #[error("invalid grid, expected square, hex or polar")]
+    GridParseError,
+    #[error("{0} mazes can only be exported as {1}")]
+    FormatOnly(&'static str, &'static str),
+}
+
+/// Shape of the maze cells, the square grid is the one of `Maze`. The others are carved
+/// as a `GridMaze` and only drawn by the export, `Maze`, `MazeUI` and `Direction` the game
+/// plays on stay square.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Grid {
+    Square,
+    /// six sided cells, see `Hex`
+    Hex,
+    /// concentric rings, see `Polar`
+    Polar,
+}
+
+impl Grid {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Grid::Square => "square",
+            Grid::Hex => "hex",
+            Grid::Polar => "polar",
+        }
+    }
+}
+
+impl FromStr for Grid {
+    type Err = GridError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Grid::Square),
+            "hex" => Ok(Grid::Hex),
+            "polar" | "circle" => Ok(Grid::Polar),
+            _ => Err(GridError::GridParseError),
+        }
+    }
+}
+
+/// How the cells of a maze are laid out and which ones are next to each other. Cells
+/// are numbered from 0, the entrance is the first and the exit the last.
+pub trait Topology {
+    /// number of cells
+    fn len(&self) -> usize;
+
+    /// cells sharing a wall with the cell
+    fn neighbours(&self, cell: usize) -> Vec<usize>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Maze carved on any topology, cells connect through the passages between neighbours.
+#[derive(Clone, Debug)]
+pub struct GridMaze<T> {
+    pub topology: T,
+    /// pairs of neighbours without a wall between them, the lower cell first
+    passages: HashSet<(usize, usize)>,
+}
+
+impl<T: Topology> GridMaze<T> {
+    /// Carve a maze with randomized Kruskal: knock down walls between cells of different
+    /// sets until every cell is connected. Easy mazes get extra openings like `Maze`,
+    /// the algorithm of the options is ignored.
+    pub fn generate(topology: T, opts: &Opts) -> GridMaze<T> {
+        let mut rng = match opts.seed {
+            Some(seed) => Pcg64::seed_from_u64(seed),
+            None => Pcg64::from_entropy(),
+        };
+        let mut walls: Vec<(usize, usize)> = (0..topology.len())
+            .flat_map(|c| {
+                topology
+                    .neighbours(c)
+                    .into_iter()
+                    .filter(move |n| *n > c)
+                    .map(move |n| (c, n))
+            })
+            .collect();
+        walls.shuffle(&mut rng);
+        let mut cells = DisjSet::new(topology.len());
+        let mut passages = HashSet::new();
+        walls.retain(|&(a, b)| match cells.find_roots(a, b) {
+            DisJoint(r1, r2) => {
+                cells.union(r1, r2);
+                passages.insert((a, b));
+                false
+            }
+            _ => true,
+        });
+        if opts.difficulty == Difficulty::Easy {
+            passages.extend(walls.iter().take(walls.len() / 5));
+        }
+        GridMaze { topology, passages }
+    }
+
+    pub fn entrance(&self) -> usize {
+        0
+    }
+
+    pub fn exit(&self) -> usize {
+        self.topology.len().saturating_sub(1)
+    }
+
+    /// check if there is no wall between the two cells
+    pub fn is_open(&self, a: usize, b: usize) -> bool {
+        self.passages.contains(&(a.min(b), a.max(b)))
+    }
+
+    /// Shortest path from the entrance to the exit, both included.
+    pub fn solution(&self) -> Option<Vec<usize>> {
+        let mut parent = vec![None; self.topology.len()];
+        let mut queue = VecDeque::new();
+        parent[self.entrance()] = Some(self.entrance());
+        queue.push_back(self.entrance());
+        while let Some(c) = queue.pop_front() {
+            for n in self.topology.neighbours(c) {
+                if parent[n].is_none() && self.is_open(c, n) {
+                    parent[n] = Some(c);
+                    queue.push_back(n);
+                }
+            }
+        }
+        let mut path = vec![self.exit()];
+        while let Some(&c) = path.last().filter(|c| **c != self.entrance()) {
+            path.push(parent[c]?);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Grid of flat topped hexagons in columns, odd columns half a cell lower.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Hex {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Hex {
+    fn cell(&self, column: i32, row: i32) -> Option<usize> {
+        if column < 0 || row < 0 || column >= self.width as i32 || row >= self.height as i32 {
+            return None;
+        }
+        Some(row as usize * self.width as usize + column as usize)
+    }
+
+    /// column and row of the cell
+    fn coords(&self, cell: usize) -> (i32, i32) {
+        (
+            (cell % self.width as usize) as i32,
+            (cell / self.width as usize) as i32,
+        )
+    }
+
+    /// Neighbours of the cell in the order of its sides: north, north east, south east,
+    /// south, south west and north west.
+    fn sides(&self, cell: usize) -> [Option<usize>; 6] {
+        let (c, r) = self.coords(cell);
+        // odd columns are lower, their side neighbours are a row further down
+        let shift = c % 2;
+        [
+            self.cell(c, r - 1),
+            self.cell(c + 1, r - 1 + shift),
+            self.cell(c + 1, r + shift),
+            self.cell(c, r + 1),
+            self.cell(c - 1, r + shift),
+            self.cell(c - 1, r - 1 + shift),
+        ]
+    }
+
+    /// Draw the maze with slashes and underscores, the solution is dotted when given.
+    ///
+    /// ```text
+    ///  __    __
+    /// /  \__/  \
+    /// \__/  \__/
+    /// ```
+    pub fn render(maze: &GridMaze<Hex>, solution: &[usize]) -> String {
+        let hex = maze.topology;
+        let width = 3 * hex.width as usize + 1;
+        let height = 2 * hex.height as usize + 1 + (hex.width > 1) as usize;
+        let mut board = vec![vec![' '; width]; height];
+        // top left corner of the cell, on the row of its top wall
+        let corner = |cell: usize| {
+            let (c, r) = hex.coords(cell);
+            (3 * c as usize, 2 * r as usize + (c % 2) as usize)
+        };
+        // board positions of every side, in the order of `sides`
+        let sides = |cell: usize| {
+            let (x, y) = corner(cell);
+            [
+                vec![(x + 1, y), (x + 2, y)],
+                vec![(x + 3, y + 1)],
+                vec![(x + 3, y + 2)],
+                vec![(x + 1, y + 2), (x + 2, y + 2)],
+                vec![(x, y + 2)],
+                vec![(x, y + 1)],
+            ]
+        };
+        const WALLS: [char; 6] = ['_', '\\', '/', '_', '\\', '/'];
+        for cell in 0..hex.len() {
+            for (side, wall) in sides(cell).iter().zip(WALLS.iter()) {
+                for &(x, y) in side {
+                    board[y][x] = *wall;
+                }
+            }
+        }
+        for cell in 0..hex.len() {
+            let neighbours = hex.sides(cell);
+            for (i, (side, n)) in sides(cell).iter().zip(neighbours.iter()).enumerate() {
+                let open = match n {
+                    Some(n) => maze.is_open(cell, *n),
+                    // the way in at the top and out at the bottom
+                    None => (cell == maze.entrance() && i == 0) || (cell == maze.exit() && i == 3),
+                };
+                if open {
+                    for &(x, y) in side {
+                        board[y][x] = ' ';
+                    }
+                }
+            }
+        }
+        for &cell in solution {
+            let (x, y) = corner(cell);
+            board[y + 1][x + 1] = '·';
+            board[y + 1][x + 2] = '·';
+        }
+        let mut out = String::new();
+        for row in board {
+            out.push_str(row.into_iter().collect::<String>().trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Topology for Hex {
+    fn len(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    fn neighbours(&self, cell: usize) -> Vec<usize> {
+        self.sides(cell).iter().flatten().copied().collect()
+    }
+}
+
+/// Width in pixels of the rings of polar SVG images.
+const RING_PX: f64 = 24.0;
+
+/// Cells in concentric rings around a center cell. Outer rings are split into more cells
+/// so they stay about as wide as they are deep, every cell of a ring opens onto the same
+/// number of cells of the next one. The entrance is the center and the exit on the rim.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Polar {
+    /// cells of every ring, from the center out
+    counts: Vec<usize>,
+    /// first cell of every ring
+    starts: Vec<usize>,
+}
+
+impl Polar {
+    pub fn new(rings: u16) -> Polar {
+        let rings = rings.max(1) as usize;
+        let mut counts = vec![1];
+        for r in 1..rings {
+            let circumference = 2.0 * PI * r as f64;
+            let prev = counts[r - 1];
+            // split the cells of the previous ring while they get wider than a ring is deep
+            let ratio = (circumference / prev as f64).round().max(1.0) as usize;
+            counts.push(prev * ratio);
+        }
+        let starts = counts
+            .iter()
+            .scan(0, |start, n| {
+                let s = *start;
+                *start += n;
+                Some(s)
+            })
+            .collect();
+        Polar { counts, starts }
+    }
+
+    pub fn rings(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// ring of the cell and its index in the ring
+    fn locate(&self, cell: usize) -> (usize, usize) {
+        let ring = self.starts.iter().rposition(|s| *s <= cell).unwrap_or(0);
+        (ring, cell - self.starts[ring])
+    }
+
+    /// Draw the maze as an SVG image, walls are arcs and spokes and the path is a line
+    /// through the middle of its cells.
+    pub fn svg(maze: &GridMaze<Polar>, solution: &[usize]) -> String {
+        let polar = &maze.topology;
+        let size = 2.0 * (polar.rings() as f64 + 1.0) * RING_PX;
+        let center = size / 2.0;
+        let point = |radius: f64, angle: f64| {
+            format!(
+                "{:.1},{:.1}",
+                center + radius * angle.cos(),
+                center + radius * angle.sin()
+            )
+        };
+        let mut walls = Vec::new();
+        for cell in 1..polar.len() {
+            let (r, i) = polar.locate(cell);
+            let n = polar.counts[r];
+            let step = 2.0 * PI / n as f64;
+            let (from, to) = (i as f64 * step, (i + 1) as f64 * step);
+            let (inner, outer) = (r as f64 * RING_PX, (r + 1) as f64 * RING_PX);
+            let arc = |radius: f64| {
+                format!(
+                    "M{}A{:.1},{:.1} 0 0 1 {}",
+                    point(radius, from),
+                    radius,
+                    radius,
+                    point(radius, to)
+                )
+            };
+            let parent = polar.starts[r - 1] + i * polar.counts[r - 1] / n;
+            if !maze.is_open(cell, parent) {
+                walls.push(arc(inner));
+            }
+            let ccw = polar.starts[r] + (i + n - 1) % n;
+            if !maze.is_open(cell, ccw) {
+                walls.push(format!("M{}L{}", point(inner, from), point(outer, from)));
+            }
+            // the rim, open at the exit
+            if r + 1 == polar.rings() && cell != maze.exit() {
+                walls.push(arc(outer));
+            }
+        }
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n",
+            size
+        );
+        out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+        out.push_str(&format!(
+            "<path d=\"{}\" stroke=\"black\" stroke-width=\"2\" stroke-linecap=\"round\" fill=\"none\"/>\n",
+            walls.join("")
+        ));
+        if !solution.is_empty() {
+            let points: Vec<String> = solution
+                .iter()
+                .map(|&cell| match polar.locate(cell) {
+                    (0, _) => point(0.0, 0.0),
+                    (r, i) => {
+                        let step = 2.0 * PI / polar.counts[r] as f64;
+                        point((r as f64 + 0.5) * RING_PX, (i as f64 + 0.5) * step)
+                    }
+                })
+                .collect();
+            out.push_str(&format!(
+                "<polyline points=\"{}\" stroke=\"red\" stroke-width=\"4\" fill=\"none\"/>\n",
+                points.join(" ")
+            ));
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+impl Topology for Polar {
+    fn len(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    fn neighbours(&self, cell: usize) -> Vec<usize> {
+        let (r, i) = self.locate(cell);
+        let n = self.counts[r];
+        let mut neighbours = Vec::new();
+        if r > 0 {
+            for side in [(i + 1) % n, (i + n - 1) % n].iter() {
+                let c = self.starts[r] + side;
+                if c != cell && !neighbours.contains(&c) {
+                    neighbours.push(c);
+                }
+            }
+            neighbours.push(self.starts[r - 1] + i * self.counts[r - 1] / n);
+        }
+        if r + 1 < self.rings() {
+            let ratio = self.counts[r + 1] / n;
+            neighbours.extend((i * ratio..(i + 1) * ratio).map(|o| self.starts[r + 1] + o));
+        }
+        neighbours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        let hex = Hex {
+            width: 3,
+            height: 2,
+        };
+        // odd columns are half a cell lower
+        assert_eq!(hex.neighbours(0), vec![1, 3]);
+        assert_eq!(hex.neighbours(1), vec![2, 5, 4, 3, 0]);
+        assert_eq!(hex.neighbours(4), vec![1, 5, 3]);
+
+        let opts = Opts {
+            seed: Some(4),
+            ..Default::default()
+        };
+        let maze = GridMaze::generate(hex, &opts);
+        // a perfect maze has one passage less than cells
+        assert_eq!(maze.passages.len(), 5);
+        let solution = maze.solution().unwrap();
+        assert_eq!(solution.first(), Some(&0));
+        assert_eq!(solution.last(), Some(&5));
+
+        let walled = GridMaze {
+            topology: hex,
+            passages: HashSet::new(),
+        };
+        assert_eq!(walled.solution(), None);
+        assert_eq!(
+            Hex::render(&walled, &[]),
+            concat!(
+                "       __\n",
+                "/  \\__/  \\\n",
+                "\\__/  \\__/\n",
+                "/  \\__/  \\\n",
+                "\\__/  \\  /\n",
+                "   \\__/\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_polar() {
+        let polar = Polar::new(4);
+        assert_eq!(polar.counts, vec![1, 6, 12, 24]);
+        assert_eq!(polar.len(), 43);
+        // the center opens onto the whole first ring
+        assert_eq!(polar.neighbours(0), vec![1, 2, 3, 4, 5, 6]);
+        // around the ring, inwards and out
+        assert_eq!(polar.neighbours(1), vec![2, 6, 0, 7, 8]);
+        assert_eq!(polar.neighbours(42), vec![19, 41, 18]);
+
+        let opts = Opts {
+            seed: Some(6),
+            ..Default::default()
+        };
+        let maze = GridMaze::generate(polar, &opts);
+        assert_eq!(maze.passages.len(), 42);
+        let solution = maze.solution().unwrap();
+        assert_eq!(solution.first(), Some(&0));
+        assert_eq!(solution.last(), Some(&42));
+        let svg = Polar::svg(&maze, &solution);
+        assert!(svg.starts_with("<svg") && svg.contains("<polyline"));
+    }
+}
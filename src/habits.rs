@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::maze::Direction::{self, Down, Left, Right, Up};
+use crate::maze::{Maze, Position};
+
+#[derive(Error, Debug)]
+pub enum HabitsError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+}
+
+/// Where the habits are kept, `rusty-maze/habits.ron` in the data directory of the
+/// platform next to the statistics. None when there is no such directory.
+pub fn habits_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("habits.ron"))
+}
+
+/// Way a move leaves a cell compared to the move that came in.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum Turn {
+    Left,
+    Right,
+    Straight,
+    Back,
+}
+
+impl Turn {
+    /// turn from moving `from` to moving `to`
+    pub fn of(from: Direction, to: Direction) -> Turn {
+        if to == from {
+            Turn::Straight
+        } else if to == from.turn_right() {
+            Turn::Right
+        } else if to == from.turn_left() {
+            Turn::Left
+        } else {
+            Turn::Back
+        }
+    }
+}
+
+/// How a player moves, summed over the completed runs.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Habits {
+    runs: usize,
+    /// moves made in every direction
+    moves: HashMap<Direction, usize>,
+    /// moves straight back the way the previous one came
+    backtracks: usize,
+    /// ways taken out of junctions entered by a move, by turn
+    turns: HashMap<Turn, usize>,
+    /// moves out of junctions
+    junctions: usize,
+    /// moves out of junctions that led nearer an exit
+    good_choices: usize,
+}
+
+impl Habits {
+    /// Load habits from the given file, a missing file yields none.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Habits, HabitsError> {
+        match File::open(path) {
+            Ok(f) => Ok(ron::de::from_reader(BufReader::new(f))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Habits::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Load habits like `Stats::load_or_recover`, a damaged file is moved aside to
+    /// `habits.ron.corrupt` so the next run doesn't write over it.
+    pub fn load_or_recover<P: AsRef<Path>>(path: P) -> Habits {
+        Habits::load(&path).unwrap_or_else(|_| {
+            let _ = std::fs::rename(&path, path.as_ref().with_extension("ron.corrupt"));
+            Habits::default()
+        })
+    }
+
+    /// Write habits next to the file, then move them over it.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), HabitsError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("ron.tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        ron::ser::to_writer(&mut out, self)?;
+        out.flush()?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Add the moves of a completed run, as kept by `Joystick::history`.
+    pub fn record(&mut self, maze: &Maze, history: &[(Position, Option<Direction>)]) {
+        self.runs += 1;
+        let to_exit = maze.exit_distance_map();
+        let dist = |p: Position| to_exit[maze.pos_to_cell(p) as usize];
+        for pair in history.windows(2) {
+            let ((from, came), (to, d)) = (pair[0], pair[1]);
+            // resets jump back to the entrance without a move
+            let d = match d {
+                Some(d) => d,
+                None => continue,
+            };
+            *self.moves.entry(d).or_insert(0) += 1;
+            let turn = came.map(|c| Turn::of(c, d));
+            if turn == Some(Turn::Back) {
+                self.backtracks += 1;
+            }
+            if maze.movements(from).len() > 2 {
+                self.junctions += 1;
+                if let Some(t) = turn {
+                    *self.turns.entry(t).or_insert(0) += 1;
+                }
+                if matches!((dist(from), dist(to)), (Some(a), Some(b)) if b < a) {
+                    self.good_choices += 1;
+                }
+            }
+        }
+    }
+
+    /// completed runs
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// moves of every run together
+    pub fn total_moves(&self) -> usize {
+        self.moves.values().sum()
+    }
+
+    /// share of the moves in the direction
+    pub fn direction_share(&self, d: Direction) -> f32 {
+        share(self.moves.get(&d).copied().unwrap_or(0), self.total_moves())
+    }
+
+    /// share of the junctions entered by a move that were left by the turn
+    pub fn turn_share(&self, t: Turn) -> f32 {
+        share(
+            self.turns.get(&t).copied().unwrap_or(0),
+            self.turns.values().sum(),
+        )
+    }
+
+    /// share of the moves that went straight back
+    pub fn backtrack_share(&self) -> f32 {
+        share(self.backtracks, self.total_moves())
+    }
+
+    /// share of the junctions left towards an exit
+    pub fn accuracy(&self) -> f32 {
+        share(self.good_choices, self.junctions)
+    }
+}
+
+fn share(part: usize, whole: usize) -> f32 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f32 / whole as f32
+    }
+}
+
+impl fmt::Display for Habits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |s: f32| (s * 100.0).round() as u32;
+        writeln!(
+            f,
+            "{} runs, {} moves: left {}%, right {}%, up {}%, down {}%",
+            self.runs,
+            self.total_moves(),
+            percent(self.direction_share(Left)),
+            percent(self.direction_share(Right)),
+            percent(self.direction_share(Up)),
+            percent(self.direction_share(Down))
+        )?;
+        writeln!(
+            f,
+            "you went straight back on {}% of your moves",
+            percent(self.backtrack_share())
+        )?;
+        writeln!(
+            f,
+            "you turn left at {}% of junctions, right at {}%, go straight at {}% and back at {}%",
+            percent(self.turn_share(Turn::Left)),
+            percent(self.turn_share(Turn::Right)),
+            percent(self.turn_share(Turn::Straight)),
+            percent(self.turn_share(Turn::Back))
+        )?;
+        write!(
+            f,
+            "you take the way to the exit at {}% of {} junctions",
+            percent(self.accuracy()),
+            self.junctions
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_habits() {
+        assert_eq!(Turn::of(Up, Left), Turn::Left);
+        assert_eq!(Turn::of(Right, Down), Turn::Right);
+        assert_eq!(Turn::of(Down, Down), Turn::Straight);
+        assert_eq!(Turn::of(Left, Right), Turn::Back);
+
+        // the middle is a junction with dead ends up and right and the way out down
+        let walls = vec![(0, 1), (1, 2), (2, 5), (5, 8), (6, 7), (3, 6)];
+        let m = Maze::create(3, 3, walls).unwrap();
+        let p = |x, y| Position { x, y };
+        let history = vec![
+            (p(0, 0), None),
+            (p(0, 1), Some(Down)),
+            (p(1, 1), Some(Right)),
+            (p(1, 0), Some(Up)),
+            (p(1, 1), Some(Down)),
+            (p(1, 2), Some(Down)),
+            (p(2, 2), Some(Right)),
+        ];
+        let mut habits = Habits::default();
+        habits.record(&m, &history);
+        assert_eq!(habits.total_moves(), 6);
+        assert_eq!(habits.direction_share(Down), 0.5);
+        assert_eq!(habits.backtrack_share(), 1.0 / 6.0);
+        // left into the dead end the first time, straight on to the exit the second
+        assert_eq!(habits.junctions, 2);
+        assert_eq!(habits.turn_share(Turn::Left), 0.5);
+        assert_eq!(habits.accuracy(), 0.5);
+        assert!(habits
+            .to_string()
+            .contains("you turn left at 50% of junctions"));
+
+        let dir = std::env::temp_dir().join(format!("rusty_maze_habits_{}", std::process::id()));
+        let path = dir.join("habits.ron");
+        habits.save(&path).unwrap();
+        assert_eq!(Habits::load_or_recover(&path), habits);
+        std::fs::write(&path, "(runs: 1, moves:").unwrap();
+        assert!(Habits::load(&path).is_err());
+        assert_eq!(Habits::load_or_recover(&path), Habits::default());
+        assert!(dir.join("habits.ron.corrupt").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
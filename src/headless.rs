@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use termion::event::Key;
+
+use crate::capture::{Frame, FrameHook};
+use crate::engine::Engine;
+use crate::entity::Entities;
+use crate::game::{Action, GameOpts};
+use crate::keymap::KeyMap;
+use crate::maze::{Direction, Joystick, Markers, Maze, MazeUI};
+use crate::orienteering::Course;
+use crate::replay::Replay;
+
+/// Time between two key presses in the replay of a headless game.
+const KEY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Game driver without a terminal. Keys go through the same engine as the interactive
+/// game and every rendered frame is captured as a string so it can be compared or diffed.
+pub struct Headless<'a> {
+    engine: Engine<'a>,
+    /// one per turn of the maze
+    uis: Vec<MazeUI<'a>>,
+    show_path: bool,
+    presses: u32,
+    frames: Vec<String>,
+    hook: Option<FrameHook>,
+    keys: KeyMap,
+}
+
+impl<'a> Headless<'a> {
+    pub fn new(maze: &'a Maze) -> Headless<'a> {
+        Headless::with_opts(std::slice::from_ref(maze), &GameOpts::default())
+    }
+
+    /// Play with the fog, rotations, memory mode, enemies, puzzle or assist of the
+    /// options, `views` come from `engine::views`. Memory mode hides the walls from the
+    /// first frame.
+    pub fn with_opts(views: &'a [Maze], opts: &GameOpts) -> Headless<'a> {
+        let mut joystick = views[0].joystick();
+        joystick.assist = opts.assist;
+        let mut engine = Engine::new(views, joystick, opts);
+        if opts.memory.is_some() {
+            engine.hide_walls();
+        }
+        let mut h = Headless {
+            engine,
+            uis: views.iter().map(|v| v.ui()).collect(),
+            show_path: false,
+            presses: 0,
+            frames: Vec::new(),
+            hook: None,
+            keys: KeyMap::default(),
+        };
+        h.render();
+        h
+    }
+
+    /// Feed a single key press. Returns the action it triggered, if any.
+    pub fn press(&mut self, k: Key) -> Option<Action> {
+        let action = self.keys.action(&k);
+        // the victory screen takes the keys once the run is won
+        if action == Some(Action::TogglePath) && !self.engine.is_finished() {
+            self.show_path = !self.show_path;
+        }
+        // saving, messages and leaving the game are left to the caller
+        self.presses += 1;
+        self.engine.act(action, KEY_INTERVAL * self.presses);
+        self.render();
+        action
+    }
+
+    /// Feed keys until they run out or one of them ends the game.
+    pub fn play<I>(&mut self, keys: I) -> &[String]
+    where
+        I: IntoIterator<Item = Key>,
+    {
+        for k in keys {
+            if let Some(Action::NewGame | Action::Quit) = self.press(k) {
+                break;
+            }
+        }
+        &self.frames
+    }
+
+    /// Feed a scripted list of moves using the default movement keys.
+    pub fn moves<'d, I>(&mut self, moves: I) -> &[String]
+    where
+        I: IntoIterator<Item = &'d Direction>,
+    {
+        self.play(moves.into_iter().map(|d| match d {
+            Direction::Left => Key::Left,
+            Direction::Right => Key::Right,
+            Direction::Up => Key::Up,
+            Direction::Down => Key::Down,
+        }))
+    }
+
+    /// captured frames, the first one is the board before any input
+    pub fn frames(&self) -> &[String] {
+        &self.frames
+    }
+
+    pub fn joystick(&self) -> &Joystick<'a> {
+        self.engine.joystick()
+    }
+
+    /// recording of the game, a key press every `KEY_INTERVAL`
+    pub fn replay(&self) -> &Replay {
+        self.engine.replay()
+    }
+
+    /// entities ticked on every key press
+    pub fn entities_mut(&mut self) -> &mut Entities {
+        self.engine.entities_mut()
+    }
+
+    /// call the hook with every following frame
+    pub fn on_frame<F: FnMut(&Frame) + 'static>(&mut self, hook: F) {
+        self.hook = Some(Box::new(hook));
+    }
+
+    /// checkpoints to visit before the exit ends the run
+    pub fn course_mut(&mut self) -> &mut Course {
+        self.engine.course_mut()
+    }
+
+    /// markers drawn in the following frames
+    pub fn markers_mut(&mut self) -> &mut Markers {
+        self.engine.markers_mut()
+    }
+
+    fn render(&mut self) {
+        let ui = &self.uis[self.engine.rotation().quarter_turns() as usize];
+        let show = self.engine.is_finished() || self.show_path;
+        let frame = self.engine.frame(ui, show);
+        if let Some(hook) = self.hook.as_mut() {
+            hook(&frame);
+        }
+        self.frames.push(frame.text());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine;
+    use crate::maze::Direction::{Down, Left, Right};
+    use crate::maze::Position;
+
+    #[test]
+    fn test_play() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut h = Headless::new(&m);
+        h.moves([Right, Right, Down].iter());
+        assert!(h.joystick().is_exit());
+        assert_eq!(
+            h.frames(),
+            [
+                "┌───────────┐\n\
+                 │ @         │\n\
+                 ├───────╴   │\n\
+                 │       Exit│\n\
+                 └───────────┘",
+                "┌───────────┐\n\
+                 │     @     │\n\
+                 ├───────╴   │\n\
+                 │       Exit│\n\
+                 └───────────┘",
+                "┌───────────┐\n\
+                 │         @ │\n\
+                 ├───────╴   │\n\
+                 │       Exit│\n\
+                 └───────────┘",
+                "┌───────────┐\n\
+                 │ ········· │\n\
+                 ├───────╴ · │\n\
+                 │       Ex@t│\n\
+                 └───────────┘",
+            ]
+        );
+
+        // moves are over once out
+        h.moves([Left].iter());
+        assert!(h.joystick().is_exit());
+
+        // a checkpoint left keeps the run going past the exit
+        let mut h = Headless::new(&m);
+        h.course_mut().add(Position { x: 1, y: 1 });
+        h.moves([Right, Right, Down, Left].iter());
+        assert_eq!(h.joystick().pos, Position { x: 1, y: 1 });
+        assert!(h.frames()[3].contains("Ex@t") && !h.frames()[3].contains('·'));
+        h.moves([Right, Left].iter());
+        assert!(h.joystick().is_exit());
+
+        // quitting stops the script
+        let mut h = Headless::new(&m);
+        h.play(vec![Key::Char('p'), Key::Char('q'), Key::Right]);
+        assert_eq!(h.frames().len(), 3);
+        assert_eq!(h.joystick().move_count(), 0);
+    }
+
+    #[test]
+    fn test_opts() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        // only the cells next to the player show through the fog
+        let opts = GameOpts {
+            fog: 1,
+            ..Default::default()
+        };
+        let h = Headless::with_opts(std::slice::from_ref(&m), &opts);
+        assert_eq!(
+            h.frames()[0],
+            "┌────────    \n\
+             │ @          \n\
+             ├───────╴    \n\
+             │       Exit \n\
+             └────        "
+        );
+
+        // no walls from the first frame in memory mode, they still block
+        let opts = GameOpts {
+            memory: Some(0),
+            ..Default::default()
+        };
+        let mut h = Headless::with_opts(std::slice::from_ref(&m), &opts);
+        h.moves([Right, Down].iter());
+        assert!(h.frames().iter().all(|f| !f.contains('─')));
+        assert_eq!(h.joystick().pos, Position { x: 1, y: 0 });
+
+        // the maze turns after every move, the keys go the way they show on screen
+        let opts = GameOpts {
+            rotate_every: 1,
+            ..Default::default()
+        };
+        let views = engine::views(&m, &opts);
+        let mut h = Headless::with_opts(&views, &opts);
+        h.moves([Right, Right].iter());
+        assert_eq!(
+            h.frames()[1],
+            "┌───┬───┐\n\
+             │   │   │\n\
+             │   │   │\n\
+             │   │ @ │\n\
+             │   ╵   │\n\
+             Exit    │\n\
+             └───────┘"
+        );
+        assert_eq!(h.joystick().pos, Position { x: 1, y: 0 });
+    }
+}
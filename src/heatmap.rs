@@ -0,0 +1,281 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use termion::{color, style};
+use thiserror::Error;
+
+use crate::export::{self, ExportError, Raster};
+use crate::maze::{Maze, Position};
+use crate::replay::{Replay, ReplayError};
+
+/// Glyphs of the text overlay, from the least to the most traveled cells.
+const SHADES: [char; 4] = ['░', '▒', '▓', '█'];
+/// 256 color backgrounds of the colored overlay, pale yellow to red.
+const COLORS: [u8; 6] = [229, 227, 220, 214, 208, 196];
+
+#[derive(Error, Debug)]
+pub enum HeatmapError {
+    #[error("{0}: {1}")]
+    Replay(PathBuf, ReplayError),
+    #[error("{} was recorded on maze {found:016x}, not {expected:016x}", .path.display())]
+    Mismatch {
+        path: PathBuf,
+        expected: u64,
+        found: u64,
+    },
+    #[error("no .rmz replays in {}", .0.display())]
+    Empty(PathBuf),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Where the players of many replays of the same maze went, as the number of times a
+/// player stood on every cell.
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    maze: Maze,
+    visits: Vec<u32>,
+    runs: usize,
+}
+
+impl Heatmap {
+    pub fn new(maze: &Maze) -> Heatmap {
+        Heatmap {
+            maze: maze.clone(),
+            visits: vec![0; maze.width as usize * maze.height as usize],
+            runs: 0,
+        }
+    }
+
+    /// Sum the `.rmz` replays of the directory, in name order. They must all be recorded
+    /// on the maze of the first.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Heatmap, HeatmapError> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension() == Some("rmz".as_ref()))
+            .collect();
+        paths.sort();
+        let mut heatmap: Option<Heatmap> = None;
+        for path in paths {
+            let replay = Replay::load(&path).map_err(|e| HeatmapError::Replay(path.clone(), e))?;
+            let heatmap = heatmap.get_or_insert_with(|| Heatmap::new(replay.maze()));
+            let (expected, found) = (heatmap.maze.fingerprint(), replay.maze().fingerprint());
+            if expected != found {
+                return Err(HeatmapError::Mismatch {
+                    path,
+                    expected,
+                    found,
+                });
+            }
+            heatmap.add(&replay);
+        }
+        heatmap.ok_or_else(|| HeatmapError::Empty(dir.as_ref().to_path_buf()))
+    }
+
+    /// add the cells a replay went through, it must be recorded on the same maze
+    pub fn add(&mut self, replay: &Replay) {
+        self.runs += 1;
+        for p in replay.positions() {
+            self.visits[self.maze.pos_to_cell(p) as usize] += 1;
+        }
+    }
+
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    /// replays summed
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// times a player stood on the cell
+    pub fn visits(&self, p: Position) -> u32 {
+        self.visits[self.maze.pos_to_cell(p) as usize]
+    }
+
+    /// visits of every cell relative to the most visited one, in [0, 1]
+    pub fn heat(&self) -> Vec<f32> {
+        let max = self.visits.iter().copied().max().unwrap_or(0).max(1);
+        self.visits.iter().map(|v| *v as f32 / max as f32).collect()
+    }
+
+    /// Shade the inside of the visited cells of a board drawn by `maze().ui()`, with
+    /// background colors or with block glyphs over the blank characters. Returns the lines
+    /// of the board.
+    pub fn overlay(&self, mut board: Vec<Vec<char>>, color: bool) -> Vec<String> {
+        let ui = self.maze.ui();
+        let mut paint = vec![vec![None; board.first().map_or(0, |r| r.len())]; board.len()];
+        for (i, t) in self
+            .heat()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, t)| *t > 0.0)
+        {
+            let p = self.maze.cell_to_pos(i as u16);
+            let (left, top) = (p.x * ui.cell_width, p.y * ui.cell_height);
+            let level = |n: usize| ((t * n as f32).ceil() as usize).clamp(1, n) - 1;
+            // the inside of the cell and the openings to the cells left of and above it
+            for y in top..top + ui.cell_height {
+                for x in left..left + ui.cell_width {
+                    let (x, y) = (x as usize, y as usize);
+                    let inside = x > left as usize && y > top as usize;
+                    if color && (inside || board[y][x] == ' ') {
+                        paint[y][x] = Some(COLORS[level(COLORS.len())]);
+                    } else if !color && board[y][x] == ' ' {
+                        board[y][x] = SHADES[level(SHADES.len())];
+                    }
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (r, pr) in board.into_iter().zip(paint) {
+            let mut line = String::new();
+            let mut current = None;
+            for (c, p) in r.into_iter().zip(pr) {
+                if p != current {
+                    match p {
+                        Some(v) => line.push_str(&color::Bg(color::AnsiValue(v)).to_string()),
+                        None => line.push_str(style::Reset.as_ref()),
+                    }
+                    current = p;
+                }
+                line.push(c);
+            }
+            if current.is_some() {
+                line.push_str(style::Reset.as_ref());
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// the maze as text with the overlay over it
+    pub fn render(&self, color: bool) -> String {
+        let board = self
+            .maze
+            .ui()
+            .render(false)
+            .lines()
+            .map(|l| l.chars().collect())
+            .collect();
+        let mut out = self.overlay(board, color).join("\n");
+        out.push('\n');
+        out
+    }
+
+    /// the maze as an SVG image with the cells shaded red by their heat
+    pub fn svg(&self) -> String {
+        export::heat_svg(&self.maze, &self.heat())
+    }
+
+    /// the maze as a PNG image with the cells shaded towards the path color by their heat
+    pub fn png<W: Write>(&self, out: W, raster: &Raster) -> Result<(), ExportError> {
+        export::heat_png(out, &self.maze, &self.heat(), raster)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Direction::{Down, Left, Right};
+    use crate::replay::Step;
+    use std::time::Duration;
+
+    #[test]
+    fn test_heatmap() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let record = |moves: &[_]| {
+            let mut replay = Replay::new(&m);
+            let mut j = m.joystick();
+            for d in moves {
+                j.mv(d);
+                replay.record(Step::Move(*d), &j, Duration::from_secs(1));
+            }
+            replay
+        };
+        let dir = std::env::temp_dir().join(format!("rusty_maze_heat_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // only the replays are read
+        std::fs::write(dir.join("notes.txt"), "not a replay").unwrap();
+        assert!(matches!(
+            Heatmap::from_dir(&dir),
+            Err(HeatmapError::Empty(_))
+        ));
+        record(&[Right, Right, Down])
+            .save(dir.join("a.rmz"))
+            .unwrap();
+        // the second player turns back once before going on
+        record(&[Right, Left, Right, Right, Down])
+            .save(dir.join("b.rmz"))
+            .unwrap();
+        let heatmap = Heatmap::from_dir(&dir).unwrap();
+        assert_eq!(heatmap.runs(), 2);
+        let p = |x, y| Position { x, y };
+        assert_eq!(heatmap.visits(p(0, 0)), 3);
+        assert_eq!(heatmap.visits(p(1, 0)), 3);
+        assert_eq!(heatmap.visits(p(2, 1)), 2);
+        assert_eq!(heatmap.heat()[4], 0.0);
+        assert_eq!(
+            heatmap.render(false),
+            "┌───────────┐\n\
+             │███████▓▓▓▓│\n\
+             ├───────╴▓▓▓│\n\
+             │       Exit│\n\
+             └───────────┘\n"
+        );
+        assert!(heatmap.svg().contains("fill-opacity=\"0.67\""));
+
+        let other = Maze::create(3, 2, vec![(0, 3)]).unwrap();
+        Replay::new(&other).save(dir.join("c.rmz")).unwrap();
+        assert!(matches!(
+            Heatmap::from_dir(&dir),
+            Err(HeatmapError::Mismatch { .. })
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_overlay() {
+        // a tall maze going down the left column then right to the exit
+        let m = Maze::create(2, 3, vec![(0, 1), (2, 3)]).unwrap();
+        let mut heatmap = Heatmap::new(&m);
+        // nothing shaded before any run
+        assert_eq!(heatmap.runs(), 0);
+        assert!(heatmap.heat().iter().all(|t| *t == 0.0));
+        assert_eq!(heatmap.render(false), m.ui().render(false));
+        assert_eq!(heatmap.render(true), heatmap.render(false));
+
+        let mut replay = Replay::new(&m);
+        let mut j = m.joystick();
+        for d in [Down, Down, Right, Left].iter() {
+            j.mv(d);
+            replay.record(Step::Move(*d), &j, Duration::from_secs(1));
+        }
+        heatmap.add(&replay);
+        // the turn back at the bottom makes it the hottest cell, twice the others
+        assert_eq!(heatmap.visits(Position { x: 0, y: 2 }), 2);
+        assert_eq!(
+            heatmap.render(false),
+            "┌───┬───┐\n\
+             │▒▒▒│   │\n\
+             │▒▒▒│   │\n\
+             │▒▒▒│   │\n\
+             │███╵▒▒▒│\n\
+             │███Exit│\n\
+             └───────┘\n"
+        );
+        let colored = heatmap.render(true);
+        let hot = color::Bg(color::AnsiValue(196)).to_string();
+        let warm = color::Bg(color::AnsiValue(220)).to_string();
+        assert_eq!(colored.matches(&hot).count(), 2);
+        assert_eq!(colored.matches(&warm).count(), 5);
+        // the colors never run past the end of a line into the next one
+        let reset: &str = style::Reset.as_ref();
+        assert!(colored
+            .lines()
+            .filter_map(|l| l.rfind('\u{1b}').map(|i| &l[i..]))
+            .all(|last| last.starts_with(reset)));
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HooksError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+}
+
+/// Where the hooks are read from, `rusty-maze/hooks.ron` in the config directory of the
+/// platform. Never the current directory, the commands of a downloaded folder would run
+/// when playing in it. None when there is no such directory.
+pub fn hooks_file() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("rusty-maze").join("hooks.ron"))
+}
+
+/// Game event passed to a hook as JSON on its standard input.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// the exit of a maze was reached
+    Won {
+        maze: String,
+        fingerprint: u64,
+        moves: usize,
+        millis: u64,
+        par: usize,
+    },
+    /// the game was saved to the file
+    Saved { path: String },
+}
+
+impl Event {
+    pub fn payload(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Shell commands to run on game events, e.g. to send a notification or upload stats.
+///
+/// ```ron
+/// (won: Some("notify-send 'Maze solved'"), saved: None)
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Hooks {
+    #[serde(default)]
+    pub won: Option<String>,
+    #[serde(default)]
+    pub saved: Option<String>,
+}
+
+impl Hooks {
+    /// Load the hooks from the given file, a missing file runs none.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Hooks, HooksError> {
+        match File::open(path) {
+            Ok(f) => Ok(ron::de::from_reader(BufReader::new(f))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Hooks::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// command hooked to the event, if any
+    pub fn command(&self, event: &Event) -> Option<&str> {
+        match event {
+            Event::Won { .. } => self.won.as_deref(),
+            Event::Saved { .. } => self.saved.as_deref(),
+        }
+    }
+
+    /// Start the command hooked to the event with its payload and return without waiting
+    /// for it to finish.
+    pub fn run(&self, event: &Event) -> Result<(), std::io::Error> {
+        let command = match self.command(event) {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let payload = event.payload();
+        std::thread::spawn(move || {
+            if let Some(mut stdin) = child.stdin.take() {
+                // hooks are free to ignore the payload
+                let _ = stdin.write_all(payload.as_bytes());
+            }
+            let _ = child.wait();
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks() {
+        let hooks: Hooks = ron::de::from_str(r#"(won: Some("cat"))"#).unwrap();
+        let won = Event::Won {
+            maze: "Amber Hollow".to_string(),
+            fingerprint: 42,
+            moves: 12,
+            millis: 3400,
+            par: 10,
+        };
+        assert_eq!(hooks.command(&won), Some("cat"));
+        assert_eq!(
+            hooks.command(&Event::Saved {
+                path: "maze.ron".to_string()
+            }),
+            None
+        );
+        assert_eq!(
+            won.payload(),
+            r#"{"event":"won","maze":"Amber Hollow","fingerprint":42,"moves":12,"millis":3400,"par":10}"#
+        );
+    }
+}
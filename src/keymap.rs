@@ -0,0 +1,372 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use termion::event::Key;
+use thiserror::Error;
+
+use crate::game::Action;
+use crate::maze::Direction::{Down, Left, Right, Up};
+
+#[derive(Error, Debug)]
+pub enum KeyMapError {
+    #[error("unknown action {0}")]
+    Action(String),
+    #[error("unknown key {0}, expected a character, space, tab, enter, esc or an arrow")]
+    Key(String),
+    #[error("{} is bound to both {1} and {2}", key_name(.0))]
+    Conflict(Key, String, String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+}
+
+/// Keys of every action when no config changes them.
+const DEFAULT_KEYS: &[(Key, Action)] = &[
+    (Key::Char('h'), Action::Move(Left)),
+    (Key::Char('a'), Action::Move(Left)),
+    (Key::Left, Action::Move(Left)),
+    (Key::Char('j'), Action::Move(Down)),
+    (Key::Char('s'), Action::Move(Down)),
+    (Key::Down, Action::Move(Down)),
+    (Key::Char('k'), Action::Move(Up)),
+    (Key::Char('w'), Action::Move(Up)),
+    (Key::Up, Action::Move(Up)),
+    (Key::Char('l'), Action::Move(Right)),
+    (Key::Char('d'), Action::Move(Right)),
+    (Key::Right, Action::Move(Right)),
+    (Key::Char('r'), Action::Reset),
+    (Key::Char('u'), Action::Undo),
+    (Key::Char('e'), Action::Save),
+    (Key::Char('1'), Action::SaveSlot(1)),
+    (Key::Char('2'), Action::SaveSlot(2)),
+    (Key::Char('3'), Action::SaveSlot(3)),
+    (Key::Char('4'), Action::SaveSlot(4)),
+    (Key::Char('5'), Action::SaveSlot(5)),
+    (Key::Char('6'), Action::SaveSlot(6)),
+    (Key::Char('7'), Action::SaveSlot(7)),
+    (Key::Char('8'), Action::SaveSlot(8)),
+    (Key::Char('9'), Action::SaveSlot(9)),
+    (Key::Char('L'), Action::LoadSlot),
+    (Key::Char(':'), Action::Command),
+    (Key::Char('R'), Action::Replay),
+    (Key::Char('p'), Action::TogglePath),
+    (Key::Char('o'), Action::ToggleSolution),
+    (Key::Char('H'), Action::Hint),
+    (Key::Char('b'), Action::Breadcrumbs),
+    (Key::Char('i'), Action::Stats),
+    (Key::Char('m'), Action::Messages),
+    (Key::Char('\t'), Action::SwitchPlayer),
+    (Key::Char('?'), Action::Help),
+    (Key::Char('n'), Action::NewGame),
+    (Key::Char('q'), Action::Quit),
+];
+
+/// Actions listed by the help overlay with what they do. Entries of a single action show
+/// all its keys, the others the first key of every action.
+const HELP: &[(&[Action], &str)] = &[
+    (
+        &[
+            Action::Move(Left),
+            Action::Move(Down),
+            Action::Move(Up),
+            Action::Move(Right),
+        ],
+        "move left, down, up or right",
+    ),
+    (&[Action::Reset], "back to the entrance"),
+    (&[Action::Undo], "take back the last move"),
+    (
+        &[Action::Breadcrumbs],
+        "show the way back to the last junction with a way not taken",
+    ),
+    (&[Action::Hint], "hint"),
+    (&[Action::TogglePath], "show the path taken"),
+    (
+        &[Action::ToggleSolution],
+        "show the solution, the run won't set a record",
+    ),
+    (&[Action::Save], "save"),
+    (
+        &[
+            Action::SaveSlot(1),
+            Action::SaveSlot(2),
+            Action::SaveSlot(3),
+            Action::SaveSlot(4),
+            Action::SaveSlot(5),
+            Action::SaveSlot(6),
+            Action::SaveSlot(7),
+            Action::SaveSlot(8),
+            Action::SaveSlot(9),
+        ],
+        "save to a slot",
+    ),
+    (&[Action::LoadSlot], "load a slot"),
+    (&[Action::Command], "type a command"),
+    (&[Action::Replay], "watch the run so far"),
+    (&[Action::Stats], "statistics"),
+    (&[Action::Messages], "messages"),
+    (&[Action::SwitchPlayer], "switch players in co-op"),
+    (&[Action::NewGame], "new game"),
+    (&[Action::Quit], "quit"),
+    (&[Action::Help], "this help, esc or again resumes"),
+];
+
+/// Where the key bindings are read from, `rusty-maze/keys.ron` in the config directory
+/// of the platform. None when there is no such directory.
+pub fn keys_file() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("rusty-maze").join("keys.ron"))
+}
+
+/// Actions of the keys. A config maps action names to the keys replacing their default
+/// ones, the actions it leaves out keep theirs:
+///
+/// ```text
+/// {
+///     "left": ["Left", "y"],
+///     "save": ["S"],
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyMap {
+    actions: HashMap<Key, Action>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap {
+            actions: DEFAULT_KEYS.iter().copied().collect(),
+        }
+    }
+}
+
+impl KeyMap {
+    /// Load the bindings from the given file, a missing file keeps the defaults.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<KeyMap, KeyMapError> {
+        match File::open(path) {
+            Ok(f) => KeyMap::with(ron::de::from_reader(BufReader::new(f))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(KeyMap::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The defaults with the keys of the actions in the config replaced, failing when a
+    /// key ends up bound to two actions.
+    pub fn with(config: BTreeMap<String, Vec<String>>) -> Result<KeyMap, KeyMapError> {
+        let mut bound = Vec::new();
+        for (action, keys) in config {
+            let action: Action = action.parse()?;
+            for k in keys {
+                bound.push((parse_key(&k)?, action));
+            }
+        }
+        let mut map = KeyMap::default();
+        map.actions
+            .retain(|_, a| !bound.iter().any(|(_, b)| b == a));
+        for (k, action) in bound {
+            match map.actions.insert(k, action) {
+                Some(other) if other != action => {
+                    return Err(KeyMapError::Conflict(
+                        k,
+                        action_name(other),
+                        action_name(action),
+                    ))
+                }
+                _ => (),
+            }
+        }
+        Ok(map)
+    }
+
+    /// the action bound to the key
+    pub fn action(&self, k: &Key) -> Option<Action> {
+        self.actions.get(k).copied()
+    }
+
+    /// keys bound to the action, in the order of the defaults then by name
+    pub fn keys(&self, action: Action) -> Vec<Key> {
+        let order = |k: &Key| DEFAULT_KEYS.iter().position(|d| d.0 == *k);
+        let mut keys: Vec<Key> = self
+            .actions
+            .iter()
+            .filter(|(_, a)| **a == action)
+            .map(|(k, _)| *k)
+            .collect();
+        keys.sort_by_key(|k| (order(k).unwrap_or(usize::MAX), key_name(k)));
+        keys
+    }
+
+    /// Short list of keys and what they do, e.g. "?: help, q: exit", with the first key
+    /// of each action. Actions without a key are left out.
+    pub fn hints(&self, entries: &[(Action, &str)]) -> String {
+        let hints: Vec<String> = entries
+            .iter()
+            .filter_map(|(action, what)| {
+                let k = self.keys(*action).first().map(key_name)?;
+                Some(format!("{}: {}", k, what))
+            })
+            .collect();
+        hints.join(", ")
+    }
+
+    /// lines of the help overlay, the keys of every entry and what they do
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        HELP.iter()
+            .map(|(actions, what)| {
+                let names: Vec<String> = match actions {
+                    [action] => self.keys(*action).iter().map(key_name).collect(),
+                    _ => actions
+                        .iter()
+                        .filter_map(|a| self.keys(*a).first().map(key_name))
+                        .collect(),
+                };
+                (names.join(" "), *what)
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Action {
+    type Err = KeyMapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Action::Move(Left)),
+            "down" => Ok(Action::Move(Down)),
+            "up" => Ok(Action::Move(Up)),
+            "right" => Ok(Action::Move(Right)),
+            "reset" => Ok(Action::Reset),
+            "undo" => Ok(Action::Undo),
+            "save" => Ok(Action::Save),
+            "load" => Ok(Action::LoadSlot),
+            "command" => Ok(Action::Command),
+            "replay" => Ok(Action::Replay),
+            "path" => Ok(Action::TogglePath),
+            "solution" => Ok(Action::ToggleSolution),
+            "hint" => Ok(Action::Hint),
+            "breadcrumbs" => Ok(Action::Breadcrumbs),
+            "stats" => Ok(Action::Stats),
+            "messages" => Ok(Action::Messages),
+            "switch" => Ok(Action::SwitchPlayer),
+            "help" => Ok(Action::Help),
+            "new" => Ok(Action::NewGame),
+            "quit" => Ok(Action::Quit),
+            _ => match s.strip_prefix("slot").and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=9) => Ok(Action::SaveSlot(n)),
+                _ => Err(KeyMapError::Action(s.to_string())),
+            },
+        }
+    }
+}
+
+/// name of the action in configs
+fn action_name(a: Action) -> String {
+    match a {
+        Action::Move(Left) => "left".to_string(),
+        Action::Move(Down) => "down".to_string(),
+        Action::Move(Up) => "up".to_string(),
+        Action::Move(Right) => "right".to_string(),
+        Action::SaveSlot(n) => format!("slot{}", n),
+        a => format!("{:?}", a).to_lowercase(),
+    }
+}
+
+/// Key of a config, a single character or the name of a special key.
+fn parse_key(s: &str) -> Result<Key, KeyMapError> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(Key::Char(c));
+    }
+    match s.to_lowercase().as_str() {
+        "space" => Ok(Key::Char(' ')),
+        "tab" => Ok(Key::Char('\t')),
+        "enter" => Ok(Key::Char('\n')),
+        "esc" => Ok(Key::Esc),
+        "left" => Ok(Key::Left),
+        "right" => Ok(Key::Right),
+        "up" => Ok(Key::Up),
+        "down" => Ok(Key::Down),
+        _ => Err(KeyMapError::Key(s.to_string())),
+    }
+}
+
+/// name of the key as shown in the help
+pub fn key_name(k: &Key) -> String {
+    match k {
+        Key::Char(' ') => "space".to_string(),
+        Key::Char('\t') => "tab".to_string(),
+        Key::Char('\n') => "enter".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Esc => "esc".to_string(),
+        Key::Left => "←".to_string(),
+        Key::Right => "→".to_string(),
+        Key::Up => "↑".to_string(),
+        Key::Down => "↓".to_string(),
+        k => format!("{:?}", k),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap() {
+        let keys = KeyMap::default();
+        assert_eq!(keys.action(&Key::Char('a')), Some(Action::Move(Left)));
+        assert_eq!(keys.action(&Key::Char('?')), Some(Action::Help));
+        assert_eq!(keys.action(&Key::Char('x')), None);
+        assert_eq!(keys.help()[0].0, "h j k l");
+
+        let config = ron::de::from_str(r#"{"left": ["y", "Left"], "save": ["S"], "slot1": ["!"]}"#);
+        let keys = KeyMap::with(config.unwrap()).unwrap();
+        assert_eq!(keys.action(&Key::Char('y')), Some(Action::Move(Left)));
+        assert_eq!(keys.action(&Key::Char('h')), None);
+        assert_eq!(keys.action(&Key::Char('S')), Some(Action::Save));
+        assert_eq!(keys.action(&Key::Char('e')), None);
+        assert_eq!(keys.keys(Action::Move(Left)), [Key::Left, Key::Char('y')]);
+        assert_eq!(keys.help()[0].0, "← j k l");
+        assert_eq!(keys.action(&Key::Char('!')), Some(Action::SaveSlot(1)));
+
+        // q stays bound to quit
+        let config = ron::de::from_str(r#"{"save": ["q"]}"#).unwrap();
+        assert_eq!(
+            KeyMap::with(config).unwrap_err().to_string(),
+            "q is bound to both quit and save"
+        );
+        let config = ron::de::from_str(r#"{"jump": ["J"]}"#).unwrap();
+        assert!(matches!(KeyMap::with(config), Err(KeyMapError::Action(_))));
+        let config = ron::de::from_str(r#"{"up": ["PageUp"]}"#).unwrap();
+        assert!(matches!(KeyMap::with(config), Err(KeyMapError::Key(_))));
+    }
+
+    #[test]
+    fn test_help() {
+        let keys = KeyMap::default();
+        // every key listed does something and every action is listed
+        for ((names, _), (actions, _)) in keys.help().iter().zip(HELP) {
+            for name in names.split(' ') {
+                let k = DEFAULT_KEYS.iter().find(|d| key_name(&d.0) == name);
+                assert!(actions.contains(&k.unwrap().1), "{}", name);
+            }
+        }
+        for (_, a) in DEFAULT_KEYS {
+            assert!(
+                HELP.iter().any(|(actions, _)| actions.contains(a)),
+                "{:?}",
+                a
+            );
+        }
+
+        let hints = [(Action::Help, "help"), (Action::Quit, "exit")];
+        assert_eq!(keys.hints(&hints), "?: help, q: exit");
+        let config = ron::de::from_str(r#"{"help": ["F"]}"#).unwrap();
+        assert_eq!(
+            KeyMap::with(config).unwrap().hints(&hints),
+            "F: help, q: exit"
+        );
+    }
+}
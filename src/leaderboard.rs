@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::records::Record;
+
+#[derive(Error, Debug)]
+pub enum LeaderboardError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+}
+
+/// Where the leaderboards are kept, `rusty-maze/leaderboard.ron` in the data directory of
+/// the platform next to the statistics. None when there is no such directory.
+pub fn leaderboard_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("leaderboard.ron"))
+}
+
+/// Places kept on the board of every maze.
+pub const TOP: usize = 10;
+
+/// Longest name kept on a board, in characters.
+const NAME_LEN: usize = 16;
+
+/// Run of a player on a leaderboard.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub moves: usize,
+    pub millis: u64,
+    /// score of a treasure hunt or a return trip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<usize>,
+}
+
+impl Entry {
+    pub fn new(name: &str, run: Record) -> Entry {
+        Entry {
+            name: name.chars().take(NAME_LEN).collect(),
+            moves: run.moves,
+            millis: run.millis,
+            score: run.score,
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<16} {:>7.1}s {:>5} moves",
+            self.name,
+            self.millis as f32 / 1000.0,
+            self.moves
+        )?;
+        match self.score {
+            Some(score) => write!(f, " {:>6} points", score),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fastest runs of seeded mazes, keyed by maze fingerprint so a seed played at another
+/// size or difficulty gets a board of its own.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    boards: HashMap<u64, Vec<Entry>>,
+}
+
+impl Leaderboard {
+    /// Load the leaderboard from the given file, a missing file yields an empty one.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Leaderboard, LeaderboardError> {
+        match File::open(path) {
+            Ok(f) => Ok(ron::de::from_reader(BufReader::new(f))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Leaderboard::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Load the leaderboard like `Stats::load_or_recover`, a damaged file is moved aside
+    /// to `leaderboard.ron.corrupt` so the next run doesn't write over its boards.
+    pub fn load_or_recover<P: AsRef<Path>>(path: P) -> Leaderboard {
+        Leaderboard::load(&path).unwrap_or_else(|_| {
+            let _ = std::fs::rename(&path, path.as_ref().with_extension("ron.corrupt"));
+            Leaderboard::default()
+        })
+    }
+
+    /// Write the leaderboard next to the file, then move it over it.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), LeaderboardError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("ron.tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        ron::ser::to_writer(&mut out, self)?;
+        out.flush()?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// the board of the maze fingerprint, fastest first
+    pub fn top(&self, fingerprint: u64) -> &[Entry] {
+        self.boards.get(&fingerprint).map_or(&[], |b| b.as_slice())
+    }
+
+    /// Submit a run, faster wins and fewer moves break ties. Returns the place it took,
+    /// from 0, or None when it didn't make the top.
+    pub fn submit(&mut self, fingerprint: u64, entry: Entry) -> Option<usize> {
+        let board = self.boards.entry(fingerprint).or_default();
+        let place = board
+            .iter()
+            .position(|e| (entry.millis, entry.moves) < (e.millis, e.moves))
+            .unwrap_or(board.len());
+        if place >= TOP {
+            return None;
+        }
+        board.insert(place, entry);
+        board.truncate(TOP);
+        Some(place)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaderboard() {
+        let run = |moves, millis| Record {
+            moves,
+            millis,
+            score: None,
+        };
+        let mut board = Leaderboard::default();
+        assert_eq!(board.submit(1, Entry::new("ada", run(30, 9000))), Some(0));
+        assert_eq!(board.submit(1, Entry::new("bob", run(34, 8000))), Some(0));
+        // ties on time go to fewer moves, then to the earlier run
+        assert_eq!(board.submit(1, Entry::new("cy", run(28, 9000))), Some(1));
+        assert_eq!(board.submit(1, Entry::new("dee", run(28, 9000))), Some(2));
+        assert_eq!(board.top(2), &[]);
+        for i in 0..TOP as u64 {
+            board.submit(1, Entry::new("eve", run(20, 1000 + i)));
+        }
+        assert_eq!(board.top(1).len(), TOP);
+        assert_eq!(board.top(1)[TOP - 1].name, "eve");
+        assert_eq!(board.submit(1, Entry::new("fay", run(40, 20000))), None);
+        assert_eq!(
+            Entry::new("a very long player name", run(42, 12345)).to_string(),
+            "a very long play    12.3s    42 moves"
+        );
+        assert_eq!(
+            Entry::new("ada", run(42, 12345).scored(952)).to_string(),
+            "ada                 12.3s    42 moves    952 points"
+        );
+
+        let dir = std::env::temp_dir().join(format!("rusty_maze_board_{}", std::process::id()));
+        let path = dir.join("leaderboard.ron");
+        board.save(&path).unwrap();
+        assert_eq!(Leaderboard::load_or_recover(&path), board);
+        std::fs::write(&path, "(boards: {1: [").unwrap();
+        assert!(Leaderboard::load(&path).is_err());
+        assert_eq!(Leaderboard::load_or_recover(&path), Leaderboard::default());
+        assert!(dir.join("leaderboard.ron.corrupt").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,3 +1,45 @@
+pub mod calibrate;
+pub mod campaign;
+pub mod capture;
+pub mod coop;
+// older than the lints the build denies, kept as it was written
+#[allow(
+    dead_code,
+    clippy::needless_return,
+    clippy::match_like_matches_macro,
+    clippy::redundant_pattern_matching
+)]
 mod disjset;
+pub mod engine;
+pub mod entity;
+pub mod export;
 pub mod game;
+pub mod grid;
+pub mod habits;
+pub mod headless;
+pub mod heatmap;
+pub mod hooks;
+pub mod keymap;
+pub mod leaderboard;
+pub mod mask;
 pub mod maze;
+pub mod messages;
+pub mod names;
+pub mod notation;
+pub mod orienteering;
+pub mod overlay;
+pub mod palette;
+pub mod pool;
+pub mod race;
+pub mod records;
+pub mod replay;
+pub mod save;
+pub mod sound;
+pub mod stats;
+pub mod stego;
+pub mod teach;
+pub mod terminal;
+pub mod tournament;
+pub mod treasure;
+pub mod worksheet;
+pub mod world;
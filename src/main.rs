@@ -1,17 +1,39 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use structopt::StructOpt;
 use termion::input::TermRead;
 use termion::raw::IntoRawMode;
 
-use rusty_maze::game::{Game, GameState};
-use rusty_maze::maze::Difficulty;
-use std::fs::File;
-use std::io::BufReader;
+use rusty_maze::calibrate::{self, Calibration, Size};
+use rusty_maze::campaign::{self, Curve, LevelPack, Ramp};
+use rusty_maze::export::{export, poster, ExportError, Format, Marks, Pages, Raster, Rgb, Tileset};
+use rusty_maze::game::{
+    play_lesson, play_race, play_replay, Game, GameOpts, GameState, Theme, MESSAGE_LINES,
+};
+use rusty_maze::grid::{Grid, GridError, GridMaze, Hex, Polar};
+use rusty_maze::habits::{self, Habits};
+use rusty_maze::heatmap::Heatmap;
+use rusty_maze::hooks::{self, Hooks};
+use rusty_maze::keymap::{self, KeyMap};
+use rusty_maze::leaderboard;
+use rusty_maze::mask::Mask;
+use rusty_maze::maze::{
+    Algorithm, CellSize, Difficulty, Maze, MazeError, Opts, Placement, Position,
+};
+use rusty_maze::race::Solver;
+use rusty_maze::records;
+use rusty_maze::replay::{self, Replay};
+use rusty_maze::save::{self, SaveFormat};
+use rusty_maze::stats::{self, Stats};
+use rusty_maze::stego;
+use rusty_maze::terminal::Background;
+use rusty_maze::tournament::{self, Tournament, KEY_ENV};
+use rusty_maze::worksheet::Worksheet;
+use rusty_maze::world;
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "rusty_maze", about = "Rusty Maze Game")]
-struct Opt {
+struct MazeOpt {
     #[structopt(
         short = "w",
         long = "width",
@@ -24,37 +46,978 @@ struct Opt {
         help = "Maze height [default: terminal height]"
     )]
     height: Option<u16>,
-    #[structopt(short = "d", long, default_value = "Hard", help = "Maze difficulty")]
+    #[structopt(
+        short = "d",
+        long,
+        default_value = "Hard",
+        help = "Maze difficulty: easy, normal, hard, expert or insane"
+    )]
     difficulty: Difficulty,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Chance [0, 1) of sparing border walls for more interior paths"
+    )]
+    border_penalty: f32,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Minimum solution length as a factor of width + height"
+    )]
+    min_solution: f32,
+    #[structopt(
+        long,
+        default_value = "kruskal",
+        help = "Generation algorithm: kruskal, backtracker for long corridors, prim or wilson"
+    )]
+    algorithm: Algorithm,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Checkpoints to visit in any order before the exit unlocks"
+    )]
+    checkpoints: usize,
+    #[structopt(long, help = "Hide a treasure hinted by warmer/colder messages")]
+    treasure: bool,
+    #[structopt(
+        long,
+        value_name = "SEED",
+        conflicts_with_all = &[
+            "width", "height", "difficulty", "preview", "rotate", "algorithm", "braid",
+            "border_penalty", "min_solution", "wrap", "mask", "placement", "exits",
+        ],
+        help = "Play a tournament seed and sign the result with the key from RUSTY_MAZE_TOURNAMENT_KEY"
+    )]
+    tournament: Option<u64>,
+    #[structopt(
+        long,
+        conflicts_with = "tournament",
+        help = "Generate the same maze every time for the same seed, e.g. to share it"
+    )]
+    seed: Option<u64>,
+    #[structopt(
+        long,
+        value_name = "TEXT",
+        conflicts_with_all = &["seed", "tournament"],
+        help = "Hide a short message in the walls, read it back with the reveal command"
+    )]
+    message: Option<String>,
+    #[structopt(
+        long,
+        value_name = "NAME",
+        env = "USER",
+        default_value = "player",
+        help = "Name on the leaderboards of seeded mazes"
+    )]
+    name: String,
+    #[structopt(long, help = "Two player puzzle, hold the plate to open the door")]
+    coop: bool,
+    #[structopt(
+        long,
+        value_name = "MOVES",
+        default_value = "0",
+        help = "Turn the maze a quarter every so many moves, controls stay screen relative"
+    )]
+    rotate: usize,
+    #[structopt(
+        long,
+        value_name = "SECONDS",
+        help = "Show the maze for a few seconds, then hide the walls"
+    )]
+    memory: Option<u64>,
+    #[structopt(long, help = "Preview every new maze, n rerolls it and enter plays it")]
+    preview: bool,
+    #[structopt(long, help = "Slide around corners when a move runs into a wall")]
+    assist: bool,
+    #[structopt(
+        long,
+        help = "Show the straight line bearing to the exit, not the way there"
+    )]
+    compass: bool,
+    #[structopt(
+        long,
+        help = "After the exit, find the way back to the entrance through the fog to double the score"
+    )]
+    return_trip: bool,
+    #[structopt(
+        long,
+        help = "Join the opposite edges, passages marked with arrows lead across"
+    )]
+    wrap: bool,
+    #[structopt(
+        long,
+        parse(try_from_str = Mask::load),
+        help = "Shape the maze after a text file, cells marked # are left out"
+    )]
+    mask: Option<Mask>,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Share [0, 1] of the dead ends opened into loops, for more than one way out"
+    )]
+    braid: f32,
+    #[structopt(
+        long,
+        default_value = "corners",
+        help = "Entrance and exit: corners, farthest apart, or borders for random opposite sides"
+    )]
+    placement: Placement,
+    #[structopt(
+        long,
+        default_value = "1",
+        help = "Number of exits, the others go on random border cells and any one leads out"
+    )]
+    exits: usize,
+    #[structopt(long, help = "Reach every exit, in any order, to get out")]
+    all_exits: bool,
+    #[structopt(
+        long,
+        help = "Colors: classic, friendly, matrix, light, dark, auto to match the terminal background or time for light by day [default: by difficulty]"
+    )]
+    theme: Option<String>,
+    #[structopt(
+        long,
+        value_name = "RADIUS",
+        help = "Only show cells within the radius or already visited, 0 for no fog [default: by difficulty]"
+    )]
+    fog: Option<u16>,
+    #[structopt(long, help = "Huge cells, heavy walls and a blinking player")]
+    large_print: bool,
+    #[structopt(
+        long,
+        help = "No animations or blinking, changes show at once, e.g. for motion sensitivity"
+    )]
+    reduce_motion: bool,
+    #[structopt(
+        long,
+        default_value = "0.5",
+        help = "Volume of the sound cues from 0 to 1, needs the sound feature"
+    )]
+    volume: f32,
+    #[structopt(long, help = "Turn the sound cues off")]
+    mute: bool,
+    #[structopt(
+        long,
+        default_value = "10",
+        help = "Frames per second at most while waiting for keys, lower it over slow links"
+    )]
+    fps: u16,
+    #[structopt(
+        long,
+        help = "Endless campaign, every maze completed is followed by a larger and harder one, up to --width and --height"
+    )]
+    campaign: bool,
+    #[structopt(long, help = "Explore a world map of connected mazes")]
+    world: bool,
+    #[structopt(
+        long,
+        value_name = "PACK",
+        parse(from_os_str),
+        help = "Play the levels of a campaign pack in order"
+    )]
+    pack: Option<PathBuf>,
+    #[structopt(
+        long,
+        help = "Save file format, ron or json [default: from the file extension, ron for new saves]"
+    )]
+    save_format: Option<SaveFormat>,
+    #[structopt(
+        long,
+        value_name = "DIR",
+        parse(from_os_str),
+        help = "Directory of the save files and slots, and of the replays and signed results of won runs [default: current directory, rusty-maze/runs in the data directory for the runs]"
+    )]
+    save_dir: Option<PathBuf>,
+    #[structopt(long, help = "Resume the game left when quitting last time")]
+    resume: bool,
     #[structopt(name = "FILE", parse(from_os_str), help = "Maze data to restore")]
     file: Option<PathBuf>,
 }
 
+#[derive(Debug, StructOpt)]
+enum Command {
+    #[structopt(
+        about = "Write a maze to stdout without starting the game",
+        visible_alias = "print"
+    )]
+    Export {
+        #[structopt(
+            short = "f",
+            long,
+            default_value = "text",
+            help = "Output format: text, svg or png"
+        )]
+        format: Format,
+        #[structopt(
+            short = "t",
+            long,
+            default_value = "lines",
+            help = "Wall tiles: lines, hedge, dungeon, circuit or heavy"
+        )]
+        tileset: Tileset,
+        #[structopt(
+            long,
+            help = "Always include ANSI colors, even when not writing to a terminal"
+        )]
+        color: bool,
+        #[structopt(long, help = "Never include ANSI colors [env: NO_COLOR]")]
+        no_color: bool,
+        #[structopt(long, help = "Draw the solution over the maze")]
+        solution: bool,
+        #[structopt(
+            long,
+            help = "Label columns A-Z and rows 1-N, with a caption naming the start and exit"
+        )]
+        labels: bool,
+        #[structopt(long, default_value = "16", help = "Cell size in pixels of png images")]
+        cell_px: u32,
+        #[structopt(
+            long,
+            default_value = "2",
+            help = "Wall thickness in pixels of png images"
+        )]
+        wall_px: u32,
+        #[structopt(
+            long,
+            help = "Wall color of png images [default: the one of the tileset]"
+        )]
+        wall_color: Option<Rgb>,
+        #[structopt(
+            long,
+            default_value = "#ffffff",
+            help = "Background color of png images"
+        )]
+        background: Rgb,
+        #[structopt(
+            long,
+            default_value = "#ff0000",
+            help = "Solution and registration mark color of png images"
+        )]
+        path_color: Rgb,
+        #[structopt(
+            long,
+            value_name = "COLUMNSxROWS",
+            help = "Split the png image into poster pages written to poster-ROW-COLUMN.png, needs --format png"
+        )]
+        pages: Option<Pages>,
+        #[structopt(
+            long,
+            default_value = "square",
+            help = "Shape of the cells: square, hex or polar"
+        )]
+        grid: Grid,
+        #[structopt(
+            long,
+            default_value = "32",
+            help = "Pixels shared by neighbouring poster pages"
+        )]
+        overlap: u32,
+        #[structopt(flatten)]
+        maze: MazeOpt,
+    },
+    #[structopt(
+        about = "Generate a campaign level pack following difficulty curves",
+        after_help = "Curves are FROM:TO[:EASE] ramps over the levels, EASE is linear, in or out"
+    )]
+    Campaign {
+        #[structopt(long, default_value = "Campaign", help = "Name of the pack")]
+        name: String,
+        #[structopt(short = "n", long, default_value = "10", help = "Number of levels")]
+        levels: usize,
+        #[structopt(short = "d", long, default_value = "Normal", help = "Maze difficulty")]
+        difficulty: Difficulty,
+        #[structopt(short = "w", long, default_value = "10:40", help = "Maze width curve")]
+        width: Ramp,
+        #[structopt(short = "h", long, default_value = "5:20", help = "Maze height curve")]
+        height: Ramp,
+        #[structopt(
+            long,
+            default_value = "0",
+            help = "Share of dead ends opened into loops"
+        )]
+        braid: Ramp,
+        #[structopt(long, default_value = "0", help = "Sight radius, 0 for no fog")]
+        fog: Ramp,
+        #[structopt(long, default_value = "0", help = "Enemies per level")]
+        enemies: Ramp,
+        #[structopt(
+            short = "r",
+            long,
+            default_value = "0",
+            help = "Cells players may add to or remove from the size of the levels"
+        )]
+        resize: u16,
+        #[structopt(short = "o", long, parse(from_os_str), default_value = "campaign.ron")]
+        output: PathBuf,
+    },
+    #[structopt(
+        about = "Estimate solve times of mazes by playing many of them with simulated players"
+    )]
+    Calibrate {
+        #[structopt(
+            long,
+            default_value = "10x5,20x10,40x20",
+            use_delimiter = true,
+            help = "Maze sizes, WIDTHxHEIGHT"
+        )]
+        sizes: Vec<Size>,
+        #[structopt(
+            long,
+            default_value = "Easy,Normal,Hard",
+            use_delimiter = true,
+            help = "Maze difficulties"
+        )]
+        difficulties: Vec<Difficulty>,
+        #[structopt(
+            long,
+            default_value = "kruskal,backtracker,prim,wilson",
+            use_delimiter = true,
+            help = "Generation algorithms"
+        )]
+        algorithms: Vec<Algorithm>,
+        #[structopt(
+            short = "n",
+            long,
+            default_value = "100",
+            help = "Mazes per combination"
+        )]
+        samples: usize,
+        #[structopt(
+            long,
+            default_value = "0.7",
+            help = "Chance [0, 1] of the simulated player taking the right way at each move"
+        )]
+        skill: f32,
+        #[structopt(
+            long,
+            default_value = "250",
+            help = "Milliseconds per move of the player"
+        )]
+        move_ms: u64,
+        #[structopt(long, default_value = "0", help = "Seed of the sampled mazes")]
+        seed: u64,
+    },
+    #[structopt(
+        about = "Print the games played, wins, total moves, streak and best times by maze size"
+    )]
+    Stats {
+        #[structopt(
+            long,
+            help = "Report move directions, backtracking and choices at junctions"
+        )]
+        habits: bool,
+        #[structopt(
+            name = "HABITS",
+            parse(from_os_str),
+            help = "Habits file [default: habits.ron in the data directory]"
+        )]
+        file: Option<PathBuf>,
+    },
+    #[structopt(about = "Merge a saved maze into a region of another to build levels from parts")]
+    Merge {
+        #[structopt(name = "BASE", parse(from_os_str))]
+        base: PathBuf,
+        #[structopt(name = "PART", parse(from_os_str), help = "Maze merged into the base")]
+        part: PathBuf,
+        #[structopt(
+            long,
+            default_value = "0",
+            help = "Column of the left edge of the region"
+        )]
+        left: u16,
+        #[structopt(long, default_value = "0", help = "Row of the top edge of the region")]
+        top: u16,
+        #[structopt(short = "o", long, parse(from_os_str), default_value = "merged.ron")]
+        output: PathBuf,
+    },
+    #[structopt(about = "Print the message hidden in a saved maze with --message")]
+    Reveal {
+        #[structopt(name = "SAVE", parse(from_os_str), default_value = "maze.ron")]
+        file: PathBuf,
+    },
+    #[structopt(about = "Watch two solvers race to the exit of the same maze")]
+    Race {
+        #[structopt(default_value = "bfs", help = "Solver drawn in blue: bfs, dfs or wall")]
+        first: Solver,
+        #[structopt(default_value = "wall", help = "Solver drawn in red: bfs, dfs or wall")]
+        second: Solver,
+        #[structopt(long, default_value = "50", help = "Milliseconds between steps")]
+        delay: u64,
+        #[structopt(flatten)]
+        maze: MazeOpt,
+    },
+    #[structopt(about = "Step through the disjoint set unions that generate a maze")]
+    Teach {
+        #[structopt(flatten)]
+        maze: MazeOpt,
+    },
+    #[structopt(
+        about = "Print how hard a maze is: solution length, dead ends, branching and decisions"
+    )]
+    Analyze {
+        #[structopt(long, help = "Print the stats as JSON")]
+        json: bool,
+        #[structopt(flatten)]
+        maze: MazeOpt,
+    },
+    #[structopt(about = "Print a PDF of small mazes for a class, with a separate answer sheet")]
+    Worksheet {
+        #[structopt(short = "n", long, default_value = "6", help = "Number of mazes")]
+        count: usize,
+        #[structopt(long, default_value = "2", help = "Mazes across a page")]
+        columns: u32,
+        #[structopt(long, default_value = "3", help = "Mazes down a page")]
+        rows: u32,
+        #[structopt(
+            short = "o",
+            long,
+            parse(from_os_str),
+            default_value = "worksheet.pdf",
+            help = "Worksheet file"
+        )]
+        output: PathBuf,
+        #[structopt(
+            long,
+            parse(from_os_str),
+            default_value = "answers.pdf",
+            help = "Answer sheet file"
+        )]
+        answers: PathBuf,
+        #[structopt(flatten)]
+        maze: MazeOpt,
+    },
+    #[structopt(about = "Play back a recorded run")]
+    Replay {
+        #[structopt(
+            name = "REPLAY",
+            parse(from_os_str),
+            help = "Replay of a won run, see --save-dir"
+        )]
+        file: PathBuf,
+        #[structopt(
+            long,
+            parse(from_os_str),
+            value_name = "DIR",
+            help = "Show where the players of the replays in DIR went, g toggles it"
+        )]
+        heatmap: Option<PathBuf>,
+    },
+    #[structopt(about = "Write where the players of a directory of replays of one maze went")]
+    Heatmap {
+        #[structopt(name = "DIR", parse(from_os_str))]
+        dir: PathBuf,
+        #[structopt(
+            short = "f",
+            long,
+            default_value = "text",
+            help = "Output format: text, svg or png"
+        )]
+        format: Format,
+        #[structopt(
+            long,
+            help = "Always include ANSI colors, even when not writing to a terminal"
+        )]
+        color: bool,
+        #[structopt(long, help = "Never include ANSI colors [env: NO_COLOR]")]
+        no_color: bool,
+        #[structopt(long, default_value = "16", help = "Cell size in pixels of png images")]
+        cell_px: u32,
+        #[structopt(
+            long,
+            default_value = "#ff0000",
+            help = "Color of the most traveled cells of png images"
+        )]
+        path_color: Rgb,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "rusty_maze", about = "Rusty Maze Game")]
+struct Opt {
+    #[structopt(flatten)]
+    maze: MazeOpt,
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+}
+
+impl MazeOpt {
+    /// maze generation options
+    fn opts(&self) -> Opts {
+        Opts {
+            difficulty: self.difficulty,
+            border_penalty: self.border_penalty,
+            min_solution: self.min_solution,
+            seed: self.tournament.or(self.seed),
+            algorithm: self.algorithm,
+            wrap: self.wrap,
+            mask: self.mask.clone(),
+            braid: self.braid,
+            placement: self.placement,
+            exits: self.exits,
+            wall_weight: None,
+        }
+    }
+}
+
+/// maze dimensions from the options or the terminal size
+fn dimensions(opt: &MazeOpt) -> Result<(u16, u16), MazeError> {
+    if let Some(mask) = &opt.mask {
+        return Ok((mask.width, mask.height));
+    }
+    let termsize = termion::terminal_size().ok();
+    let size = if opt.large_print {
+        CellSize::Huge
+    } else {
+        opt.difficulty.cell_size()
+    };
+    let termwidth = termsize.map(|(w, _)| w / size.width());
+    // leave room for the status line and message pane below the maze
+    let termheight =
+        termsize.map(|(_, h)| (h.saturating_sub(MESSAGE_LINES) / size.height()).saturating_sub(1));
+
+    let width = opt.width.or(termwidth).unwrap_or(20).max(5);
+    let height = opt.height.or(termheight).unwrap_or(10).max(5);
+    Maze::check_size(width, height)?;
+    Ok((width, height))
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let opt: Opt = Opt::from_args();
 
-    // Get and lock the stdios.
+    if let Some(Command::Campaign {
+        name,
+        levels,
+        difficulty,
+        width,
+        height,
+        braid,
+        fog,
+        enemies,
+        resize,
+        output,
+    }) = opt.cmd
+    {
+        let curve = Curve {
+            levels,
+            difficulty,
+            width,
+            height,
+            braid,
+            fog,
+            enemies,
+            resize,
+        };
+        curve.generate(&name).save(&output)?;
+        println!("wrote {} levels to {}", levels, output.display());
+        return Ok(());
+    }
+
+    if let Some(Command::Calibrate {
+        sizes,
+        difficulties,
+        algorithms,
+        samples,
+        skill,
+        move_ms,
+        seed,
+    }) = opt.cmd
+    {
+        let calibration = Calibration {
+            sizes,
+            difficulties,
+            algorithms,
+            samples,
+            skill: skill.clamp(0.0, 1.0),
+            move_time: Duration::from_millis(move_ms),
+            seed,
+        };
+        println!("{}", calibrate::header());
+        for row in calibration.run()? {
+            println!("{}", row);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Worksheet {
+        count,
+        columns,
+        rows,
+        output,
+        answers,
+        maze,
+    }) = opt.cmd
+    {
+        // small mazes rather than the terminal size, the seeds are printed to find them again
+        let (width, height) = (maze.width.unwrap_or(10), maze.height.unwrap_or(10));
+        let seed = maze.seed.unwrap_or_else(rand::random);
+        let sheet = Worksheet::generate(count, width, height, &maze.opts(), seed, columns, rows)?;
+        std::fs::write(&output, sheet.pdf(false))?;
+        std::fs::write(&answers, sheet.pdf(true))?;
+        println!("wrote {} and {}", output.display(), answers.display());
+        return Ok(());
+    }
+
+    if let Some(Command::Replay { file, heatmap }) = opt.cmd {
+        let replay = Replay::load(file)?;
+        let heat = heatmap.map(Heatmap::from_dir).transpose()?;
+        if let Some(heat) = &heat {
+            if heat.maze().fingerprint() != replay.maze().fingerprint() {
+                return Err("the heatmap replays were recorded on another maze".into());
+            }
+        }
+        let stdout = std::io::stdout().into_raw_mode()?;
+        play_replay(
+            stdout,
+            termion::async_stdin().keys(),
+            &replay,
+            heat.as_ref(),
+            opt.maze.reduce_motion,
+        );
+        return Ok(());
+    }
+
+    if let Some(Command::Heatmap {
+        dir,
+        format,
+        color,
+        no_color,
+        cell_px,
+        path_color,
+    }) = opt.cmd
+    {
+        let heat = Heatmap::from_dir(dir)?;
+        match format {
+            Format::Text => {
+                let no_color_env = matches!(std::env::var_os("NO_COLOR"), Some(v) if !v.is_empty());
+                let color =
+                    !no_color && (color || (termion::is_tty(&std::io::stdout()) && !no_color_env));
+                print!("{}", heat.render(color));
+                println!("{} runs", heat.runs());
+            }
+            Format::Svg => print!("{}", heat.svg()),
+            Format::Png => {
+                let raster = Raster {
+                    cell: cell_px,
+                    path_color,
+                    ..Default::default()
+                };
+                heat.png(&mut std::io::stdout(), &raster)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Race {
+        first,
+        second,
+        delay,
+        maze,
+    }) = opt.cmd
+    {
+        let m = match maze.file {
+            Some(path) => GameState::load(path, maze.save_format)?.maze().clone(),
+            None => {
+                let (width, height) = dimensions(&maze)?;
+                Maze::generate(width, height, &maze.opts())?
+            }
+        };
+        let stdout = std::io::stdout().into_raw_mode()?;
+        play_race(
+            stdout,
+            termion::async_stdin().keys(),
+            &m,
+            (first, second),
+            Duration::from_millis(delay),
+            maze.reduce_motion || opt.maze.reduce_motion,
+        );
+        return Ok(());
+    }
+
+    if let Some(Command::Teach { maze }) = opt.cmd {
+        let (width, height) = dimensions(&maze)?;
+        let stdout = std::io::stdout().into_raw_mode()?;
+        play_lesson(
+            stdout,
+            termion::async_stdin().keys(),
+            width,
+            height,
+            &maze.opts(),
+        );
+        return Ok(());
+    }
+
+    if let Some(Command::Analyze { json, maze }) = opt.cmd {
+        let m = match maze.file {
+            Some(path) => GameState::load(path, maze.save_format)?.maze().clone(),
+            None => {
+                let (width, height) = dimensions(&maze)?;
+                Maze::generate(width, height, &maze.opts())?
+            }
+        };
+        let stats = m.analyze();
+        if json {
+            println!("{}", serde_json::to_string(&stats)?);
+        } else {
+            println!("{}", stats);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Stats { habits, file }) = opt.cmd {
+        if habits {
+            let path = file
+                .or_else(habits::habits_file)
+                .ok_or("no data directory to keep habits in")?;
+            println!("{}", Habits::load(path)?);
+        } else {
+            let path = stats::stats_file().ok_or("no data directory to keep statistics in")?;
+            println!("{}", Stats::load(path)?);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Merge {
+        base,
+        part,
+        left,
+        top,
+        output,
+    }) = opt.cmd
+    {
+        let base = GameState::load(base, None)?;
+        let part = GameState::load(part, None)?;
+        let merged = base
+            .maze()
+            .merge(part.maze(), Position { x: left, y: top })?;
+        save::save(&output, &GameState::new(merged, base.difficulty()), None)?;
+        println!("wrote {}", output.display());
+        return Ok(());
+    }
+
+    if let Some(Command::Reveal { file }) = opt.cmd {
+        println!("{}", stego::reveal(GameState::load(file, None)?.maze())?);
+        return Ok(());
+    }
+
+    if let Some(Command::Export {
+        format,
+        tileset,
+        color,
+        no_color,
+        solution,
+        labels,
+        cell_px,
+        wall_px,
+        wall_color,
+        background,
+        path_color,
+        pages,
+        grid,
+        overlap,
+        maze,
+    }) = opt.cmd
+    {
+        if labels && grid != Grid::Square {
+            return Err(ExportError::LabelsUnsupported(grid.name()).into());
+        }
+        match (grid, format) {
+            (Grid::Square, _) => (),
+            (Grid::Hex, Format::Text) => {
+                let (width, height) = dimensions(&maze)?;
+                let hex = GridMaze::generate(Hex { width, height }, &maze.opts());
+                let path = if solution {
+                    hex.solution().unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                print!("{}", Hex::render(&hex, &path));
+                return Ok(());
+            }
+            (Grid::Polar, Format::Svg) => {
+                // as many rings as fit the height
+                let (_, height) = dimensions(&maze)?;
+                let polar = GridMaze::generate(Polar::new(height / 2), &maze.opts());
+                let path = if solution {
+                    polar.solution().unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                print!("{}", Polar::svg(&polar, &path));
+                return Ok(());
+            }
+            (Grid::Hex, _) => return Err(GridError::FormatOnly("hex", "text").into()),
+            (Grid::Polar, _) => return Err(GridError::FormatOnly("polar", "svg").into()),
+        }
+        let m = match maze.file {
+            Some(path) => GameState::load(path, maze.save_format)?.maze().clone(),
+            None => {
+                let (width, height) = dimensions(&maze)?;
+                match &maze.message {
+                    Some(text) => stego::hide(width, height, text)?,
+                    None => Maze::generate(width, height, &maze.opts())?,
+                }
+            }
+        };
+        // colors by default only when writing to a terminal, see https://no-color.org
+        let no_color_env = matches!(std::env::var_os("NO_COLOR"), Some(v) if !v.is_empty());
+        let color = !no_color && (color || (termion::is_tty(&std::io::stdout()) && !no_color_env));
+        let raster = Raster {
+            cell: cell_px,
+            wall: wall_px,
+            wall_color: wall_color.unwrap_or_else(|| tileset.color()),
+            background,
+            path_color,
+        };
+        if let Some(pages) = pages {
+            if format != Format::Png {
+                return Err(ExportError::PagesUnsupported.into());
+            }
+            if labels {
+                return Err(ExportError::LabelsUnsupported("poster").into());
+            }
+            let path = if solution {
+                m.solution().unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            let images = poster(&m, &path, &raster, tileset, pages, overlap)?;
+            for (i, image) in images.iter().enumerate() {
+                let (row, column) = (i as u32 / pages.columns + 1, i as u32 % pages.columns + 1);
+                let name = format!("poster-{}-{}.png", row, column);
+                std::fs::write(&name, image)?;
+                println!("wrote {}", name);
+            }
+            return Ok(());
+        }
+        export(
+            &mut std::io::stdout(),
+            &m,
+            format,
+            tileset,
+            color,
+            Marks { solution, labels },
+            &raster,
+        )?;
+        return Ok(());
+    }
+
+    // Get and lock stdout, stdin is read without blocking so the clock keeps running.
     let stdout = std::io::stdout();
     let stdout = stdout.lock();
-    let stdin = std::io::stdin();
-    let stdin = stdin.lock();
 
     // We go to raw mode to make the control over the terminal more fine-grained.
-    let stdout = stdout.into_raw_mode()?;
-
-    if let Some(path) = opt.file {
-        let file = File::open(path)?;
-        let state: GameState = ron::de::from_reader(BufReader::new(file))?;
-        Game::restore(stdout, stdin.keys(), &state);
-    } else {
-        let termsize = termion::terminal_size().ok();
-        let termwidth = termsize.map(|(w, _)| w / 4);
-        let termheight = termsize.map(|(_, h)| (h / 2) - 1);
+    let mut stdout = stdout.into_raw_mode()?;
+    // the terminal answers the background query on stdin, before the game reads it
+    let theme = match opt.maze.theme.as_deref() {
+        None => None,
+        Some("auto") => Some(Theme::from(
+            Background::query(&mut stdout)
+                .or_else(Background::from_env)
+                .unwrap_or_else(Background::now),
+        )),
+        Some("time") => Some(Theme::from(Background::now())),
+        Some(name) => Some(Theme::named(name).ok_or_else(|| format!("unknown theme {}", name))?),
+    };
+    let stdin = termion::async_stdin();
 
-        let width = opt.width.or(termwidth).unwrap().max(5);
-        let height = opt.height.or(termheight).unwrap().max(5);
+    let (mut width, mut height) = dimensions(&opt.maze)?;
+    if opt.maze.rotate > 0 {
+        // a turned maze has to fit the screen both ways
+        width = width.min(height);
+        height = width;
+    }
+    let tournament = match opt.maze.tournament {
+        Some(seed) => {
+            let key = std::env::var(KEY_ENV)
+                .map_err(|_| format!("{} must be set to play a tournament", KEY_ENV))?;
+            // every player gets the same maze whatever their terminal
+            width = tournament::WIDTH;
+            height = tournament::HEIGHT;
+            Some(Tournament::new(seed, key.as_bytes()))
+        }
+        None => None,
+    };
+    let last_game = save::last_game();
+    let opts = GameOpts {
+        maze: match &tournament {
+            Some(t) => t.opts(),
+            None => opt.maze.opts(),
+        },
+        checkpoints: opt.maze.checkpoints,
+        // only the levels of a campaign bring enemies
+        enemies: 0,
+        treasure: opt.maze.treasure,
+        tournament,
+        coop: opt.maze.coop,
+        rotate_every: opt.maze.rotate,
+        memory: opt.maze.memory,
+        preview: opt.maze.preview,
+        assist: opt.maze.assist,
+        large_print: opt.maze.large_print,
+        fog: opt.maze.fog.unwrap_or_else(|| opt.maze.difficulty.fog()),
+        save_format: opt.maze.save_format,
+        save_dir: opt.maze.save_dir.clone().unwrap_or_default(),
+        runs_dir: opt
+            .maze
+            .save_dir
+            .clone()
+            .or_else(replay::runs_dir)
+            .unwrap_or_default(),
+        autosave: last_game.clone(),
+        records_file: records::records_file(),
+        stats_file: stats::stats_file(),
+        habits_file: habits::habits_file(),
+        leaderboard: leaderboard::leaderboard_file(),
+        world_file: world::world_file(),
+        progress_dir: campaign::progress_dir(),
+        endless_file: campaign::endless_file(),
+        // USER may be set but empty
+        player: match opt.maze.name.trim() {
+            "" => "player".to_string(),
+            name => name.to_string(),
+        },
+        fps: opt.maze.fps,
+        theme,
+        compass: opt.maze.compass,
+        return_trip: opt.maze.return_trip,
+        reduce_motion: opt.maze.reduce_motion,
+        all_exits: opt.maze.all_exits,
+        keys: match keymap::keys_file() {
+            Some(path) => KeyMap::load(path)?,
+            None => KeyMap::default(),
+        },
+        hooks: match hooks::hooks_file() {
+            Some(path) => Hooks::load(path)?,
+            None => Hooks::default(),
+        },
+        volume: if opt.maze.mute {
+            0.0
+        } else {
+            opt.maze.volume.clamp(0.0, 1.0)
+        },
+    };
 
-        Game::init(stdout, stdin.keys(), width, height, opt.difficulty);
+    let file = if opt.maze.resume {
+        Some(last_game.ok_or("no data directory to resume from")?)
+    } else {
+        opt.maze.file
+    };
+    if let Some(path) = file {
+        let state = GameState::load(path, opt.maze.save_format)?;
+        Game::restore(stdout, stdin.keys(), &state, opts);
+    } else if let Some(text) = &opt.maze.message {
+        let maze = stego::hide(width, height, text)?;
+        let state = GameState::new(maze, opt.maze.difficulty);
+        Game::restore(stdout, stdin.keys(), &state, opts);
+    } else if let Some(path) = opt.maze.pack {
+        let pack = LevelPack::load(path)?;
+        Game::campaign(stdout, stdin.keys(), &pack, opts);
+    } else if opt.maze.campaign {
+        Game::endless(stdout, stdin.keys(), width, height, opts);
+    } else if opt.maze.world {
+        Game::world(stdout, stdin.keys(), width, height, opts);
+    } else {
+        Game::init(stdout, stdin.keys(), width, height, opts);
     }
 
     Ok(())
@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MaskError {
+    #[error("mask has fewer than two open cells")]
+    TooSmall,
+    #[error("mask has {0} cells, more than a maze can hold")]
+    TooLarge(usize),
+    #[error("open cells of the mask aren't all connected, row {} column {} is cut off", .0 + 1, .1 + 1)]
+    Disconnected(usize, usize),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Shape of a maze, cells marked `#` in the mask file are blocked and left out of the
+/// maze, any other character is an open cell. Lines shorter than the longest one are
+/// open at the end:
+///
+/// ```text
+/// .##...##.
+/// .........
+/// ##.....##
+/// ####.####
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mask {
+    pub width: u16,
+    pub height: u16,
+    /// blocked cells in reading order
+    blocked: Vec<bool>,
+}
+
+impl Mask {
+    /// read the mask from a file
+    pub fn load(path: &str) -> Result<Mask, MaskError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn is_blocked(&self, x: u16, y: u16) -> bool {
+        self.blocked[(y * self.width + x) as usize]
+    }
+
+    /// indexes of the blocked cells, in reading order
+    pub fn blocked_cells(&self) -> Vec<u16> {
+        (0..self.blocked.len() as u16)
+            .filter(|&c| self.blocked[c as usize])
+            .collect()
+    }
+
+    /// check the open cells are all reachable from each other, walls aside
+    fn check_connected(&self) -> Result<(), MaskError> {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let open: Vec<usize> = (0..w * h).filter(|&c| !self.blocked[c]).collect();
+        if open.len() < 2 {
+            return Err(MaskError::TooSmall);
+        }
+        let mut seen = vec![false; w * h];
+        let mut queue = VecDeque::new();
+        seen[open[0]] = true;
+        queue.push_back(open[0]);
+        while let Some(c) = queue.pop_front() {
+            let (x, y) = (c % w, c / w);
+            let mut next = Vec::new();
+            if x > 0 {
+                next.push(c - 1);
+            }
+            if x + 1 < w {
+                next.push(c + 1);
+            }
+            if y > 0 {
+                next.push(c - w);
+            }
+            if y + 1 < h {
+                next.push(c + w);
+            }
+            for n in next {
+                if !self.blocked[n] && !seen[n] {
+                    seen[n] = true;
+                    queue.push_back(n);
+                }
+            }
+        }
+        match open.iter().find(|&&c| !seen[c]) {
+            Some(&c) => Err(MaskError::Disconnected(c / w, c % w)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Mask {
+    type Err = MaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<Vec<char>> = s.lines().map(|l| l.chars().collect()).collect();
+        let width = lines.iter().map(Vec::len).max().unwrap_or(0);
+        let cells = width * lines.len();
+        if cells > u16::MAX as usize {
+            return Err(MaskError::TooLarge(cells));
+        }
+        let mut blocked = Vec::with_capacity(cells);
+        for line in lines.iter() {
+            for x in 0..width {
+                blocked.push(line.get(x) == Some(&'#'));
+            }
+        }
+        let mask = Mask {
+            width: width as u16,
+            height: lines.len() as u16,
+            blocked,
+        };
+        mask.check_connected()?;
+        Ok(mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{Maze, Opts, Position};
+
+    #[test]
+    fn test_mask() {
+        let mask: Mask = ".##...##.\n.........\n##.....##\n####.####"
+            .parse()
+            .unwrap();
+        assert_eq!((mask.width, mask.height), (9, 4));
+        assert!(mask.is_blocked(1, 0));
+        assert!(!mask.is_blocked(4, 3));
+        assert!(matches!(
+            ".#.\n.#.".parse::<Mask>(),
+            Err(MaskError::Disconnected(0, 2))
+        ));
+        assert!(matches!("#.#".parse::<Mask>(), Err(MaskError::TooSmall)));
+
+        let opts = Opts {
+            seed: Some(4),
+            mask: Some(mask.clone()),
+            ..Default::default()
+        };
+        let m = Maze::generate(9, 4, &opts).unwrap();
+        assert_eq!(m.entrance(), Position { x: 0, y: 0 });
+        assert_eq!(m.exit(), Position { x: 4, y: 3 });
+        // every open cell is reached and no blocked one
+        let dist = m.distance_map(m.entrance());
+        for (d, blocked) in dist.iter().zip(mask.blocked.iter()) {
+            assert_eq!(d.is_some(), !blocked);
+        }
+        let board = m.ui().draw();
+        assert_eq!(board[1][6], '█');
+        assert_eq!(board[1][2], ' ');
+    }
+}
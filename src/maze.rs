@@ -1,14 +1,20 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::iter::FromIterator;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::vec;
 
-use rand::Rng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
 use serde::{Deserialize, Serialize};
+use termion::{color, style};
 use thiserror::Error;
 
 use crate::disjset::DisjSet;
-use crate::disjset::Roots::DisJoint;
+use crate::disjset::Roots::{DisJoint, Same};
+use crate::mask::Mask;
 use crate::maze::Direction::{Down, Left, Right, Up};
 
 #[derive(Error, Debug)]
@@ -19,6 +25,39 @@ pub enum MazeError {
     DifficultyParseError,
     #[error("invalid size setting")]
     CellDrawSizeParseError,
+    #[error("invalid algorithm, expected kruskal, backtracker, prim or wilson")]
+    AlgorithmParseError,
+    #[error("invalid placement, expected corners, farthest or borders")]
+    PlacementParseError,
+    #[error("packed walls don't fit a {0}x{1} maze")]
+    PackedWallsParseError(u16, u16),
+    #[error("a {0}x{1} maze doesn't fit there")]
+    MergeOutOfBounds(u16, u16),
+    #[error("merged maze has no way from the entrance to an exit")]
+    MergeUnsolvable,
+    #[error("regions with cells left out by a mask can't be generated again")]
+    RegionBlocked,
+    #[error("a {0}x{1} region has no cells to generate again")]
+    RegionEmpty(u16, u16),
+    #[error("a {0}x{1} maze has more than {} cells", MAX_CELLS)]
+    TooLarge(u16, u16),
+}
+
+/// Move of a path that can't be made, `index` counts from the first move.
+#[derive(Error, Debug, PartialEq)]
+pub enum PathError {
+    #[error("move {index} {dir:?} from {pos:?} runs into a wall")]
+    Wall {
+        index: usize,
+        pos: Position,
+        dir: Direction,
+    },
+    #[error("move {index} {dir:?} from {pos:?} leaves the maze")]
+    OutOfBounds {
+        index: usize,
+        pos: Position,
+        dir: Direction,
+    },
 }
 
 #[derive(Clone, Copy, Hash, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -29,7 +68,574 @@ pub enum Direction {
     Down,
 }
 
-static DIRECTIONS: [Direction; 4] = [Left, Right, Up, Down];
+pub(crate) static DIRECTIONS: [Direction; 4] = [Left, Right, Up, Down];
+
+impl Direction {
+    /// the way back
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Left => Right,
+            Right => Left,
+            Up => Down,
+            Down => Up,
+        }
+    }
+
+    /// a quarter turn counterclockwise
+    pub fn turn_left(&self) -> Direction {
+        self.turn_right().opposite()
+    }
+
+    /// a quarter turn clockwise
+    pub fn turn_right(&self) -> Direction {
+        match self {
+            Up => Right,
+            Right => Down,
+            Down => Left,
+            Left => Up,
+        }
+    }
+}
+
+/// Number of mazes generated at most while trying to meet the minimum solution length.
+const MAX_ATTEMPTS: usize = 100;
+
+/// Most cells of a maze, they are numbered by a u16.
+pub const MAX_CELLS: usize = u16::MAX as usize;
+
+/// Fraction of the remaining walls knocked down in Easy mazes.
+const EASY_OPENINGS: f32 = 0.2;
+
+/// Share of the heaviest weight given to the walls weighing 0 among the ones picked from,
+/// see `Generation::pick_weighted`.
+const MIN_WEIGHT: f32 = 0.001;
+
+/// Progress of a maze generation, see `Maze::generate_iter`.
+#[derive(Debug)]
+pub enum GenerationEvent {
+    /// the wall between the two cells was knocked down
+    WallRemoved((u16, u16)),
+    /// the wall between the two cells was considered and left standing, its cells were
+    /// already connected
+    WallKept((u16, u16)),
+    /// the entrance and the exit are now connected
+    Connected,
+    /// generation is complete
+    Done(Maze),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Stage {
+    /// joining disjoint sets of cells
+    Carving,
+    /// knocking down the given number of extra walls
+    Opening(usize),
+    Finished,
+}
+
+/// Counts of a generation in progress, see `Maze::generate_with_observer`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct GenerationStats {
+    /// walls picked so far, knocked down or not
+    pub considered: usize,
+    pub removed: usize,
+    /// sets of cells not connected to each other yet
+    pub sets: usize,
+}
+
+/// Iterator over the events of a maze generation.
+pub struct Generation {
+    maze: Option<Maze>,
+    cells: DisjSet,
+    rng: Pcg64,
+    algorithm: Algorithm,
+    /// cells of the current walk of the recursive backtracker or Wilson's algorithm
+    stack: Vec<u16>,
+    /// walls from the carved cells to the others, for Prim's algorithm
+    frontier: Vec<(u16, u16)>,
+    /// shuffled cells to start Wilson's walks from
+    starts: Vec<u16>,
+    difficulty: Difficulty,
+    penalty: f32,
+    /// share of the dead ends opened once carved
+    braid: f32,
+    placement: Placement,
+    /// exits wanted in all, see `Opts::exits`
+    exits: usize,
+    weight: Option<WallWeight>,
+    /// walls left for Kruskal's algorithm to consider when they are weighted, see
+    /// `weighted_order`
+    order: Vec<Wall>,
+    connected: bool,
+    stage: Stage,
+    pending: VecDeque<GenerationEvent>,
+    considered: usize,
+    removed: usize,
+}
+
+impl Generation {
+    /// counts of the events so far
+    pub fn stats(&self) -> GenerationStats {
+        GenerationStats {
+            considered: self.considered,
+            removed: self.removed,
+            sets: self.cells.distinct_sets(),
+        }
+    }
+
+    /// Random index below `len`, drawn as a u32 so a seed picks the same index on 32 and
+    /// 64 bit platforms.
+    fn index(&mut self, len: usize) -> usize {
+        self.rng.gen_range(0..len as u32) as usize
+    }
+
+    /// pick a random wall, border walls are spared according to the penalty
+    fn pick(&mut self, m: &Maze) -> usize {
+        loop {
+            let i = self.index(m.walls.len());
+            if !m.is_border_wall(m.walls[i]) || self.rng.gen::<f32>() >= self.penalty {
+                return i;
+            }
+        }
+    }
+
+    /// weight of the wall in the random picks, border walls are spared according to the
+    /// penalty
+    fn weight_of(&self, weight: &WallWeight, m: &Maze, w: &Wall) -> f32 {
+        let spared = if m.is_border_wall(*w) {
+            self.penalty
+        } else {
+            0.0
+        };
+        weight.of(w, m).max(0.0) * (1.0 - spared)
+    }
+
+    /// Random index of one of the walls, picked by their `WallWeight` with border walls
+    /// spared according to the penalty. Uniform when none of them weighs anything. Walls
+    /// weighing 0 next to others that do get a small share, a walk boxed in by them would
+    /// never end otherwise.
+    fn pick_weighted(&mut self, m: &Maze, walls: &[Wall]) -> usize {
+        let weight = match &self.weight {
+            Some(w) => w.clone(),
+            None => return self.index(walls.len()),
+        };
+        let mut weights: Vec<f32> = walls
+            .iter()
+            .map(|w| self.weight_of(&weight, m, w))
+            .collect();
+        let max = weights.iter().copied().fold(0.0, f32::max);
+        if !max.is_normal() {
+            return self.index(walls.len());
+        }
+        for w in weights.iter_mut() {
+            *w = w.max(max * MIN_WEIGHT);
+        }
+        let total: f32 = weights.iter().sum();
+        let mut x = self.rng.gen::<f32>() * total;
+        for (i, w) in weights.iter().enumerate() {
+            if x < *w {
+                return i;
+            }
+            x -= w;
+        }
+        // rounding left x past the end
+        walls.len() - 1
+    }
+
+    /// Walls of the maze in the order Kruskal's algorithm considers them, the next one
+    /// last. Every wall is weighed once and gets a random key `ln(u) / weight`, sorting by
+    /// it draws the walls by their weight without replacement, the ones weighing nothing
+    /// last in random order.
+    fn weighted_order(&mut self, m: &Maze, weight: &WallWeight) -> Vec<Wall> {
+        let mut keyed: Vec<(f32, Wall)> = m
+            .walls
+            .iter()
+            .map(|w| {
+                let u: f32 = self.rng.gen();
+                (u.ln() / self.weight_of(weight, m, w), *w)
+            })
+            .collect();
+        // shuffled first so the stable sort leaves the walls of equal keys in random order
+        keyed.shuffle(&mut self.rng);
+        keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        keyed.into_iter().map(|(_, w)| w).collect()
+    }
+
+    /// knock down a random wall between two unconnected cells
+    fn carve_random(&mut self, m: &mut Maze) {
+        let w = match self.weight.clone() {
+            Some(weight) => {
+                if self.order.is_empty() {
+                    self.order = self.weighted_order(m, &weight);
+                }
+                match self.order.pop() {
+                    Some(w) => w,
+                    None => return,
+                }
+            }
+            None => m.walls[self.pick(m)],
+        };
+        // only remove walls of different sets, otherwise the maze will be trivialized
+        if let DisJoint(..) = self.cells.find_roots(w.0 as usize, w.1 as usize) {
+            self.open(m, w.0, w.1);
+        } else {
+            self.keep_wall(w);
+        }
+    }
+
+    /// check if the cell is part of the maze carved from the entrance
+    fn is_carved(&mut self, m: &Maze, c: u16) -> bool {
+        matches!(self.cells.find_roots(m.enter as usize, c as usize), Same(_))
+    }
+
+    /// Random neighbour of the cell among the ones kept by the filter, border walls are
+    /// spared according to the penalty while there are other ways.
+    fn pick_neighbour<F>(&mut self, m: &Maze, c: u16, keep: F) -> Option<u16>
+    where
+        F: Fn(&mut Generation, u16) -> bool,
+    {
+        let p = m.cell_to_pos(c);
+        let mut next: Vec<u16> = DIRECTIONS
+            .iter()
+            .filter_map(|d| m.neighbour(p, d))
+            .map(|n| m.pos_to_cell(n))
+            .collect();
+        next.retain(|n| keep(self, *n));
+        if self.weight.is_some() && !next.is_empty() {
+            let walls: Vec<Wall> = next.iter().map(|n| (c, *n)).collect();
+            return Some(next[self.pick_weighted(m, &walls)]);
+        }
+        if next.iter().any(|n| !m.is_border_wall((c, *n))) && self.rng.gen::<f32>() < self.penalty {
+            next.retain(|n| !m.is_border_wall((c, *n)));
+        }
+        if next.is_empty() {
+            None
+        } else {
+            Some(next[self.index(next.len())])
+        }
+    }
+
+    /// remove the wall between two neighbour cells
+    fn open(&mut self, m: &mut Maze, a: u16, b: u16) {
+        if let Some(i) = m.walls.iter().position(|w| *w == (a, b) || *w == (b, a)) {
+            self.remove_wall(m, i);
+        }
+    }
+
+    /// walk to a random unvisited neighbour of the last cell, backing up at dead ends
+    fn carve_backtracker(&mut self, m: &mut Maze) {
+        let c = match self.stack.last() {
+            Some(c) => *c,
+            None => {
+                self.stack.push(m.enter);
+                return;
+            }
+        };
+        match self.pick_neighbour(m, c, |g, n| !g.is_carved(m, n)) {
+            Some(n) => {
+                self.open(m, c, n);
+                self.stack.push(n);
+            }
+            None => {
+                self.stack.pop();
+            }
+        }
+    }
+
+    /// grow the maze from a random wall on its frontier
+    fn carve_prim(&mut self, m: &mut Maze) {
+        if self.frontier.is_empty() {
+            self.add_frontier(m, m.enter);
+        }
+        let mut i = self.index(self.frontier.len());
+        if self.weight.is_some() {
+            let frontier = std::mem::take(&mut self.frontier);
+            i = self.pick_weighted(m, &frontier);
+            self.frontier = frontier;
+        } else if m.is_border_wall(self.frontier[i]) && self.rng.gen::<f32>() < self.penalty {
+            i = self.index(self.frontier.len());
+        }
+        let (c, n) = self.frontier.swap_remove(i);
+        if !self.is_carved(m, n) {
+            self.open(m, c, n);
+            self.add_frontier(m, n);
+        } else {
+            self.keep_wall((c, n));
+        }
+    }
+
+    /// add the walls from a newly carved cell to the cells not carved yet
+    fn add_frontier(&mut self, m: &Maze, c: u16) {
+        let p = m.cell_to_pos(c);
+        for n in DIRECTIONS.iter().filter_map(|d| m.neighbour(p, d)) {
+            let n = m.pos_to_cell(n);
+            if !self.is_carved(m, n) {
+                self.frontier.push((c, n));
+            }
+        }
+    }
+
+    /// Random walk from a cell outside the maze until it runs into the maze, erasing the
+    /// loops it makes, then carve the walk.
+    fn carve_wilson(&mut self, m: &mut Maze) {
+        let c = match self.stack.last() {
+            Some(c) => *c,
+            None => {
+                if self.starts.is_empty() {
+                    self.starts = (0..m.size)
+                        .filter(|&c| !m.is_blocked(m.cell_to_pos(c)))
+                        .collect();
+                    self.starts.shuffle(&mut self.rng);
+                }
+                while let Some(c) = self.starts.pop() {
+                    if !self.is_carved(m, c) {
+                        self.stack.push(c);
+                        break;
+                    }
+                }
+                return;
+            }
+        };
+        let n = match self.pick_neighbour(m, c, |_, _| true) {
+            Some(n) => n,
+            None => return,
+        };
+        if let Some(i) = self.stack.iter().position(|s| *s == n) {
+            self.stack.truncate(i + 1);
+        } else if self.is_carved(m, n) {
+            self.stack.push(n);
+            let walk = std::mem::take(&mut self.stack);
+            for w in walk.windows(2) {
+                self.open(m, w[0], w[1]);
+            }
+        } else {
+            self.stack.push(n);
+        }
+    }
+    /// Knock down a wall of a share of the dead ends, preferring walls into other dead
+    /// ends so one removal opens both. The loops give the maze more than one way out.
+    fn braid(&mut self, m: &mut Maze) {
+        let mut dead_ends = m.dead_ends();
+        dead_ends.shuffle(&mut self.rng);
+        let count = (dead_ends.len() as f32 * self.braid).round() as usize;
+        for p in dead_ends.into_iter().take(count) {
+            // opened along with an earlier one
+            if m.movements(p).len() != 1 {
+                continue;
+            }
+            let closed: Vec<Position> = DIRECTIONS
+                .iter()
+                .filter(|d| m.move_pos(p, d).is_none())
+                .filter_map(|d| m.neighbour(p, d))
+                .collect();
+            let dead: Vec<Position> = closed
+                .iter()
+                .copied()
+                .filter(|n| m.movements(*n).len() == 1)
+                .collect();
+            let pick = if dead.is_empty() { closed } else { dead };
+            if !pick.is_empty() {
+                let n = pick[self.index(pick.len())];
+                self.open(m, m.pos_to_cell(p), m.pos_to_cell(n));
+            }
+        }
+    }
+
+    /// Open more exits on random border cells reachable from the entrance until there are
+    /// `exits` in all, fewer when the border runs out of cells.
+    fn add_exits(&mut self, m: &mut Maze) {
+        let dist = m.distance_map(m.entrance());
+        let mut border: Vec<u16> = (0..m.size)
+            .filter(|&c| {
+                let p = m.cell_to_pos(c);
+                (p.x == 0 || p.y == 0 || p.x + 1 == m.width || p.y + 1 == m.height)
+                    && dist[c as usize].is_some()
+                    && c != m.enter
+                    && c != m.exit
+            })
+            .collect();
+        border.shuffle(&mut self.rng);
+        border.truncate(self.exits - 1);
+        m.more_exits = border;
+    }
+
+    /// report a wall left standing
+    fn keep_wall(&mut self, w: (u16, u16)) {
+        self.considered += 1;
+        self.pending.push_back(GenerationEvent::WallKept(w));
+    }
+
+    /// remove the wall joining the sets of its cells and report it
+    fn remove_wall(&mut self, m: &mut Maze, i: usize) {
+        self.considered += 1;
+        self.removed += 1;
+        let w = m.walls.remove(i);
+        if let DisJoint(r1, r2) = self.cells.find_roots(w.0 as usize, w.1 as usize) {
+            self.cells.union(r1, r2);
+        }
+        self.pending.push_back(GenerationEvent::WallRemoved(w));
+        if !self.connected {
+            if let Same(_) = self.cells.find_roots(m.enter as usize, m.exit as usize) {
+                self.connected = true;
+                self.pending.push_back(GenerationEvent::Connected);
+            }
+        }
+    }
+
+    /// advance the generation until it has events to report
+    fn step(&mut self) {
+        let mut m = match self.maze.take() {
+            Some(m) => m,
+            None => return,
+        };
+        match self.stage {
+            Stage::Carving => {
+                let carved = match self.difficulty {
+                    // remove walls until enter and exit are of the same set
+                    Difficulty::Normal => self.connected,
+                    // remove walls until every cell in the maze if part of the same set
+                    // blocked cells stay sets of their own
+                    _ => self.cells.distinct_sets() == 1 + m.blocked.len(),
+                };
+                if carved {
+                    self.stage = match self.difficulty {
+                        // open extra passages to create loops and shortcuts
+                        Difficulty::Easy => {
+                            Stage::Opening((m.walls.len() as f32 * EASY_OPENINGS) as usize)
+                        }
+                        _ => Stage::Opening(0),
+                    };
+                } else {
+                    match self.algorithm {
+                        Algorithm::RandomKruskal => self.carve_random(&mut m),
+                        Algorithm::RecursiveBacktracker => self.carve_backtracker(&mut m),
+                        Algorithm::Prim => self.carve_prim(&mut m),
+                        Algorithm::Wilson => self.carve_wilson(&mut m),
+                    }
+                }
+            }
+            Stage::Opening(0) => {
+                self.braid(&mut m);
+                if self.placement == Placement::Farthest || self.difficulty.farthest() {
+                    m.place_farthest();
+                }
+                if self.exits > 1 {
+                    self.add_exits(&mut m);
+                }
+                self.stage = Stage::Finished;
+                self.pending.push_back(GenerationEvent::Done(m));
+                return;
+            }
+            Stage::Opening(n) => {
+                if m.walls.is_empty() {
+                    self.stage = Stage::Opening(0);
+                } else {
+                    let i = self.index(m.walls.len());
+                    let w = m.walls.remove(i);
+                    self.considered += 1;
+                    self.removed += 1;
+                    self.pending.push_back(GenerationEvent::WallRemoved(w));
+                    self.stage = Stage::Opening(n - 1);
+                }
+            }
+            Stage::Finished => {}
+        }
+        self.maze = Some(m);
+    }
+}
+
+impl Iterator for Generation {
+    type Item = GenerationEvent;
+
+    fn next(&mut self) -> Option<GenerationEvent> {
+        while self.pending.is_empty() && self.maze.is_some() {
+            self.step();
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// Tree of the passages reachable from the maze entrance. Each cell points to its parent
+/// on the shortest way back to the entrance. Perfect mazes are exactly this tree.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpanningTree {
+    width: u16,
+    /// parent cell of every cell, the root and unreachable cells have none
+    parent: Vec<Option<u16>>,
+    /// distance of every cell to the root, None when unreachable
+    depth: Vec<Option<usize>>,
+}
+
+impl SpanningTree {
+    fn cell(&self, p: Position) -> usize {
+        (p.y * self.width + p.x) as usize
+    }
+
+    fn pos(&self, c: u16) -> Position {
+        Position {
+            x: c % self.width,
+            y: c / self.width,
+        }
+    }
+
+    /// next position on the way back to the entrance
+    pub fn parent(&self, p: Position) -> Option<Position> {
+        self.parent[self.cell(p)].map(|c| self.pos(c))
+    }
+
+    /// moves from the entrance to the position, None when it can't be reached
+    pub fn depth(&self, p: Position) -> Option<usize> {
+        self.depth[self.cell(p)]
+    }
+
+    /// Path from the entrance to the position, both included.
+    pub fn path_to(&self, p: Position) -> Option<Vec<Position>> {
+        self.depth(p)?;
+        let mut path = vec![p];
+        let mut current = p;
+        while let Some(parent) = self.parent(current) {
+            path.push(parent);
+            current = parent;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Number of cells in the subtree of every cell (itself included), indexed by cell.
+    /// Unreachable cells have an empty subtree.
+    pub fn subtree_sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self.depth.iter().map(|d| d.map_or(0, |_| 1)).collect();
+        // walk cells from the deepest up so children are complete before their parents
+        let mut cells: Vec<usize> = (0..self.depth.len())
+            .filter(|c| self.depth[*c].is_some())
+            .collect();
+        cells.sort_unstable_by_key(|c| std::cmp::Reverse(self.depth[*c]));
+        for c in cells {
+            if let Some(p) = self.parent[c] {
+                sizes[p as usize] += sizes[c];
+            }
+        }
+        sizes
+    }
+}
+
+/// Rectangle of maze cells.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Rect {
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn contains(&self, p: Position) -> bool {
+        p.x >= self.left
+            && (p.x as u32) < self.left as u32 + self.width as u32
+            && p.y >= self.top
+            && (p.y as u32) < self.top as u32 + self.height as u32
+    }
+}
 
 pub struct CellBox {
     pub top: usize,
@@ -44,13 +650,64 @@ pub struct Position {
     pub x: u16,
 }
 
-impl Into<(usize, usize)> for Position {
-    fn into(self) -> (usize, usize) {
-        (self.x as usize, self.y as usize)
+impl From<Position> for (usize, usize) {
+    fn from(p: Position) -> (usize, usize) {
+        (p.x as usize, p.y as usize)
     }
 }
 
 impl Position {
+    /// direction of an adjacent position
+    pub fn direction_to(&self, p: &Position) -> Option<Direction> {
+        match (p.x as i32 - self.x as i32, p.y as i32 - self.y as i32) {
+            (-1, 0) => Some(Left),
+            (1, 0) => Some(Right),
+            (0, -1) => Some(Up),
+            (0, 1) => Some(Down),
+            _ => None,
+        }
+    }
+
+    /// The position `amount` away in the direction, None when it would leave the range of
+    /// coordinates.
+    pub fn checked_mv(&self, d: &Direction, amount: u16) -> Option<Position> {
+        let (x, y) = (self.x, self.y);
+        Some(match d {
+            Left => Position {
+                x: x.checked_sub(amount)?,
+                y,
+            },
+            Right => Position {
+                x: x.checked_add(amount)?,
+                y,
+            },
+            Up => Position {
+                x,
+                y: y.checked_sub(amount)?,
+            },
+            Down => Position {
+                x,
+                y: y.checked_add(amount)?,
+            },
+        })
+    }
+
+    /// moves between the positions, walls aside
+    pub fn manhattan(&self, p: &Position) -> u32 {
+        let dx = self.x.max(p.x) - self.x.min(p.x);
+        let dy = self.y.max(p.y) - self.y.min(p.y);
+        dx as u32 + dy as u32
+    }
+
+    /// moves between the positions when diagonal moves count as one
+    pub fn chebyshev(&self, p: &Position) -> u32 {
+        let dx = self.x.max(p.x) - self.x.min(p.x);
+        let dy = self.y.max(p.y) - self.y.min(p.y);
+        dx.max(dy) as u32
+    }
+
+    /// The position `amount` away in the direction, see `checked_mv` for one that can't
+    /// underflow.
     pub fn mv(&self, d: &Direction, amount: u16) -> Position {
         match d {
             Left => Position {
@@ -73,38 +730,99 @@ impl Position {
     }
 }
 
+/// Quarter turns clockwise of a maze and everything in it.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Rotation(u8);
+
+impl Rotation {
+    pub fn new(quarter_turns: u8) -> Rotation {
+        Rotation(quarter_turns % 4)
+    }
+
+    pub fn quarter_turns(&self) -> u8 {
+        self.0
+    }
+
+    /// one more quarter turn clockwise
+    pub fn turn(self) -> Rotation {
+        Rotation::new(self.0 + 1)
+    }
+
+    /// rotation undoing this one
+    pub fn inverse(self) -> Rotation {
+        Rotation::new(4 - self.0)
+    }
+
+    /// the direction once rotated
+    pub fn direction(&self, d: &Direction) -> Direction {
+        (0..self.0).fold(*d, |d, _| d.turn_right())
+    }
+
+    /// the position of a `width` x `height` grid once rotated
+    pub fn position(&self, p: Position, width: u16, height: u16) -> Position {
+        let (mut p, mut h) = (p, height);
+        let mut w = width;
+        for _ in 0..self.0 {
+            p = Position {
+                x: h - 1 - p.y,
+                y: p.x,
+            };
+            std::mem::swap(&mut w, &mut h);
+        }
+        p
+    }
+
+    /// Joystick on the rotated maze mirroring the given one, e.g. to draw it.
+    pub fn joystick<'a>(&self, rotated: &'a Maze, j: &Joystick) -> Joystick<'a> {
+        let (w, h) = (j.maze.width, j.maze.height);
+        Joystick {
+            pos: self.position(j.pos, w, h),
+            maze: rotated,
+            history: j
+                .history
+                .iter()
+                .map(|(p, d)| (self.position(*p, w, h), d.map(|d| self.direction(&d))))
+                .collect(),
+            assist: j.assist,
+        }
+    }
+}
+
 pub struct Joystick<'a> {
     pub pos: Position,
     pub maze: &'a Maze,
     pub history: Vec<(Position, Option<Direction>)>,
+    /// Slide along the wall when a move is blocked, see `resolve`.
+    pub assist: bool,
 }
 
 impl Joystick<'_> {
-    fn create(maze: &Maze) -> Joystick {
+    fn create(maze: &Maze) -> Joystick<'_> {
         let pos = maze.cell_to_pos(maze.enter);
         Joystick {
             maze,
             pos,
             history: vec![(pos, None)],
+            assist: false,
         }
     }
 
-    pub fn left(&mut self) -> &Joystick {
+    pub fn left(&mut self) -> &Joystick<'_> {
         self.mv(&Left);
         self
     }
 
-    pub fn right(&mut self) -> &Joystick {
+    pub fn right(&mut self) -> &Joystick<'_> {
         self.mv(&Right);
         self
     }
 
-    pub fn up(&mut self) -> &Joystick {
+    pub fn up(&mut self) -> &Joystick<'_> {
         self.mv(&Up);
         self
     }
 
-    pub fn down(&mut self) -> &Joystick {
+    pub fn down(&mut self) -> &Joystick<'_> {
         self.mv(&Down);
         self
     }
@@ -125,133 +843,812 @@ impl Joystick<'_> {
         completed
     }
 
-    /// Attempt the given movement
+    /// Make the moves of the path in order, returns false if one of them is blocked.
+    pub fn follow(&mut self, path: &[Direction]) -> bool {
+        self.moves(path.iter()).len() == path.len()
+    }
+
+    /// Direction a move actually goes: the given one when open, otherwise with the assist
+    /// on, the only open side next to it so the player slides around corners.
+    pub fn resolve(&self, d: &Direction) -> Option<Direction> {
+        if self.maze.move_pos(self.pos, d).is_some() {
+            return Some(*d);
+        }
+        if !self.assist {
+            return None;
+        }
+        let sides = match d {
+            Left | Right => [Up, Down],
+            Up | Down => [Left, Right],
+        };
+        let mut open = sides
+            .iter()
+            .filter(|s| self.maze.move_pos(self.pos, s).is_some());
+        match (open.next(), open.next()) {
+            (Some(s), None) => Some(*s),
+            // don't guess between two ways
+            _ => None,
+        }
+    }
+
+    /// Attempt the given movement, the history holds the direction actually taken
     pub fn mv(&mut self, d: &Direction) -> bool {
-        if let Some(p) = self.maze.move_pos(self.pos, d) {
+        if let Some(d) = self.resolve(d) {
+            let p = self.maze.move_pos(self.pos, &d).unwrap();
             self.pos = p;
-            self.history.push((p, Some(*d)));
+            self.history.push((p, Some(d)));
             return true;
         }
         false
     }
 
+    /// Take back the last move, returns false when there is none since the start or last
+    /// reset
+    pub fn undo(&mut self) -> bool {
+        match self.history.last() {
+            Some((_, Some(_))) => {
+                self.history.pop();
+                self.pos = match self.history.last() {
+                    Some(h) => h.0,
+                    None => self.maze.cell_to_pos(self.maze.enter),
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Reset the position to starting position
-    pub fn reset(&mut self) -> &Joystick {
+    pub fn reset(&mut self) -> &Joystick<'_> {
         self.pos = self.maze.cell_to_pos(self.maze.enter);
         self.history.clear();
         self
     }
 
+    /// Cells in sight through the fog, by cell index: the ones within `radius` steps ignoring
+    /// walls and the ones visited on the way
+    pub fn sight(&self, radius: u16) -> Vec<bool> {
+        let m = self.maze;
+        let mut visible: Vec<bool> = (0..m.size)
+            .map(|i| m.cell_to_pos(i).manhattan(&self.pos) <= radius as u32)
+            .collect();
+        for (p, _) in &self.history {
+            visible[m.pos_to_cell(*p) as usize] = true;
+        }
+        visible
+    }
+
+    /// Way back to the most recently visited cell with a passage not taken yet, from the
+    /// current position to that cell. Just the current position when it has one, None
+    /// when every passage off the visited cells was taken.
+    pub fn breadcrumbs(&self) -> Option<Vec<Position>> {
+        let m = self.maze;
+        let cell = |p: Position| m.pos_to_cell(p) as usize;
+        let mut visited = vec![false; m.size as usize];
+        visited[cell(self.pos)] = true;
+        for (p, _) in &self.history {
+            visited[cell(*p)] = true;
+        }
+        let ways = |p: Position| DIRECTIONS.iter().filter_map(move |d| m.move_pos(p, d));
+        let target = std::iter::once(self.pos)
+            .chain(self.history.iter().rev().map(|h| h.0))
+            .find(|&p| ways(p).any(|n| !visited[cell(n)]))?;
+        // walk down the distances to the target
+        let dist = m.distance_map(target);
+        let mut path = vec![self.pos];
+        let mut p = self.pos;
+        while p != target {
+            let here = dist[cell(p)]?;
+            p = ways(p).find(|&n| matches!(dist[cell(n)], Some(d) if d < here))?;
+            path.push(p);
+        }
+        Some(path)
+    }
+
+    /// Number of moves made since the start or last reset
+    pub fn move_count(&self) -> usize {
+        self.history.iter().filter(|h| h.1.is_some()).count()
+    }
+
     /// Check if current position is exit position
     pub fn is_exit(&self) -> bool {
-        self.maze.pos_to_cell(self.pos) == self.maze.exit
+        self.maze.is_exit(self.pos)
     }
 }
 
+/// Way of carving the maze, each gives it a different texture.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub enum Difficulty {
-    Normal,
-    Hard,
+pub enum Algorithm {
+    /// knock down random walls between unconnected cells, many short dead ends
+    RandomKruskal,
+    /// random walk backing up at dead ends, long winding corridors with few dead ends
+    RecursiveBacktracker,
+    /// grow from the entrance through random frontier walls, short branches around it
+    Prim,
+    /// loop-erased random walks, every possible maze is equally likely
+    Wilson,
 }
 
-impl FromStr for Difficulty {
+impl FromStr for Algorithm {
     type Err = MazeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "Hard" | "hard" | "h" => Ok(Difficulty::Hard),
-            "Normal" | "normal" | "norm" | "n" => Ok(Difficulty::Normal),
-            _ => Err(MazeError::DifficultyParseError),
+            "RandomKruskal" | "kruskal" | "k" => Ok(Algorithm::RandomKruskal),
+            "RecursiveBacktracker" | "backtracker" | "dfs" | "b" => {
+                Ok(Algorithm::RecursiveBacktracker)
+            }
+            "Prim" | "prim" | "p" => Ok(Algorithm::Prim),
+            "Wilson" | "wilson" | "w" => Ok(Algorithm::Wilson),
+            _ => Err(MazeError::AlgorithmParseError),
         }
     }
 }
 
-pub struct Opts {
-    pub difficulty: Difficulty,
+/// Where the entrance and the exit go.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Placement {
+    /// first and last cells, top left and bottom right
+    Corners,
+    /// ends of the longest shortest path of the carved maze
+    Farthest,
+    /// random cells of two opposite borders
+    Borders,
 }
 
-impl Default for Opts {
-    fn default() -> Self {
-        Opts {
-            difficulty: Difficulty::Hard,
+impl FromStr for Placement {
+    type Err = MazeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Corners" | "corners" | "c" => Ok(Placement::Corners),
+            "Farthest" | "farthest" | "f" => Ok(Placement::Farthest),
+            "Borders" | "borders" | "b" => Ok(Placement::Borders),
+            _ => Err(MazeError::PlacementParseError),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Maze {
-    walls: Vec<(u16, u16)>,
-    enter: u16,
-    exit: u16,
-    size: u16,
-    pub width: u16,
-    pub height: u16,
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    /// hard with the entrance and exit at the ends of the longest path
+    Expert,
+    /// expert in the fog
+    Insane,
 }
 
-/// Maze is created by constructing a Disjoint Set for all the cells in the maze grid.
-/// Walls are randomly knocked down until a a path is made from the entrance (0,0)
-/// to the exit (w,h). When the entrance and exit are part of the same set then we
-/// know we have a path to the exit.
-impl Maze {
-    /// Create a new Maze of the given size
-    pub fn generate(width: u16, height: u16, opts: &Opts) -> Maze {
-        let size = width * height;
-        let mut m = Maze {
-            walls: vec![(0, 0); 0],
-            enter: 0,
-            exit: size - 1,
-            size,
-            width,
-            height,
-        };
-
+impl Difficulty {
+    /// size of the cells when drawing mazes of this difficulty
+    pub fn cell_size(&self) -> CellSize {
+        match self {
+            Difficulty::Easy => CellSize::Large,
+            _ => CellSize::Small,
+        }
+    }
+
+    /// whether the entrance and exit go to the two cells farthest apart whatever the
+    /// placement
+    pub fn farthest(&self) -> bool {
+        matches!(self, Difficulty::Expert | Difficulty::Insane)
+    }
+
+    /// sight radius when none is given, 0 for no fog
+    pub fn fog(&self) -> u16 {
+        match self {
+            Difficulty::Insane => 3,
+            _ => 0,
+        }
+    }
+}
+
+/// Size of the maze cells when drawn.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CellSize {
+    Small,
+    Large,
+    /// large print for low vision players
+    Huge,
+}
+
+impl CellSize {
+    /// width of a cell in characters, including one wall
+    pub fn width(&self) -> u16 {
+        match self {
+            CellSize::Small => 4,
+            CellSize::Large => 6,
+            CellSize::Huge => 8,
+        }
+    }
+
+    /// height of a cell in lines, including one wall
+    pub fn height(&self) -> u16 {
+        match self {
+            CellSize::Huge => 4,
+            _ => 2,
+        }
+    }
+}
+
+impl FromStr for CellSize {
+    type Err = MazeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Small" | "small" | "s" => Ok(CellSize::Small),
+            "Large" | "large" | "l" => Ok(CellSize::Large),
+            "Huge" | "huge" => Ok(CellSize::Huge),
+            _ => Err(MazeError::CellDrawSizeParseError),
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = MazeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Hard" | "hard" | "h" => Ok(Difficulty::Hard),
+            "Normal" | "normal" | "norm" | "n" => Ok(Difficulty::Normal),
+            "Easy" | "easy" | "e" => Ok(Difficulty::Easy),
+            "Expert" | "expert" | "x" => Ok(Difficulty::Expert),
+            "Insane" | "insane" | "i" => Ok(Difficulty::Insane),
+            _ => Err(MazeError::DifficultyParseError),
+        }
+    }
+}
+
+/// Wall between two neighbour cells, by cell index.
+pub type Wall = (u16, u16);
+
+/// Weight of a wall in the random picks of the generation, given the maze carved so far.
+/// Walls weighing more are knocked down sooner. Shapes the texture of the maze, like a
+/// radial or spiral bias, with any algorithm. The cells of a wall come in either order.
+///
+/// Kruskal's algorithm weighs every wall once, on the maze before carving, and leaves the
+/// walls weighing 0 for last. The other algorithms weigh the walls around the cells they
+/// grow from at every step, a wall weighing 0 is then picked a thousand times less often
+/// than the heaviest one next to it.
+#[derive(Clone)]
+pub struct WallWeight(Arc<WeightFn>);
+
+type WeightFn = dyn Fn(&Wall, &Maze) -> f32 + Send + Sync;
+
+impl WallWeight {
+    pub fn new<F>(f: F) -> WallWeight
+    where
+        F: Fn(&Wall, &Maze) -> f32 + Send + Sync + 'static,
+    {
+        WallWeight(Arc::new(f))
+    }
+
+    /// weight of the wall
+    pub fn of(&self, wall: &Wall, maze: &Maze) -> f32 {
+        (self.0)(wall, maze)
+    }
+}
+
+impl fmt::Debug for WallWeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WallWeight")
+    }
+}
+
+/// Weights are equal when they are the same function.
+impl PartialEq for WallWeight {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opts {
+    pub difficulty: Difficulty,
+    /// Chance [0, 1) of sparing a wall along the maze border when it is picked for removal.
+    /// Higher values keep the solution from hugging the edges.
+    pub border_penalty: f32,
+    /// Minimum solution length as a factor of width + height, 0 disables the constraint.
+    /// Mazes are regenerated until they meet it, keeping the longest one if none do.
+    pub min_solution: f32,
+    /// Generate the same maze every time for the same seed and options. The seed is
+    /// expanded with `SeedableRng::seed_from_u64` into a Pcg64 generator, both are
+    /// specified and portable so the maze is the same on every platform.
+    pub seed: Option<u64>,
+    pub algorithm: Algorithm,
+    /// Join the opposite edges of the maze, see `Maze::is_wrapped`.
+    pub wrap: bool,
+    /// Cells left out of the maze, it takes the size of the mask.
+    pub mask: Option<Mask>,
+    /// Share [0, 1] of the dead ends opened into loops once the maze is carved, 0 keeps
+    /// perfect mazes with a single way out.
+    pub braid: f32,
+    pub placement: Placement,
+    /// Number of exits, the ones after the first go on random border cells. Reaching any
+    /// of them leads out.
+    pub exits: usize,
+    /// Bias of the random wall picks, uniform when None.
+    pub wall_weight: Option<WallWeight>,
+}
+
+impl Default for Opts {
+    fn default() -> Self {
+        Opts {
+            difficulty: Difficulty::Hard,
+            border_penalty: 0.0,
+            min_solution: 0.0,
+            seed: None,
+            algorithm: Algorithm::RandomKruskal,
+            wrap: false,
+            mask: None,
+            braid: 0.0,
+            placement: Placement::Corners,
+            exits: 1,
+            wall_weight: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Maze {
+    walls: Vec<(u16, u16)>,
+    enter: u16,
+    exit: u16,
+    size: u16,
+    pub width: u16,
+    pub height: u16,
+    /// passages may cross the edges, see `is_wrapped`
+    #[serde(default)]
+    wrap: bool,
+    /// cells left out of the maze, sorted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    blocked: Vec<u16>,
+    /// exits besides `exit`, see `exits`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    more_exits: Vec<u16>,
+}
+
+/// Measures of how hard a maze is, see `Maze::analyze`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct MazeStats {
+    /// moves of the shortest way out, None when there is none
+    pub solution_length: Option<usize>,
+    pub dead_ends: usize,
+    /// Average number of ways on from the cells that have any, walking out from the
+    /// entrance. 1 is a single corridor, higher is bushier.
+    pub branching_factor: f32,
+    /// junctions on the shortest way out, see `Maze::decisions`
+    pub decision_points: usize,
+}
+
+impl fmt::Display for MazeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.solution_length {
+            Some(n) => writeln!(f, "solution length: {} moves", n)?,
+            None => writeln!(f, "solution length: no way out")?,
+        }
+        writeln!(f, "dead ends: {}", self.dead_ends)?;
+        writeln!(f, "branching factor: {:.2}", self.branching_factor)?;
+        write!(f, "decision points: {}", self.decision_points)
+    }
+}
+
+/// Version of the maze generation, bumped whenever a seed generates another maze than it
+/// did before, like the Expert exits moving as far apart as they go.
+pub const GENERATOR_VERSION: u32 = 2;
+
+/// Everything a seeded maze is generated from, a few bytes standing for the whole maze.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MazeSeed {
+    /// generation the seed was saved with, 1 for the seeds from before it was kept
+    #[serde(default = "MazeSeed::first_generator")]
+    pub generator: u32,
+    pub seed: u64,
+    pub algorithm: Algorithm,
+    pub width: u16,
+    pub height: u16,
+    pub difficulty: Difficulty,
+    pub border_penalty: f32,
+    pub min_solution: f32,
+    pub wrap: bool,
+    pub braid: f32,
+    pub placement: Placement,
+    #[serde(default = "MazeSeed::one")]
+    pub exits: usize,
+}
+
+impl MazeSeed {
+    /// exits of seeds saved before there could be more than one
+    fn one() -> usize {
+        1
+    }
+
+    fn first_generator() -> u32 {
+        1
+    }
+
+    /// the seed generates the maze it was saved with
+    pub fn is_current(&self) -> bool {
+        self.generator == GENERATOR_VERSION
+    }
+
+    /// What a maze of the given size and options is generated from, None when it isn't
+    /// seeded or is shaped by a mask or wall weights.
+    pub fn of(width: u16, height: u16, opts: &Opts) -> Option<MazeSeed> {
+        if opts.mask.is_some() || opts.wall_weight.is_some() {
+            return None;
+        }
+        Some(MazeSeed {
+            generator: GENERATOR_VERSION,
+            seed: opts.seed?,
+            algorithm: opts.algorithm,
+            width,
+            height,
+            difficulty: opts.difficulty,
+            border_penalty: opts.border_penalty,
+            min_solution: opts.min_solution,
+            wrap: opts.wrap,
+            braid: opts.braid,
+            placement: opts.placement,
+            exits: opts.exits,
+        })
+    }
+
+    /// generation options of the maze
+    pub fn opts(&self) -> Opts {
+        Opts {
+            difficulty: self.difficulty,
+            border_penalty: self.border_penalty,
+            min_solution: self.min_solution,
+            seed: Some(self.seed),
+            algorithm: self.algorithm,
+            wrap: self.wrap,
+            mask: None,
+            braid: self.braid,
+            placement: self.placement,
+            exits: self.exits,
+            wall_weight: None,
+        }
+    }
+
+    /// generate the maze again
+    pub fn generate(&self) -> Result<Maze, MazeError> {
+        Maze::generate(self.width, self.height, &self.opts())
+    }
+}
+
+/// Maze with its walls packed two bits a cell, for the wall to the right neighbour and
+/// the one below. The bits are written as hex digits, two cells a digit and the lowest
+/// bits first.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PackedMaze {
+    pub width: u16,
+    pub height: u16,
+    enter: u16,
+    exit: u16,
+    #[serde(default)]
+    wrap: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    blocked: Vec<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    more_exits: Vec<u16>,
+    walls: String,
+}
+
+impl PackedMaze {
+    /// the maze the walls were packed from, walls in the order a generation leaves them
+    pub fn unpack(&self) -> Result<Maze, MazeError> {
+        let error = || MazeError::PackedWallsParseError(self.width, self.height);
+        let size = self.width as usize * self.height as usize;
+        if self.walls.len() != size / 2 + size % 2
+            || self.enter as usize >= size
+            || self.exit as usize >= size
+            || self.more_exits.iter().any(|&c| c as usize >= size)
+        {
+            return Err(error());
+        }
+        let mut m = Maze {
+            walls: Vec::new(),
+            enter: self.enter,
+            exit: self.exit,
+            size: size as u16,
+            width: self.width,
+            height: self.height,
+            wrap: self.wrap,
+            blocked: self.blocked.clone(),
+            more_exits: self.more_exits.clone(),
+        };
+        let mut walls = Vec::new();
+        for (i, digit) in self.walls.chars().enumerate() {
+            let bits = digit.to_digit(16).ok_or_else(error)?;
+            for half in 0..2 {
+                let c = (2 * i + half) as u16;
+                if c >= m.size {
+                    break;
+                }
+                let p = m.cell_to_pos(c);
+                for (bit, d) in [Right, Down].iter().enumerate() {
+                    if bits >> (2 * half + bit) & 1 == 1 {
+                        let n = m.neighbour(p, d).ok_or_else(error)?;
+                        walls.push((c, m.pos_to_cell(n)));
+                    }
+                }
+            }
+        }
+        m.walls = walls;
+        Ok(m)
+    }
+}
+
+/// Maze is created by constructing a Disjoint Set for all the cells in the maze grid.
+/// Walls are randomly knocked down until a a path is made from the entrance (0,0)
+/// to the exit (w,h). When the entrance and exit are part of the same set then we
+/// know we have a path to the exit.
+impl Maze {
+    /// Check that a maze of the size has cells that can all be numbered, see `MAX_CELLS`.
+    pub fn check_size(width: u16, height: u16) -> Result<(), MazeError> {
+        if width as usize * height as usize > MAX_CELLS {
+            return Err(MazeError::TooLarge(width, height));
+        }
+        Ok(())
+    }
+
+    /// Create a new Maze of the given size, or of the size of the mask when there is one,
+    /// masks are never too large.
+    pub fn generate(width: u16, height: u16, opts: &Opts) -> Result<Maze, MazeError> {
+        if opts.mask.is_none() {
+            Maze::check_size(width, height)?;
+        }
+        let target = (opts.min_solution.max(0.0) * (width + height) as f32).ceil() as usize;
+        let mut best = Maze::generate_once(width, height, opts);
+        let mut best_len = best.solution_length().unwrap_or(0);
+        for attempt in 1..MAX_ATTEMPTS {
+            if best_len >= target {
+                break;
+            }
+            // seeded retries move on to the next seed so they don't repeat the same maze
+            let opts = Opts {
+                seed: opts.seed.map(|s| s.wrapping_add(attempt as u64)),
+                ..opts.clone()
+            };
+            let m = Maze::generate_once(width, height, &opts);
+            let len = m.solution_length().unwrap_or(0);
+            if len > best_len {
+                best = m;
+                best_len = len;
+            }
+        }
+        Ok(best)
+    }
+
+    /// Create a new Maze drawing its randomness from the given generator, the same
+    /// generator state always yields the same maze. Any seed in the options is ignored.
+    pub fn generate_with_rng<R: Rng>(
+        width: u16,
+        height: u16,
+        opts: &Opts,
+        rng: &mut R,
+    ) -> Result<Maze, MazeError> {
+        let opts = Opts {
+            seed: Some(rng.gen()),
+            ..opts.clone()
+        };
+        Maze::generate(width, height, &opts)
+    }
+
+    /// Create a new Maze of the given size along with its spanning tree.
+    pub fn generate_with_tree(
+        width: u16,
+        height: u16,
+        opts: &Opts,
+    ) -> Result<(Maze, SpanningTree), MazeError> {
+        let m = Maze::generate(width, height, opts)?;
+        let tree = m.spanning_tree();
+        Ok((m, tree))
+    }
+
+    fn generate_once(width: u16, height: u16, opts: &Opts) -> Maze {
+        Maze::generate_with_observer(width, height, opts, |_, _| ())
+    }
+
+    /// Create a new Maze, telling the observer about every event of the generation along
+    /// with the counts so far, e.g. to show progress on very large mazes. Like
+    /// `generate_iter` the minimum solution length isn't enforced.
+    pub fn generate_with_observer<F>(width: u16, height: u16, opts: &Opts, mut observer: F) -> Maze
+    where
+        F: FnMut(&GenerationEvent, &GenerationStats),
+    {
+        let mut generation = Maze::generate_iter(width, height, opts);
+        while let Some(e) = generation.next() {
+            observer(&e, &generation.stats());
+            if let GenerationEvent::Done(m) = e {
+                return m;
+            }
+        }
+        unreachable!("generation ends with the maze")
+    }
+
+    /// Generate a maze step by step. Every removed wall is reported so the generation
+    /// can be animated or observed, the last event holds the finished maze. Unlike
+    /// `generate` the minimum solution length isn't enforced.
+    pub fn generate_iter(width: u16, height: u16, opts: &Opts) -> Generation {
+        let mut m = Maze::closed(width, height, opts);
+        let mut rng = match opts.seed {
+            Some(seed) => Pcg64::seed_from_u64(seed),
+            None => Pcg64::from_entropy(),
+        };
+        if opts.placement == Placement::Borders {
+            m.place_on_borders(&mut rng);
+        }
+        Generation {
+            cells: DisjSet::new(m.size as usize),
+            rng,
+            algorithm: opts.algorithm,
+            stack: Vec::new(),
+            frontier: Vec::new(),
+            starts: Vec::new(),
+            difficulty: opts.difficulty,
+            // corners can only be reached through border walls, so some must always be removable
+            penalty: opts.border_penalty.clamp(0.0, 0.99),
+            braid: opts.braid.clamp(0.0, 1.0),
+            placement: opts.placement,
+            exits: opts.exits,
+            weight: opts.wall_weight.clone(),
+            order: Vec::new(),
+            connected: m.enter == m.exit,
+            stage: Stage::Carving,
+            pending: VecDeque::new(),
+            considered: 0,
+            removed: 0,
+            maze: Some(m),
+        }
+    }
+
+    /// Maze of the given size with every wall standing, the start of a generation. The
+    /// entrance and exit are the first and last cells not blocked by the mask.
+    pub(crate) fn closed(width: u16, height: u16, opts: &Opts) -> Maze {
+        let (width, height) = match &opts.mask {
+            Some(mask) => (mask.width, mask.height),
+            None => (width, height),
+        };
+        let size = width * height;
+        let blocked = opts
+            .mask
+            .as_ref()
+            .map(Mask::blocked_cells)
+            .unwrap_or_default();
+        let mut open = (0..size).filter(|c| blocked.binary_search(c).is_err());
+        let mut m = Maze {
+            walls: vec![(0, 0); 0],
+            enter: open.next().unwrap_or(0),
+            exit: open.next_back().unwrap_or(size - 1),
+            size,
+            width,
+            height,
+            wrap: opts.wrap,
+            blocked: Vec::new(),
+            more_exits: Vec::new(),
+        };
+
         for c in 0..size {
             // create right cell wall if not end of a row
             if (c % width) != width - 1 {
                 m.walls.push((c, c + 1));
+            } else if m.wraps_x() {
+                // or the wall across the edge to the start of the row
+                m.walls.push((c, c + 1 - width));
             }
 
-            // create bottom cell wall if not last row
-            let b = c + width;
-            if b < size {
-                m.walls.push((c, b));
+            // create bottom cell wall if not last row, c + width may not fit a u16 there
+            if c < size - width {
+                m.walls.push((c, c + width));
+            } else if m.wraps_y() {
+                m.walls.push((c, c % width));
             }
         }
+        // blocked cells are walled in by the renderer, they have no walls of their own
+        m.walls.retain(|w| {
+            blocked.binary_search(&w.0).is_err() && blocked.binary_search(&w.1).is_err()
+        });
+        m.blocked = blocked;
+        m
+    }
 
-        // randomly destroy some walls
-        let mut cells = DisjSet::new(size as usize);
-        let mut rng = rand::thread_rng();
-        match opts.difficulty {
-            Difficulty::Hard => {
-                // remove walls until every cell in the maze if part of the same set
-                loop {
-                    let i = rng.gen_range(0..m.walls.len());
-                    let w = m.walls[i];
-                    // only remove walls of different sets, otherwise the maze will be trivialized
-                    if let DisJoint(r1, r2) = cells.find_roots(w.0 as usize, w.1 as usize) {
-                        cells.union(r1, r2);
-                        m.walls.remove(i);
-                    }
-                    if cells.distinct_sets() == 1 {
-                        break;
-                    }
-                }
-            }
-            Difficulty::Normal => {
-                // remove walls until enter and exit are of the same set
-                while let DisJoint(_, _) = cells.find_roots(m.enter as usize, m.exit as usize) {
-                    let i = rng.gen_range(0..m.walls.len());
-                    let w = m.walls[i];
-                    // only remove walls of different sets, otherwise the maze will be trivialized
-                    if let DisJoint(r1, r2) = cells.find_roots(w.0 as usize, w.1 as usize) {
-                        cells.union(r1, r2);
-                        m.walls.remove(i);
-                    }
-                }
-            }
+    /// Move the entrance and the exit to random open cells of the left and right borders,
+    /// or of the top and bottom ones. Stays in the corners when a mask leaves a border
+    /// without open cells.
+    fn place_on_borders<R: Rng>(&mut self, rng: &mut R) {
+        let (w, h) = (self.width, self.height);
+        let sides: [(Vec<u16>, Vec<u16>); 2] = [
+            (
+                (0..h).map(|y| y * w).collect(),
+                (0..h).map(|y| y * w + w - 1).collect(),
+            ),
+            ((0..w).collect(), (0..w).map(|x| (h - 1) * w + x).collect()),
+        ];
+        let (mut from, mut to) = sides[rng.gen_range(0..2u32) as usize].clone();
+        from.retain(|&c| !self.is_blocked(self.cell_to_pos(c)));
+        to.retain(|&c| !self.is_blocked(self.cell_to_pos(c)));
+        if from.is_empty() || to.is_empty() {
+            return;
+        }
+        let pick =
+            |cells: &[u16], rng: &mut R| cells[rng.gen_range(0..cells.len() as u32) as usize];
+        self.enter = pick(&from, rng);
+        self.exit = pick(&to, rng);
+        // start on either side
+        if rng.gen::<bool>() {
+            std::mem::swap(&mut self.enter, &mut self.exit);
         }
+    }
+
+    /// Move the entrance and the exit to the two cells farthest apart. The farthest cell
+    /// from anywhere is one end of the longest shortest path, the farthest from it is the
+    /// other end. Only cells reachable from the entrance are considered.
+    fn place_farthest(&mut self) {
+        let farthest = |dist: Vec<Option<usize>>| {
+            (0..self.size)
+                .filter_map(|c| dist[c as usize].map(|d| (d, c)))
+                // the first of equally distant cells
+                .max_by_key(|&(d, c)| (d, std::cmp::Reverse(c)))
+                .map_or(self.enter, |(_, c)| c)
+        };
+        let a = farthest(self.distance_map(self.cell_to_pos(self.enter)));
+        let b = farthest(self.distance_map(self.cell_to_pos(a)));
+        self.enter = a;
+        self.exit = b;
+    }
+
+    /// the maze with the given walls instead of its own
+    pub(crate) fn with_walls(&self, walls: Vec<(u16, u16)>) -> Maze {
+        Maze {
+            walls,
+            ..self.clone()
+        }
+    }
 
-        return m;
+    /// walls between the cells, each one between a cell and its right or bottom neighbour
+    /// or across an edge of a wrapped maze
+    pub(crate) fn walls(&self) -> &[(u16, u16)] {
+        &self.walls
+    }
+
+    /// The maze with its walls packed into bits, None when a wall doesn't stand between
+    /// neighbour cells and can't be packed.
+    pub fn pack(&self) -> Option<PackedMaze> {
+        let mut bits = vec![0u8; self.size as usize];
+        for &(a, b) in self.walls.iter() {
+            // a wall is the right or bottom one of one of its cells
+            let bit = [(a, b), (b, a)].iter().find_map(|&(c, n)| {
+                let p = self.cell_to_pos(c);
+                [Right, Down]
+                    .iter()
+                    .position(|d| {
+                        self.neighbour(p, d).map(|n| self.pos_to_cell(n)) == Some(n)
+                            && !self.is_blocked(p)
+                    })
+                    .map(|i| (c, i))
+            })?;
+            bits[bit.0 as usize] |= 1 << bit.1;
+        }
+        let walls = bits
+            .chunks(2)
+            .map(|pair| {
+                let digit = pair[0] | pair.get(1).map_or(0, |b| b << 2);
+                std::char::from_digit(digit as u32, 16).unwrap()
+            })
+            .collect();
+        Some(PackedMaze {
+            width: self.width,
+            height: self.height,
+            enter: self.enter,
+            exit: self.exit,
+            wrap: self.wrap,
+            blocked: self.blocked.clone(),
+            more_exits: self.more_exits.clone(),
+            walls,
+        })
     }
 
     /// Create new maze of the given size and walls.
@@ -264,88 +1661,611 @@ impl Maze {
             width: w,
             height: h,
             size,
+            wrap: false,
+            blocked: Vec::new(),
+            more_exits: Vec::new(),
         };
         for w in walls {
             if w.0 > m.exit || w.1 > m.exit {
                 return Err(MazeError::WallOutOfBounds(w));
             }
         }
-        return Ok(m);
+        Ok(m)
     }
 
-    /// Compute the available movements for the given position in the grid.
-    fn movements(&self, p: Position) -> HashSet<Direction> {
-        let mut moves: HashSet<Direction> = HashSet::new();
-        for d in DIRECTIONS.iter() {
-            match self.move_pos(p, d) {
-                Some(_) => {
-                    moves.insert(d.clone());
+    /// Number of moves needed to reach every cell from the given position, indexed by cell.
+    /// Unreachable cells are None.
+    pub fn distance_map(&self, from: Position) -> Vec<Option<usize>> {
+        self.distances(&[from])
+    }
+
+    /// Number of moves needed to reach the nearest exit from every cell, indexed by cell.
+    pub fn exit_distance_map(&self) -> Vec<Option<usize>> {
+        self.distances(&self.exits())
+    }
+
+    /// Number of moves needed to reach every cell from the nearest of the positions.
+    fn distances(&self, from: &[Position]) -> Vec<Option<usize>> {
+        let mut dist = vec![None; self.size as usize];
+        let mut queue = VecDeque::new();
+        for &p in from {
+            dist[self.pos_to_cell(p) as usize] = Some(0);
+            queue.push_back((p, 0));
+        }
+        while let Some((p, d)) = queue.pop_front() {
+            for np in DIRECTIONS.iter().filter_map(|m| self.move_pos(p, m)) {
+                let c = self.pos_to_cell(np) as usize;
+                if dist[c].is_none() {
+                    dist[c] = Some(d + 1);
+                    queue.push_back((np, d + 1));
                 }
-                None => (),
-            };
+            }
         }
-        return moves;
+        dist
     }
 
-    /// Attempt to move from the given position in the direction. If a wall prevents the move
-    /// None is returned otherwise the new position grid position is returned.
-    fn move_pos(&self, p: Position, d: &Direction) -> Option<Position> {
-        if self.pos_to_cell(p) > self.exit {
-            return None;
-        }
+    /// First move of a shortest path from the position to the nearest exit, None at an
+    /// exit or when none can be reached.
+    pub fn next_move(&self, from: Position) -> Option<Direction> {
+        let dist = self.exit_distance_map();
+        let here = dist[self.pos_to_cell(from) as usize]?;
+        DIRECTIONS.iter().copied().find(|d| {
+            matches!(
+                self.move_pos(from, d).and_then(|p| dist[self.pos_to_cell(p) as usize]),
+                Some(n) if n < here
+            )
+        })
+    }
 
-        let dest = match d {
-            Left => {
-                if p.x == 0 {
-                    None
-                } else {
-                    Some(Position { x: p.x - 1, y: p.y })
+    /// Breadth first tree of the passages from the entrance.
+    pub fn spanning_tree(&self) -> SpanningTree {
+        let mut tree = SpanningTree {
+            width: self.width,
+            parent: vec![None; self.size as usize],
+            depth: vec![None; self.size as usize],
+        };
+        let mut queue = VecDeque::new();
+        tree.depth[self.enter as usize] = Some(0);
+        queue.push_back(self.cell_to_pos(self.enter));
+        while let Some(p) = queue.pop_front() {
+            let c = self.pos_to_cell(p);
+            let d = tree.depth[c as usize].unwrap_or(0);
+            for np in DIRECTIONS.iter().filter_map(|m| self.move_pos(p, m)) {
+                let nc = self.pos_to_cell(np) as usize;
+                if tree.depth[nc].is_none() {
+                    tree.depth[nc] = Some(d + 1);
+                    tree.parent[nc] = Some(c);
+                    queue.push_back(np);
                 }
             }
-            Right => {
-                if p.x == self.width - 1 {
-                    None
-                } else {
-                    Some(Position { x: p.x + 1, y: p.y })
-                }
+        }
+        tree
+    }
+
+    /// position of the entrance
+    pub fn entrance(&self) -> Position {
+        self.cell_to_pos(self.enter)
+    }
+
+    /// position of the exit, the first one when there are more
+    pub fn exit(&self) -> Position {
+        self.cell_to_pos(self.exit)
+    }
+
+    /// positions of every exit, the first one first
+    pub fn exits(&self) -> Vec<Position> {
+        std::iter::once(self.exit)
+            .chain(self.more_exits.iter().copied())
+            .map(|c| self.cell_to_pos(c))
+            .collect()
+    }
+
+    /// check if the position is one of the exits
+    pub fn is_exit(&self, p: Position) -> bool {
+        let c = self.pos_to_cell(p);
+        c == self.exit || self.more_exits.contains(&c)
+    }
+
+    /// Shortest path from the entrance to the nearest exit, both included.
+    pub fn solution(&self) -> Option<Vec<Position>> {
+        let tree = self.spanning_tree();
+        self.exits()
+            .into_iter()
+            .filter_map(|e| tree.path_to(e))
+            .min_by_key(Vec::len)
+    }
+
+    /// Moves of the shortest path from the entrance to the exit, empty when there is none.
+    pub fn solve(&self) -> Vec<Direction> {
+        self.solution()
+            .unwrap_or_default()
+            .windows(2)
+            .filter_map(|w| self.direction(w[0], w[1]))
+            .collect()
+    }
+
+    /// direction of a neighbour position, across the edges of wrapped mazes too
+    fn direction(&self, from: Position, to: Position) -> Option<Direction> {
+        DIRECTIONS
+            .iter()
+            .copied()
+            .find(|d| self.neighbour(from, d) == Some(to))
+    }
+
+    /// Whether moving off an edge of the maze comes back in from the opposite edge, like
+    /// on a torus. Only edges at least three cells apart wrap, closer ones are already
+    /// neighbours.
+    pub fn is_wrapped(&self) -> bool {
+        self.wrap
+    }
+
+    /// Cells with a single way in or out, blocked ones aside.
+    pub fn dead_ends(&self) -> Vec<Position> {
+        (0..self.size)
+            .map(|c| self.cell_to_pos(c))
+            .filter(|p| self.movements(*p).len() == 1)
+            .collect()
+    }
+
+    /// Whether the cell was left out of the maze by a mask, see `Opts::mask`.
+    pub fn is_blocked(&self, p: Position) -> bool {
+        self.blocked.binary_search(&self.pos_to_cell(p)).is_ok()
+    }
+
+    fn wraps_x(&self) -> bool {
+        self.wrap && self.width > 2
+    }
+
+    fn wraps_y(&self) -> bool {
+        self.wrap && self.height > 2
+    }
+
+    /// Number of moves of the shortest path from the entrance to the nearest exit.
+    pub fn solution_length(&self) -> Option<usize> {
+        let dist = self.distance_map(self.cell_to_pos(self.enter));
+        self.exits()
+            .iter()
+            .filter_map(|e| dist[self.pos_to_cell(*e) as usize])
+            .min()
+    }
+
+    /// Difficulty score: junctions along the shortest path where the player can take a
+    /// wrong turn.
+    pub fn decisions(&self) -> usize {
+        let solution = self.solution().unwrap_or_default();
+        solution
+            .iter()
+            .enumerate()
+            .take(solution.len().saturating_sub(1))
+            .filter(|(i, p)| {
+                let open = DIRECTIONS
+                    .iter()
+                    .filter(|d| self.move_pos(**p, d).is_some())
+                    .count();
+                // one side leads on, the entrance has no way back
+                open > if *i == 0 { 1 } else { 2 }
+            })
+            .count()
+    }
+
+    /// Measure the maze, e.g. to pick mazes of a wanted challenge.
+    pub fn analyze(&self) -> MazeStats {
+        let tree = self.spanning_tree();
+        let mut children = vec![0usize; self.size as usize];
+        for parent in tree.parent.iter().flatten() {
+            children[*parent as usize] += 1;
+        }
+        let branches: Vec<usize> = children.into_iter().filter(|&c| c > 0).collect();
+        MazeStats {
+            solution_length: self.solution_length(),
+            dead_ends: self.dead_ends().len(),
+            branching_factor: if branches.is_empty() {
+                0.0
+            } else {
+                branches.iter().sum::<usize>() as f32 / branches.len() as f32
+            },
+            decision_points: self.decisions(),
+        }
+    }
+
+    /// Check if the wall runs along the outer edge of the maze, between two border cells.
+    fn is_border_wall(&self, w: (u16, u16)) -> bool {
+        let (a, b) = (self.cell_to_pos(w.0), self.cell_to_pos(w.1));
+        if a.y == b.y {
+            // wall between horizontal neighbours runs along the top or bottom row
+            a.y == 0 || a.y == self.height - 1
+        } else {
+            // wall between vertical neighbours runs along the left or right column
+            a.x == b.x && (a.x == 0 || a.x == self.width - 1)
+        }
+    }
+
+    /// Stable hash of the maze layout, identical mazes share the same fingerprint
+    /// regardless of how they were created.
+    pub fn fingerprint(&self) -> u64 {
+        let mut walls: Vec<(u16, u16)> = self
+            .walls
+            .iter()
+            .map(|&(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        walls.sort_unstable();
+
+        // FNV-1a, std hashers aren't guaranteed to be stable between releases
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |v: u16| {
+            for b in v.to_le_bytes().iter() {
+                hash ^= *b as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
             }
-            Up => {
-                if p.y == 0 {
-                    None
-                } else {
-                    Some(Position { x: p.x, y: p.y - 1 })
+        };
+        feed(self.width);
+        feed(self.height);
+        feed(self.enter);
+        feed(self.exit);
+        if self.wrap {
+            feed(1);
+        }
+        for &c in self.blocked.iter() {
+            feed(c);
+        }
+        for &c in self.more_exits.iter() {
+            feed(c);
+        }
+        for (a, b) in walls {
+            feed(a);
+            feed(b);
+        }
+        hash
+    }
+
+    /// First 8 hex digits of the fingerprint, short enough for players to compare aloud.
+    pub fn short_fingerprint(&self) -> String {
+        format!("{:08x}", self.fingerprint() >> 32)
+    }
+
+    /// The maze turned clockwise, walls, entrance and exit included.
+    pub fn rotate(&self, r: Rotation) -> Maze {
+        let (width, height) = if r.quarter_turns() % 2 == 1 {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let cell = |c: u16| {
+            let p = r.position(self.cell_to_pos(c), self.width, self.height);
+            p.y * width + p.x
+        };
+        Maze {
+            walls: self
+                .walls
+                .iter()
+                .map(|&(a, b)| (cell(a), cell(b)))
+                .collect(),
+            enter: cell(self.enter),
+            exit: cell(self.exit),
+            size: self.size,
+            width,
+            height,
+            wrap: self.wrap,
+            blocked: {
+                let mut blocked: Vec<u16> = self.blocked.iter().map(|&c| cell(c)).collect();
+                blocked.sort_unstable();
+                blocked
+            },
+            more_exits: self.more_exits.iter().map(|&c| cell(c)).collect(),
+        }
+    }
+
+    /// Copy of the maze with `other` merged into the region with its top left corner at
+    /// `at`. The walls around the region are closed, then opened again one per part of the
+    /// maze they join, the passages there were first. An entrance or exit inside the region
+    /// is replaced by the one of `other`. Fails when `other` doesn't fit or no exit can be
+    /// reached from the entrance after the merge.
+    pub fn merge(&self, other: &Maze, at: Position) -> Result<Maze, MazeError> {
+        if at.x as u32 + other.width as u32 > self.width as u32
+            || at.y as u32 + other.height as u32 > self.height as u32
+        {
+            return Err(MazeError::MergeOutOfBounds(other.width, other.height));
+        }
+        let inside = |c: u16| {
+            let p = self.cell_to_pos(c);
+            p.x >= at.x && p.x < at.x + other.width && p.y >= at.y && p.y < at.y + other.height
+        };
+        let cell = |c: u16| {
+            let p = other.cell_to_pos(c);
+            (p.y + at.y) * self.width + p.x + at.x
+        };
+        let mut walls: Vec<Wall> = self
+            .walls
+            .iter()
+            .copied()
+            .filter(|w| !inside(w.0) && !inside(w.1))
+            .collect();
+        // walls across the edges of a wrapped maze have no place in the region
+        let adjacent =
+            |&&(a, b): &&Wall| other.cell_to_pos(a).manhattan(&other.cell_to_pos(b)) == 1;
+        walls.extend(
+            other
+                .walls
+                .iter()
+                .filter(adjacent)
+                .map(|&(a, b)| (cell(a), cell(b))),
+        );
+        let mut blocked: Vec<u16> = self
+            .blocked
+            .iter()
+            .copied()
+            .filter(|c| !inside(*c))
+            .collect();
+        blocked.extend(other.blocked.iter().map(|&c| cell(c)));
+        blocked.sort_unstable();
+        let mut more_exits: Vec<u16> = self
+            .more_exits
+            .iter()
+            .copied()
+            .filter(|c| !inside(*c))
+            .collect();
+        more_exits.extend(other.more_exits.iter().map(|&c| cell(c)));
+
+        // walls from the region to the rest of the maze, the open ones first
+        let mut boundary: Vec<(bool, Wall)> = Vec::new();
+        for c in (0..self.size).filter(|c| inside(*c)) {
+            let p = self.cell_to_pos(c);
+            for d in DIRECTIONS.iter() {
+                if let Some(n) = self
+                    .neighbour(p, d)
+                    .filter(|n| !inside(self.pos_to_cell(*n)))
+                {
+                    boundary.push((self.move_pos(p, d).is_none(), (c, self.pos_to_cell(n))));
                 }
             }
-            Down => {
-                if p.y == self.height - 1 {
-                    None
-                } else {
-                    Some(Position { x: p.x, y: p.y + 1 })
+        }
+        boundary.sort_by_key(|b| b.0);
+        walls.extend(boundary.iter().map(|b| b.1));
+
+        let mut m = Maze {
+            walls,
+            enter: if inside(self.enter) {
+                cell(other.enter)
+            } else {
+                self.enter
+            },
+            exit: if inside(self.exit) {
+                cell(other.exit)
+            } else {
+                self.exit
+            },
+            blocked,
+            more_exits,
+            ..self.clone()
+        };
+        let mut parts = DisjSet::new(m.size as usize);
+        for c in 0..m.size {
+            let p = m.cell_to_pos(c);
+            for d in [Right, Down].iter() {
+                if let Some(n) = m.move_pos(p, d) {
+                    if let DisJoint(r1, r2) =
+                        parts.find_roots(c as usize, m.pos_to_cell(n) as usize)
+                    {
+                        parts.union(r1, r2);
+                    }
                 }
             }
+        }
+        for (_, (a, b)) in boundary {
+            if m.blocked.binary_search(&a).is_ok() || m.blocked.binary_search(&b).is_ok() {
+                continue;
+            }
+            if let DisJoint(r1, r2) = parts.find_roots(a as usize, b as usize) {
+                parts.union(r1, r2);
+                m.walls.retain(|w| *w != (a, b));
+            }
+        }
+        m.solution().ok_or(MazeError::MergeUnsolvable)?;
+        Ok(m)
+    }
+
+    /// Carve the cells of the region again with the options, keeping the rest of the maze
+    /// as it is. The new region is merged in like a part, see `merge`, and the entrance
+    /// and exits stay where they were. Normal difficulty carves the whole region like
+    /// Hard so none of it is cut off.
+    pub fn regenerate_region(&self, rect: Rect, opts: &Opts) -> Result<Maze, MazeError> {
+        if rect.width == 0 || rect.height == 0 {
+            return Err(MazeError::RegionEmpty(rect.width, rect.height));
+        }
+        // checked before generating, `merge` would only tell once the region is carved
+        if rect.left as u32 + rect.width as u32 > self.width as u32
+            || rect.top as u32 + rect.height as u32 > self.height as u32
+        {
+            return Err(MazeError::MergeOutOfBounds(rect.width, rect.height));
+        }
+        let inside = |c: &u16| rect.contains(self.cell_to_pos(*c));
+        if self.blocked.iter().any(inside) {
+            return Err(MazeError::RegionBlocked);
+        }
+        let opts = Opts {
+            difficulty: match opts.difficulty {
+                Difficulty::Normal => Difficulty::Hard,
+                d => d,
+            },
+            min_solution: 0.0,
+            wrap: false,
+            mask: None,
+            placement: Placement::Corners,
+            exits: 1,
+            ..opts.clone()
+        };
+        let part = Maze::generate(rect.width, rect.height, &opts)?;
+        let at = Position {
+            x: rect.left,
+            y: rect.top,
         };
+        Ok(Maze {
+            enter: self.enter,
+            exit: self.exit,
+            more_exits: self.more_exits.clone(),
+            ..self.merge(&part, at)?
+        })
+    }
 
-        return match dest {
-            Some(dp) => {
-                let i1 = self.pos_to_cell(p);
-                let i2 = self.pos_to_cell(dp);
-                if self.walls.contains(&(i1, i2)) || self.walls.contains(&(i2, i1)) {
-                    None
-                } else {
-                    Some(dp.clone())
+    /// memorable name of the maze, derived from its fingerprint
+    pub fn name(&self) -> String {
+        crate::names::name(self.fingerprint())
+    }
+
+    /// Mazes of the same family: each one swaps up to `swaps` walls of this maze, knocking
+    /// one down and closing a passage of the loop it opens, so every cell stays reachable.
+    /// Swaps that move the solution length more than a tenth away from this maze's are
+    /// undone to keep the difficulty about the same. The variations are all different
+    /// from each other and from this maze, fewer than `count` come back when the maze is
+    /// too small to vary that much.
+    pub fn variations(&self, count: usize, swaps: usize, seed: u64) -> Vec<Maze> {
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let length = self.solution_length().unwrap_or(0);
+        let slack = length / 10;
+        let mut seen: HashSet<u64> = HashSet::new();
+        seen.insert(self.fingerprint());
+        let mut family = Vec::new();
+        for _ in 0..count * MAX_ATTEMPTS {
+            if family.len() == count {
+                break;
+            }
+            let mut m = self.clone();
+            for _ in 0..swaps {
+                let swapped = match m.swap_wall(&mut rng) {
+                    Some(swapped) => swapped,
+                    None => break,
+                };
+                let len = swapped.solution_length().unwrap_or(0);
+                if len + slack >= length && len <= length + slack {
+                    m = swapped;
                 }
             }
-            None => None,
-        };
+            if seen.insert(m.fingerprint()) {
+                family.push(m);
+            }
+        }
+        family
+    }
+
+    /// Knock down a random wall and close a random passage on the way between its two
+    /// cells, None when there are no walls inside the maze.
+    fn swap_wall<R: Rng>(&self, rng: &mut R) -> Option<Maze> {
+        let &(a, b) = self.walls.choose(rng)?;
+        let mut m = self.clone();
+        m.walls.retain(|&w| w != (a, b));
+        // the walk between the cells back in the maze before the wall came down
+        let dist = self.distance_map(self.cell_to_pos(b));
+        let mut path = vec![self.cell_to_pos(a)];
+        while let Some(&p) = path.last() {
+            let here = dist[self.pos_to_cell(p) as usize]?;
+            if here == 0 {
+                break;
+            }
+            let next = DIRECTIONS
+                .iter()
+                .filter_map(|d| self.move_pos(p, d))
+                .find(|n| dist[self.pos_to_cell(*n) as usize] == Some(here - 1))?;
+            path.push(next);
+        }
+        let i = rng.gen_range(0..path.len() - 1);
+        let (c1, c2) = (self.pos_to_cell(path[i]), self.pos_to_cell(path[i + 1]));
+        m.walls.push((c1.min(c2), c1.max(c2)));
+        Some(m)
+    }
+
+    /// Compute the available movements for the given position in the grid.
+    pub(crate) fn movements(&self, p: Position) -> HashSet<Direction> {
+        let mut moves: HashSet<Direction> = HashSet::new();
+        for d in DIRECTIONS.iter() {
+            if self.move_pos(p, d).is_some() {
+                moves.insert(*d);
+            }
+        }
+        moves
+    }
+
+    /// Attempt to move from the given position in the direction. If a wall prevents the move
+    /// None is returned otherwise the new position grid position is returned.
+    pub(crate) fn move_pos(&self, p: Position, d: &Direction) -> Option<Position> {
+        if self.pos_to_cell(p) >= self.size || self.is_blocked(p) {
+            return None;
+        }
+        let dp = self.neighbour(p, d)?;
+        let i1 = self.pos_to_cell(p);
+        let i2 = self.pos_to_cell(dp);
+        if self.walls.contains(&(i1, i2)) || self.walls.contains(&(i2, i1)) {
+            None
+        } else {
+            Some(dp)
+        }
+    }
+
+    /// grid neighbour of the position in the direction, walls aside, None when blocked
+    fn neighbour(&self, p: Position, d: &Direction) -> Option<Position> {
+        let n = match d {
+            Left if p.x > 0 => Some(Position { x: p.x - 1, y: p.y }),
+            Right if p.x + 1 < self.width => Some(Position { x: p.x + 1, y: p.y }),
+            Up if p.y > 0 => Some(Position { x: p.x, y: p.y - 1 }),
+            Down if p.y + 1 < self.height => Some(Position { x: p.x, y: p.y + 1 }),
+            // across the edge of a wrapped maze
+            Left | Right if self.wraps_x() => Some(Position {
+                x: self.width - 1 - p.x,
+                y: p.y,
+            }),
+            Up | Down if self.wraps_y() => Some(Position {
+                x: p.x,
+                y: self.height - 1 - p.y,
+            }),
+            _ => None,
+        }?;
+        if self.is_blocked(n) {
+            None
+        } else {
+            Some(n)
+        }
+    }
+
+    /// Whether the move leaves the grid and comes back in on the opposite edge.
+    pub(crate) fn crosses_edge(&self, p: Position, d: &Direction) -> bool {
+        match self.neighbour(p, d) {
+            Some(n) => p.direction_to(&n) != Some(*d),
+            None => false,
+        }
+    }
+
+    /// Replay the moves from the entrance and return where they end, or where the first
+    /// invalid one breaks.
+    pub fn verify_path(&self, moves: &[Direction]) -> Result<Position, PathError> {
+        let mut pos = self.entrance();
+        for (index, d) in moves.iter().enumerate() {
+            pos = match self.move_pos(pos, d) {
+                Some(p) => p,
+                None if self.neighbour(pos, d).is_some() => {
+                    return Err(PathError::Wall {
+                        index,
+                        pos,
+                        dir: *d,
+                    })
+                }
+                None => {
+                    return Err(PathError::OutOfBounds {
+                        index,
+                        pos,
+                        dir: *d,
+                    })
+                }
+            };
+        }
+        Ok(pos)
     }
 
     /// translate position to cell index
-    fn pos_to_cell(&self, p: Position) -> u16 {
+    pub(crate) fn pos_to_cell(&self, p: Position) -> u16 {
         p.y * self.width + p.x
     }
 
     /// translate cell index to a grid position
-    fn cell_to_pos(&self, p: u16) -> Position {
+    pub(crate) fn cell_to_pos(&self, p: u16) -> Position {
         Position {
             x: p % self.width,
             y: p / self.width,
@@ -353,19 +2273,92 @@ impl Maze {
     }
 
     /// create joystick for moving and tracking.
-    pub fn joystick(&self) -> Joystick {
+    pub fn joystick(&self) -> Joystick<'_> {
         Joystick::create(self)
     }
 
-    pub fn ui(&self) -> MazeUI {
+    pub fn ui(&self) -> MazeUI<'_> {
+        self.ui_sized(CellSize::Small)
+    }
+
+    /// ui drawing cells of the given size
+    pub fn ui_sized(&self, size: CellSize) -> MazeUI<'_> {
         MazeUI {
-            cell_width: 4,
-            cell_height: 2,
+            cell_width: size.width(),
+            cell_height: size.height(),
             maze: self,
         }
     }
 }
 
+pub(crate) const EXIT_LABEL: &str = "Exit";
+
+/// Highlighting of board characters in colored renders.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Paint {
+    Exit,
+    Start,
+    Path,
+}
+
+/// Glyph drawn over the maze at a maze position, e.g. a player, ghost or rival.
+#[derive(Copy, Clone, Debug)]
+pub struct Marker {
+    pub pos: Position,
+    pub glyph: char,
+    pub color: Option<color::AnsiValue>,
+}
+
+/// Markers drawn over the maze. Positions markers moved away from are remembered so
+/// renderers only need to redraw the cells that changed.
+#[derive(Default, Debug)]
+pub struct Markers {
+    markers: Vec<Option<Marker>>,
+    vacated: Vec<Position>,
+}
+
+impl Markers {
+    /// add a marker, returns the id used to move or remove it
+    pub fn add(&mut self, m: Marker) -> usize {
+        self.markers.push(Some(m));
+        self.markers.len() - 1
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Marker> {
+        self.markers.get(id).and_then(|m| m.as_ref())
+    }
+
+    /// move the marker to the given maze position
+    pub fn mv(&mut self, id: usize, pos: Position) {
+        if let Some(Some(m)) = self.markers.get_mut(id) {
+            if m.pos != pos {
+                self.vacated.push(m.pos);
+                m.pos = pos;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Marker> {
+        let m = self.markers.get_mut(id).and_then(|m| m.take());
+        if let Some(m) = m {
+            self.vacated.push(m.pos);
+        }
+        m
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Marker> {
+        self.markers.iter().flatten()
+    }
+
+    /// positions left behind since the last call, excluding positions still occupied
+    pub fn take_vacated(&mut self) -> Vec<Position> {
+        let mut vacated: Vec<Position> = self.vacated.drain(..).collect();
+        vacated.retain(|p| self.iter().all(|m| m.pos != *p));
+        vacated.dedup();
+        vacated
+    }
+}
+
 pub struct MazeUI<'a> {
     pub cell_width: u16,
     pub cell_height: u16,
@@ -389,11 +2382,17 @@ impl Locate<Position> for MazeUI<'_> {
 }
 
 impl Locate<Joystick<'_>> for MazeUI<'_> {
-    fn locate(&self, j: &Joystick) -> Position {
+    fn locate(&self, j: &Joystick<'_>) -> Position {
         self.locate(&j.pos)
     }
 }
 
+impl fmt::Display for MazeUI<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
 impl MazeUI<'_> {
     /// compute the bounding box for a cell in the maze
     fn cell_box(&self, p: &Position) -> CellBox {
@@ -418,12 +2417,162 @@ impl MazeUI<'_> {
         self.locate(&self.maze.cell_to_pos(self.maze.exit))
     }
 
+    /// board positions covered by the joystick trail, cell centers and the gaps between them
+    pub fn trail(&self, j: &Joystick) -> Vec<Position> {
+        let path: Vec<Position> = j.history.iter().map(|h| h.0).collect();
+        self.trail_of(&path)
+    }
+
+    /// board positions covered by a path of adjacent maze positions
+    pub fn trail_of(&self, path: &[Position]) -> Vec<Position> {
+        let mut cells = Vec::new();
+        let mut last: Option<&Position> = None;
+        for p in path {
+            match last.map(|l| self.path_segment(l, p)) {
+                Some(segment) if !segment.is_empty() => cells.extend(segment),
+                // the start of the path or a jump, like after a reset
+                _ => cells.push(self.locate(p)),
+            }
+            last = Some(p);
+        }
+        cells
+    }
+
+    /// Board positions of a step between adjacent maze positions, from the one after the
+    /// center of `from` up to the center of `to`, whatever the cell size. Empty when the
+    /// positions aren't adjacent.
+    pub fn path_segment(&self, from: &Position, to: &Position) -> Vec<Position> {
+        let d = match from.direction_to(to) {
+            Some(d) => d,
+            None => return Vec::new(),
+        };
+        let step = match d {
+            Left | Right => self.cell_width,
+            Up | Down => self.cell_height,
+        };
+        let start = self.locate(from);
+        (1..=step).map(|i| start.mv(&d, i)).collect()
+    }
+
+    /// Positions of the exit labels on the board with their text, `Exit` for the first
+    /// exit and the others numbered from 2, every label as long as `EXIT_LABEL`.
+    pub fn exit_labels(&self) -> Vec<(Position, String)> {
+        self.maze
+            .exits()
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let text = if i == 0 {
+                    EXIT_LABEL.to_string()
+                } else {
+                    format!("Ex{:>2}", i + 1)
+                };
+                (self.locate(e).mv(&Left, 2), text)
+            })
+            .collect()
+    }
+
+    /// write the exit labels over a board
+    fn label(&self, mut board: Vec<Vec<char>>) -> Vec<Vec<char>> {
+        for (label, text) in self.exit_labels() {
+            for (i, c) in text.chars().enumerate() {
+                board[label.y as usize][label.x as usize + i] = c;
+            }
+        }
+        board
+    }
+
+    /// Render the maze as text lines, no terminal required. With color the exit label and
+    /// entrance are highlighted using ANSI escape codes.
+    pub fn render(&self, color: bool) -> String {
+        self.render_path(&[], color)
+    }
+
+    /// Render the maze as text lines with a path of maze positions (e.g. the solution)
+    /// drawn over it.
+    pub fn render_path(&self, path: &[Position], color: bool) -> String {
+        let mut board = self.draw();
+        let mut paint = vec![vec![None; board[0].len()]; board.len()];
+        for p in self.trail_of(path) {
+            paint[p.y as usize][p.x as usize] = Some(Paint::Path);
+            if !color {
+                board[p.y as usize][p.x as usize] = '·';
+            }
+        }
+        // the labels stay readable over the end of the path
+        let board = self.label(board);
+        for (label, _) in self.exit_labels() {
+            for i in 0..EXIT_LABEL.len() {
+                paint[label.y as usize][label.x as usize + i] = Some(Paint::Exit);
+            }
+        }
+        let start = self.locate(&self.maze.cell_to_pos(self.maze.enter));
+        paint[start.y as usize][start.x as usize] = Some(Paint::Start);
+
+        let mut out = String::new();
+        for (r, pr) in board.into_iter().zip(paint) {
+            let mut current = None;
+            for (c, p) in r.into_iter().zip(pr) {
+                if color && p != current {
+                    if current.is_some() {
+                        out.push_str(style::Reset.as_ref());
+                    }
+                    match p {
+                        Some(Paint::Exit) => out.push_str(&color::Fg(color::Green).to_string()),
+                        Some(Paint::Start) => out.push_str(&color::Bg(color::Yellow).to_string()),
+                        Some(Paint::Path) => out.push_str(&color::Bg(color::Blue).to_string()),
+                        None => (),
+                    }
+                    current = p;
+                }
+                out.push(c);
+            }
+            if current.is_some() {
+                out.push_str(style::Reset.as_ref());
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// draw the maze as seen in game: exit label, trail (when shown) and joystick marker
+    pub fn frame(&self, j: &Joystick, show_path: bool) -> Vec<Vec<char>> {
+        self.frame_on(self.draw(), j, show_path)
+    }
+
+    /// Like `frame` over a board drawn otherwise, e.g. through the fog.
+    pub fn frame_on(
+        &self,
+        mut board: Vec<Vec<char>>,
+        j: &Joystick,
+        show_path: bool,
+    ) -> Vec<Vec<char>> {
+        if show_path {
+            for p in self.trail(j) {
+                board[p.y as usize][p.x as usize] = '·';
+            }
+        }
+        let mut board = self.label(board);
+        let p = self.locate(j);
+        board[p.y as usize][p.x as usize] = '@';
+        board
+    }
+
+    /// draw markers over a board matrix
+    pub fn overlay(&self, board: &mut [Vec<char>], markers: &Markers) {
+        for m in markers.iter() {
+            let p = self.locate(&m.pos);
+            board[p.y as usize][p.x as usize] = m.glyph;
+        }
+    }
+
     /// draw maze as a matrix of cell boxes
     pub fn draw(&self) -> Vec<Vec<char>> {
         // init board matrix
         let cp = self.cell_width - 1;
         let bw = ((self.maze.width * cp) + (self.maze.width + 1)) as usize; // board width
-        let bh = ((self.maze.height * 2) + 1) as usize; // board height
+        let ch = self.cell_height as usize;
+        let bh = (self.maze.height as usize * ch) + 1; // board height
         let mut board = vec![vec![' '; bw]; bh];
 
         let row = |r: &mut Vec<char>, st: char, end: char, join: char, pad: char| {
@@ -431,50 +2580,61 @@ impl MazeUI<'_> {
             r[i] = st;
             for c in 0..self.maze.width {
                 for _ in 0..cp {
-                    i = i + 1;
+                    i += 1;
                     r[i] = pad;
                 }
                 if c < self.maze.width - 1 {
-                    i = i + 1;
+                    i += 1;
                     r[i] = join;
                 }
             }
-            i = i + 1;
+            i += 1;
             r[i] = end;
         };
 
         // build grid
         row(&mut board[0], '┌', '┐', '┬', '─');
-        for i in 0..self.maze.height {
-            let r = ((i * 2) + 1) as usize;
-            row(&mut board[r], '│', '│', '│', ' ');
-            row(&mut board[r + 1], '├', '┤', '┼', '─');
+        for i in 0..self.maze.height as usize {
+            for r in board.iter_mut().skip(i * ch + 1).take(ch - 1) {
+                row(r, '│', '│', '│', ' ');
+            }
+            row(&mut board[(i + 1) * ch], '├', '┤', '┼', '─');
         }
         row(&mut board[bh - 1], '└', '┘', '┴', '─');
 
         // remove walls
         for i in 0..self.maze.size {
-            let p = self.maze.cell_to_pos(i as u16);
+            let p = self.maze.cell_to_pos(i);
             let pbox = self.cell_box(&p);
-            let moves = self.maze.movements(p);
+            let mut moves = self.maze.movements(p);
+            // the border stays up where a wrapped maze crosses its edges, see below
+            moves.retain(|d| !self.maze.crosses_edge(p, d));
             if moves.contains(&Left) {
-                for rw in pbox.top..=pbox.bottom {
-                    board[rw][pbox.left] = ' ';
+                for rw in board.iter_mut().take(pbox.bottom + 1).skip(pbox.top) {
+                    rw[pbox.left] = ' ';
                 }
             }
             if moves.contains(&Right) {
-                for rw in pbox.top..=pbox.bottom {
-                    board[rw][pbox.right] = ' ';
+                for rw in board.iter_mut().take(pbox.bottom + 1).skip(pbox.top) {
+                    rw[pbox.right] = ' ';
                 }
             }
             if moves.contains(&Up) {
-                for cl in pbox.left..=pbox.right {
-                    board[pbox.top][cl] = ' ';
+                for cl in board[pbox.top]
+                    .iter_mut()
+                    .take(pbox.right + 1)
+                    .skip(pbox.left)
+                {
+                    *cl = ' ';
                 }
             }
             if moves.contains(&Down) {
-                for cl in pbox.left..=pbox.right {
-                    board[pbox.bottom][cl] = ' ';
+                for cl in board[pbox.bottom]
+                    .iter_mut()
+                    .take(pbox.right + 1)
+                    .skip(pbox.left)
+                {
+                    *cl = ' ';
                 }
             }
         }
@@ -503,10 +2663,10 @@ impl MazeUI<'_> {
                     if j % self.cell_width as usize == 0 && board[i][j] == ' ' {
                         let mut chars = vec![' '; 4];
                         if i > 0 {
-                            chars[0] = board[i.checked_sub(1).unwrap_or(0)][j]
+                            chars[0] = board[i.saturating_sub(1)][j]
                         }
                         if j > 0 {
-                            chars[1] = board[i][j.checked_sub(1).unwrap_or(0)]
+                            chars[1] = board[i][j.saturating_sub(1)]
                         }
                         if i < bh - 1 {
                             chars[2] = board[i + 1][j]
@@ -516,15 +2676,108 @@ impl MazeUI<'_> {
                         }
                         let spec = String::from_iter(chars);
                         match corners.get(spec.as_str()) {
-                            Some(c) => board[i][j] = c.clone(),
+                            Some(c) => board[i][j] = *c,
                             None => board[i][j] = '*',
                         }
                     }
                 }
-            }
-        }
-
-        return board;
+            }
+        }
+
+        // blocked cells are solid, along with the walls between them
+        for &c in self.maze.blocked.iter() {
+            let p = self.maze.cell_to_pos(c);
+            let b = self.cell_box(&p);
+            let right = match self.maze.neighbour(p, &Right) {
+                None if p.x + 1 < self.maze.width => b.right + 1,
+                _ => b.right,
+            };
+            let bottom = match self.maze.neighbour(p, &Down) {
+                None if p.y + 1 < self.maze.height => b.bottom + 1,
+                _ => b.bottom,
+            };
+            for row in board.iter_mut().take(bottom).skip(b.top + 1) {
+                for cl in row.iter_mut().take(right).skip(b.left + 1) {
+                    *cl = '█';
+                }
+            }
+        }
+
+        // arrows on the border where passages of wrapped mazes cross the edges
+        for i in 0..self.maze.size {
+            let p = self.maze.cell_to_pos(i);
+            let pbox = self.cell_box(&p);
+            let c = self.locate(&p);
+            for d in DIRECTIONS.iter() {
+                if !self.maze.crosses_edge(p, d) || self.maze.move_pos(p, d).is_none() {
+                    continue;
+                }
+                let (x, y, arrow) = match d {
+                    Left => (pbox.left, c.y as usize, '←'),
+                    Right => (pbox.right, c.y as usize, '→'),
+                    Up => (c.x as usize, pbox.top, '↑'),
+                    Down => (c.x as usize, pbox.bottom, '↓'),
+                };
+                board[y][x] = arrow;
+            }
+        }
+
+        board
+    }
+
+    /// Board of `draw` with everything outside the visible cells blanked, `visible` is
+    /// indexed by cell like `Joystick::sight`
+    pub fn draw_visible(&self, visible: &[bool]) -> Vec<Vec<char>> {
+        self.hide_unseen(self.draw(), visible)
+    }
+
+    /// Like `draw_visible` over a board drawn otherwise.
+    pub fn hide_unseen(&self, mut board: Vec<Vec<char>>, visible: &[bool]) -> Vec<Vec<char>> {
+        let mut seen = vec![vec![false; board[0].len()]; board.len()];
+        for (i, _) in visible.iter().enumerate().filter(|(_, v)| **v) {
+            let b = self.cell_box(&self.maze.cell_to_pos(i as u16));
+            for row in seen.iter_mut().take(b.bottom + 1).skip(b.top) {
+                for c in row.iter_mut().take(b.right + 1).skip(b.left) {
+                    *c = true;
+                }
+            }
+        }
+        for (row, seen) in board.iter_mut().zip(seen) {
+            for (c, s) in row.iter_mut().zip(seen) {
+                if !s {
+                    *c = ' ';
+                }
+            }
+        }
+        board
+    }
+
+    /// Lines of the board with the cell centers on a background color, given as escape
+    /// sequences like `color::Blue.bg_str()` and indexed by cell.
+    pub fn paint_cells(&self, board: Vec<Vec<char>>, colors: &[Option<&str>]) -> Vec<String> {
+        let mut paint = vec![vec![None; board[0].len()]; board.len()];
+        for (i, bg) in colors.iter().enumerate() {
+            let c = self.locate(&self.maze.cell_to_pos(i as u16));
+            paint[c.y as usize][c.x as usize] = *bg;
+        }
+        board
+            .into_iter()
+            .zip(paint)
+            .map(|(row, paint)| {
+                let mut line = String::new();
+                for (c, p) in row.into_iter().zip(paint) {
+                    match p {
+                        Some(bg) => {
+                            line.push_str(bg);
+                            line.push(c);
+                            line.push_str(style::Reset.as_ref());
+                        }
+                        None => line.push(c),
+                    }
+                }
+                line
+            })
+            .collect()
     }
 }
 
@@ -535,13 +2788,72 @@ mod tests {
 
     #[test]
     fn test_ui_draw() {
-        let m = Maze::generate(15, 15, &Default::default());
+        let m = Maze::generate(15, 15, &Default::default()).unwrap();
         let matrix = m.ui().draw();
         for r in matrix {
             println!("{}", String::from_iter(r));
         }
     }
 
+    #[test]
+    fn test_render() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        assert_eq!(
+            m.ui().to_string(),
+            "┌───────────┐\n\
+             │           │\n\
+             ├───────╴   │\n\
+             │       Exit│\n\
+             └───────────┘\n"
+        );
+        let colored = m.ui().render(true);
+        assert!(colored.contains(&format!("{}Exit", color::Fg(color::Green))));
+        assert!(colored.contains(&format!("{} {}", color::Bg(color::Yellow), style::Reset)));
+
+        let path = [
+            Position { x: 0, y: 0 },
+            Position { x: 1, y: 0 },
+            Position { x: 2, y: 0 },
+            Position { x: 2, y: 1 },
+        ];
+        assert_eq!(
+            m.ui().render_path(&path, false),
+            "┌───────────┐\n\
+             │ ········· │\n\
+             ├───────╴ · │\n\
+             │       Exit│\n\
+             └───────────┘\n"
+        );
+    }
+
+    #[test]
+    fn test_markers() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut markers = Markers::default();
+        let ghost = markers.add(Marker {
+            pos: Position { x: 1, y: 0 },
+            glyph: 'G',
+            color: None,
+        });
+        let rival = markers.add(Marker {
+            pos: Position { x: 0, y: 1 },
+            glyph: 'R',
+            color: Some(color::AnsiValue(1)),
+        });
+        markers.mv(ghost, Position { x: 2, y: 0 });
+        markers.mv(rival, Position { x: 1, y: 0 });
+        assert_eq!(markers.take_vacated(), vec![Position { x: 0, y: 1 }]);
+        assert!(markers.take_vacated().is_empty());
+
+        let mut board = m.ui().draw();
+        m.ui().overlay(&mut board, &markers);
+        assert_eq!(String::from_iter(&board[1]), "│     R   G │");
+
+        assert_eq!(markers.remove(ghost).map(|g| g.glyph), Some('G'));
+        assert!(markers.get(ghost).is_none());
+        assert_eq!(markers.take_vacated(), vec![Position { x: 2, y: 0 }]);
+    }
+
     #[test]
     fn test_save() {
         let walls = vec![
@@ -586,11 +2898,1020 @@ mod tests {
         let completed = j.moves([Right, Down, Right, Down, Right, Down, Down, Right].iter());
         assert_eq!(completed.len(), 8);
         assert_eq!(j.pos, Position { x: 4, y: 4 });
-        assert_eq!(j.is_exit(), true);
+        assert!(j.is_exit());
 
         // stuck down bad path
         j = m.joystick();
         j.moves([Right, Down, Right, Right, Right, Down, Down, Down].iter());
         assert_eq!(j.pos, Position { x: 4, y: 2 });
     }
+
+    #[test]
+    fn test_border_penalty() {
+        let m = Maze::create(4, 3, vec![]).unwrap();
+        assert!(m.is_border_wall((0, 1)));
+        assert!(m.is_border_wall((0, 4)));
+        assert!(m.is_border_wall((9, 10)));
+        assert!(m.is_border_wall((7, 11)));
+        assert!(!m.is_border_wall((4, 5)));
+        assert!(!m.is_border_wall((1, 5)));
+
+        // a full penalty must still be able to open the corners
+        let opts = Opts {
+            difficulty: Difficulty::Hard,
+            border_penalty: 1.0,
+            ..Default::default()
+        };
+        let m = Maze::generate(6, 6, &opts).unwrap();
+        assert_eq!(m.walls.len(), 2 * 6 * 5 - (6 * 6 - 1));
+    }
+
+    #[test]
+    fn test_too_large() {
+        // the cells of a maze are numbered by a u16
+        assert!(Maze::check_size(255, 257).is_ok());
+        assert!(matches!(
+            Maze::check_size(256, 256),
+            Err(MazeError::TooLarge(256, 256))
+        ));
+        assert!(matches!(
+            Maze::generate(300, 300, &Opts::default()),
+            Err(MazeError::TooLarge(300, 300))
+        ));
+    }
+
+    #[test]
+    fn test_min_solution() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        assert_eq!(m.solution_length(), Some(3));
+        let walled = Maze::create(2, 2, vec![(1, 3), (2, 3)]).unwrap();
+        assert_eq!(walled.solution_length(), None);
+
+        let opts = Opts {
+            difficulty: Difficulty::Normal,
+            min_solution: 1.5,
+            ..Default::default()
+        };
+        let m = Maze::generate(8, 8, &opts).unwrap();
+        assert!(m.solution_length().unwrap() >= 24);
+    }
+
+    #[test]
+    fn test_easy() {
+        let opts = Opts {
+            difficulty: Difficulty::Easy,
+            ..Default::default()
+        };
+        let m = Maze::generate(8, 6, &opts).unwrap();
+        // a perfect maze keeps 2wh - w - h - (wh - 1) walls, easy ones have openings on top
+        assert!(m.walls.len() < 2 * 8 * 6 - 8 - 6 - (8 * 6 - 1));
+        assert!(m.solution_length().is_some());
+
+        let large = Maze::create(2, 2, vec![(0, 2)]).unwrap();
+        assert_eq!(
+            large.ui_sized(CellSize::Large).to_string(),
+            "┌───────────┐\n\
+             │           │\n\
+             ├─────╴     │\n\
+             │      Exit │\n\
+             └───────────┘\n"
+        );
+    }
+
+    #[test]
+    fn test_expert() {
+        assert_eq!("x".parse::<Difficulty>().unwrap(), Difficulty::Expert);
+        assert_eq!("insane".parse::<Difficulty>().unwrap().fog(), 3);
+        let opts = Opts {
+            seed: Some(3),
+            ..Default::default()
+        };
+        let hard = Maze::generate(10, 6, &opts).unwrap();
+        let expert = Maze::generate(
+            10,
+            6,
+            &Opts {
+                difficulty: Difficulty::Expert,
+                ..opts
+            },
+        )
+        .unwrap();
+        // the same perfect maze with its ends as far apart as they go
+        assert_eq!(expert.walls(), hard.walls());
+        let longest = expert.solution_length().unwrap();
+        for c in 0..expert.size {
+            let dist = expert.distance_map(expert.cell_to_pos(c));
+            assert!(dist.iter().all(|d| d.unwrap() <= longest));
+        }
+    }
+
+    #[test]
+    fn test_wall_weight() {
+        let across = |w: &Wall| w.0.max(w.1) - w.0.min(w.1) == 1;
+        let opts = Opts {
+            seed: Some(5),
+            wall_weight: Some(WallWeight::new(
+                move |w, _| if across(w) { 20.0 } else { 1.0 },
+            )),
+            ..Default::default()
+        };
+        assert_eq!(opts.clone(), opts);
+        assert_eq!(MazeSeed::of(20, 10, &opts), None);
+        for algorithm in [
+            Algorithm::RandomKruskal,
+            Algorithm::RecursiveBacktracker,
+            Algorithm::Prim,
+            Algorithm::Wilson,
+        ]
+        .iter()
+        {
+            let m = Maze::generate(
+                20,
+                10,
+                &Opts {
+                    algorithm: *algorithm,
+                    ..opts.clone()
+                },
+            )
+            .unwrap();
+            // 190 walls across could be opened, a perfect maze opens 199 walls in all
+            let opened = 19 * 10 - m.walls.iter().filter(|w| across(w)).count();
+            assert!(opened > 150, "{:?} opened {}", algorithm, opened);
+            assert_eq!(m.walls.len(), 2 * 20 * 10 - 20 - 10 - 199);
+        }
+        // nothing weighs anything, the picks are uniform again
+        let flat = Opts {
+            wall_weight: Some(WallWeight::new(|_, _| 0.0)),
+            ..opts
+        };
+        assert!(Maze::generate(8, 8, &flat).unwrap().solution().is_some());
+
+        // the walls of the corner weigh nothing but one of them has to go to reach it
+        let corner = Opts {
+            seed: Some(1),
+            wall_weight: Some(WallWeight::new(|w, _| {
+                if w.0 == 0 || w.1 == 0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            })),
+            ..Default::default()
+        };
+        for algorithm in [
+            Algorithm::RandomKruskal,
+            Algorithm::RecursiveBacktracker,
+            Algorithm::Prim,
+            Algorithm::Wilson,
+        ]
+        .iter()
+        {
+            let m = Maze::generate(
+                6,
+                4,
+                &Opts {
+                    algorithm: *algorithm,
+                    ..corner.clone()
+                },
+            )
+            .unwrap();
+            assert!(m.distance_map(m.entrance()).iter().all(Option::is_some));
+        }
+        // left for last, the corner is joined by a single wall once the rest is carved
+        let (mut removed, mut corner_removed) = (0, None);
+        for e in Maze::generate_iter(6, 4, &corner) {
+            if let GenerationEvent::WallRemoved(w) = e {
+                removed += 1;
+                if w.0 == 0 || w.1 == 0 {
+                    corner_removed = Some(removed);
+                }
+            }
+        }
+        assert_eq!(corner_removed, Some(6 * 4 - 1));
+    }
+
+    #[test]
+    fn test_merge() {
+        let opts = Opts {
+            seed: Some(2),
+            ..Default::default()
+        };
+        let base = Maze::generate(10, 6, &opts).unwrap();
+        let room = Maze::create(3, 3, vec![]).unwrap();
+        let at = Position { x: 2, y: 2 };
+        let m = base.merge(&room, at).unwrap();
+        assert_eq!(m.entrance(), base.entrance());
+        assert_eq!(m.exit(), base.exit());
+        // the room is open and joined to every part of the rest
+        let center = Position { x: 3, y: 3 };
+        assert!(DIRECTIONS.iter().all(|d| m.move_pos(center, d).is_some()));
+        assert!(m.distance_map(m.entrance()).iter().all(Option::is_some));
+
+        assert!(matches!(
+            base.merge(&room, Position { x: 8, y: 4 }),
+            Err(MazeError::MergeOutOfBounds(3, 3))
+        ));
+        // the entrance of the merged maze takes the place of the one it covers
+        let corridor = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let m = base.merge(&corridor, Position { x: 0, y: 0 }).unwrap();
+        assert_eq!(m.entrance(), Position { x: 0, y: 0 });
+        assert!(m.solution().is_some());
+    }
+
+    #[test]
+    fn test_position_moves() {
+        assert_eq!(Left.opposite(), Right);
+        assert_eq!(Up.turn_left(), Left);
+        assert_eq!(Up.turn_right(), Right);
+        assert_eq!(Down.turn_right().turn_right(), Up);
+        let p = Position { x: 0, y: 3 };
+        assert_eq!(p.checked_mv(&Left, 1), None);
+        assert_eq!(p.checked_mv(&Up, 3), Some(Position { x: 0, y: 0 }));
+        assert_eq!(p.checked_mv(&Up, 4), None);
+        let far = Position { x: u16::MAX, y: 7 };
+        assert_eq!(far.checked_mv(&Right, 1), None);
+        assert_eq!(p.manhattan(&far), u16::MAX as u32 + 4);
+        assert_eq!(p.chebyshev(&Position { x: 2, y: 8 }), 5);
+    }
+
+    #[test]
+    fn test_regenerate_region() {
+        let opts = Opts {
+            seed: Some(5),
+            ..Default::default()
+        };
+        let base = Maze::generate(12, 8, &opts).unwrap();
+        let rect = Rect {
+            left: 3,
+            top: 2,
+            width: 5,
+            height: 4,
+        };
+        let m = base
+            .regenerate_region(
+                rect,
+                &Opts {
+                    seed: Some(6),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_ne!(m.fingerprint(), base.fingerprint());
+        assert_eq!((m.entrance(), m.exit()), (base.entrance(), base.exit()));
+        // passages outside the region are kept, and every cell is still reachable
+        for c in 0..base.size {
+            let p = base.cell_to_pos(c);
+            for d in [Right, Down].iter() {
+                let outside = |n: &Position| !rect.contains(p) && !rect.contains(*n);
+                if base.neighbour(p, d).filter(outside).is_some() {
+                    assert_eq!(base.move_pos(p, d), m.move_pos(p, d));
+                }
+            }
+        }
+        assert!(m.distance_map(m.entrance()).iter().all(Option::is_some));
+
+        let masked = Maze::generate(
+            3,
+            3,
+            &Opts {
+                mask: Some(".#.\n...\n...".parse().unwrap()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let all = Rect {
+            left: 0,
+            top: 0,
+            width: 3,
+            height: 3,
+        };
+        assert!(matches!(
+            masked.regenerate_region(all, &Opts::default()),
+            Err(MazeError::RegionBlocked)
+        ));
+        for (width, height) in [(0, 2), (2, 0)].iter() {
+            let empty = Rect {
+                left: 1,
+                top: 1,
+                width: *width,
+                height: *height,
+            };
+            assert!(matches!(
+                base.regenerate_region(empty, &Opts::default()),
+                Err(MazeError::RegionEmpty(..))
+            ));
+        }
+        let past = Rect {
+            left: u16::MAX,
+            top: 0,
+            width: 2,
+            height: 2,
+        };
+        assert!(!past.contains(Position { x: 1, y: 1 }));
+        assert!(matches!(
+            base.regenerate_region(past, &Opts::default()),
+            Err(MazeError::MergeOutOfBounds(2, 2))
+        ));
+    }
+
+    #[test]
+    fn test_generate_iter() {
+        let opts = Opts {
+            difficulty: Difficulty::Normal,
+            ..Default::default()
+        };
+        let events: Vec<GenerationEvent> = Maze::generate_iter(6, 5, &opts).collect();
+        let removed = events
+            .iter()
+            .filter(|e| matches!(e, GenerationEvent::WallRemoved(_)))
+            .count();
+        let connected = events
+            .iter()
+            .position(|e| matches!(e, GenerationEvent::Connected))
+            .unwrap();
+        // normal generation stops as soon as the exit is reachable
+        assert_eq!(connected, events.len() - 2);
+        match events.last() {
+            Some(GenerationEvent::Done(m)) => {
+                assert_eq!(m.walls.len(), 2 * 6 * 5 - 6 - 5 - removed);
+                assert!(m.solution_length().is_some());
+            }
+            e => panic!("unexpected last event {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_generate_with_observer() {
+        let opts = Opts {
+            seed: Some(11),
+            ..Default::default()
+        };
+        let mut last = GenerationStats {
+            sets: 48,
+            ..Default::default()
+        };
+        let mut kept = 0;
+        let m = Maze::generate_with_observer(8, 6, &opts, |e, stats| {
+            if let GenerationEvent::WallKept(_) = e {
+                kept += 1;
+            }
+            assert!(stats.considered >= last.considered && stats.sets <= last.sets);
+            last = *stats;
+        });
+        // a perfect maze takes one union less than there are cells
+        assert_eq!(last.removed, 47);
+        assert_eq!(last.sets, 1);
+        assert_eq!(last.considered, last.removed + kept);
+        assert_eq!(
+            m.fingerprint(),
+            Maze::generate(8, 6, &opts).unwrap().fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_spanning_tree() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let tree = m.spanning_tree();
+        let exit = Position { x: 2, y: 1 };
+        assert_eq!(tree.depth(exit), Some(3));
+        assert_eq!(tree.parent(exit), Some(Position { x: 2, y: 0 }));
+        assert_eq!(tree.parent(Position { x: 0, y: 0 }), None);
+        assert_eq!(
+            tree.path_to(exit),
+            Some(vec![
+                Position { x: 0, y: 0 },
+                Position { x: 1, y: 0 },
+                Position { x: 2, y: 0 },
+                Position { x: 2, y: 1 },
+            ])
+        );
+        assert_eq!(tree.subtree_sizes(), vec![6, 5, 4, 1, 2, 3]);
+
+        let (m, tree) = Maze::generate_with_tree(7, 5, &Default::default()).unwrap();
+        let exit = m.cell_to_pos(m.exit);
+        assert_eq!(tree.depth(exit), m.solution_length());
+        assert_eq!(m.solution().map(|s| s.len() - 1), m.solution_length());
+        assert_eq!(tree.subtree_sizes()[0], 35);
+    }
+
+    #[test]
+    fn test_seed() {
+        let opts = Opts {
+            seed: Some(7),
+            min_solution: 1.0,
+            ..Default::default()
+        };
+        let m = Maze::generate(12, 8, &opts).unwrap();
+        assert_eq!(
+            m.fingerprint(),
+            Maze::generate(12, 8, &opts).unwrap().fingerprint()
+        );
+        let other = Opts {
+            seed: Some(8),
+            ..opts.clone()
+        };
+        assert_ne!(
+            m.fingerprint(),
+            Maze::generate(12, 8, &other).unwrap().fingerprint()
+        );
+
+        let with_rng =
+            |seed| Maze::generate_with_rng(12, 8, &opts, &mut Pcg64::seed_from_u64(seed)).unwrap();
+        assert_eq!(with_rng(1).fingerprint(), with_rng(1).fingerprint());
+        assert_ne!(with_rng(1).fingerprint(), with_rng(2).fingerprint());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let r = Rotation::new(1);
+        let rotated = m.rotate(r);
+        assert_eq!((rotated.width, rotated.height), (2, 3));
+        assert_eq!(
+            rotated.ui().to_string(),
+            "┌───┬───┐\n\
+             │   │   │\n\
+             │   │   │\n\
+             │   │   │\n\
+             │   ╵   │\n\
+             Exit    │\n\
+             └───────┘\n"
+        );
+        assert_eq!(rotated.solution_length(), m.solution_length());
+        assert_eq!(rotated.exit(), Position { x: 0, y: 2 });
+        assert_eq!(m.rotate(Rotation::new(4)).fingerprint(), m.fingerprint());
+
+        let mut j = m.joystick();
+        j.right();
+        let view = r.joystick(&rotated, &j);
+        assert_eq!(view.pos, Position { x: 1, y: 1 });
+        assert_eq!(view.history[1].1, Some(Down));
+        // screen relative controls go through the inverse rotation
+        assert_eq!(r.inverse().direction(&Down), Right);
+    }
+
+    #[test]
+    fn test_decisions() {
+        let corridor = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        assert_eq!(corridor.decisions(), 0);
+        // a loop through the middle column offers a choice at the entrance
+        let open = Maze::create(3, 2, vec![(1, 2)]).unwrap();
+        assert_eq!(open.decisions(), 2);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let corridor = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let stats = corridor.analyze();
+        assert_eq!(
+            stats,
+            MazeStats {
+                solution_length: Some(3),
+                dead_ends: 2,
+                branching_factor: 1.0,
+                decision_points: 0,
+            }
+        );
+        assert!(stats.to_string().starts_with("solution length: 3 moves\n"));
+        // the entrance opens both ways, one of them a dead end
+        let forked = Maze::create(3, 2, vec![(1, 2), (1, 4)]).unwrap();
+        let stats = forked.analyze();
+        assert_eq!(stats.dead_ends, 2);
+        assert_eq!(stats.branching_factor, 5.0 / 4.0);
+        // no way out and nowhere to go
+        let walled = Maze::create(2, 1, vec![(0, 1)]).unwrap();
+        assert_eq!(
+            walled.analyze(),
+            MazeStats {
+                solution_length: None,
+                dead_ends: 0,
+                branching_factor: 0.0,
+                decision_points: 0,
+            }
+        );
+        // a junction in the middle on the way out, with walled off corners
+        let walls = vec![(0, 1), (1, 2), (2, 5), (5, 8), (6, 7), (3, 6)];
+        let junction = Maze::create(3, 3, walls).unwrap();
+        let stats = junction.analyze();
+        assert_eq!(stats.solution_length, Some(4));
+        assert_eq!(stats.decision_points, 1);
+        assert_eq!(stats.branching_factor, 1.5);
+        // a loop has no dead ends, the branching is that of the way it was walked
+        let open = Maze::create(2, 2, vec![]).unwrap();
+        let stats = open.analyze();
+        assert_eq!(stats.solution_length, Some(2));
+        assert_eq!(stats.dead_ends, 0);
+        assert_eq!(stats.branching_factor, 1.5);
+    }
+
+    #[test]
+    fn test_breadcrumbs() {
+        // the middle is a junction with dead ends up and right and the way out down
+        let walls = vec![(0, 1), (1, 2), (2, 5), (5, 8), (6, 7), (3, 6)];
+        let m = Maze::create(3, 3, walls).unwrap();
+        let mut j = m.joystick();
+        assert_eq!(j.breadcrumbs(), Some(vec![Position { x: 0, y: 0 }]));
+        for d in [Down, Right, Up].iter() {
+            j.mv(d);
+        }
+        assert_eq!(
+            j.breadcrumbs(),
+            Some(vec![Position { x: 1, y: 0 }, Position { x: 1, y: 1 }])
+        );
+        j.down();
+        j.right();
+        assert_eq!(j.breadcrumbs().unwrap().len(), 2);
+        for d in [Left, Down, Right].iter() {
+            j.mv(d);
+        }
+        assert_eq!(j.breadcrumbs(), None);
+    }
+
+    #[test]
+    fn test_assist() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut j = m.joystick();
+        j.right();
+        j.right();
+        assert!(!j.mv(&Right));
+        j.assist = true;
+        assert_eq!(j.resolve(&Right), Some(Down));
+        assert!(j.mv(&Right));
+        assert_eq!(
+            j.history.last(),
+            Some(&(Position { x: 2, y: 1 }, Some(Down)))
+        );
+        // both sides open, no slide
+        let open = Maze::create(3, 2, vec![(1, 2)]).unwrap();
+        let mut j = open.joystick();
+        j.assist = true;
+        j.right();
+        j.down();
+        assert_eq!(j.resolve(&Down), None);
+    }
+
+    #[test]
+    fn test_portable_seed() {
+        // seeds are shared between players, the maze of a seed must never change
+        let opts = Opts {
+            seed: Some(2024),
+            ..Default::default()
+        };
+        let m = Maze::generate(4, 3, &opts).unwrap();
+        assert_eq!(
+            m.ui().render(false),
+            "┌───────────────┐\n\
+             │               │\n\
+             │   ╷   ╶───┐   │\n\
+             │   │       │   │\n\
+             │   │   ╷   └───┤\n\
+             │   │   │   Exit│\n\
+             └───┴───┴───────┘\n"
+        );
+    }
+
+    #[test]
+    fn test_undo() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut j = m.joystick();
+        assert!(!j.undo());
+        j.right();
+        j.right();
+        assert!(j.undo());
+        assert_eq!(j.pos, Position { x: 1, y: 0 });
+        assert_eq!(j.move_count(), 1);
+        j.reset();
+        j.right();
+        assert!(j.undo());
+        assert_eq!(j.pos, m.entrance());
+        assert!(!j.undo());
+    }
+
+    #[test]
+    fn test_verify_path() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        assert_eq!(m.verify_path(&[Right, Right, Down]), Ok(m.exit()));
+        assert_eq!(
+            m.verify_path(&[Right, Down]),
+            Err(PathError::Wall {
+                index: 1,
+                pos: Position { x: 1, y: 0 },
+                dir: Down
+            })
+        );
+        assert!(matches!(
+            m.verify_path(&[Up]),
+            Err(PathError::OutOfBounds { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_solve() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let path = m.solve();
+        assert_eq!(path, [Right, Right, Down]);
+        let mut j = m.joystick();
+        assert!(j.follow(&path));
+        assert!(j.is_exit());
+        assert!(!m.joystick().follow(&[Down]));
+    }
+
+    #[test]
+    fn test_next_move() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        assert_eq!(m.next_move(m.entrance()), Some(Right));
+        assert_eq!(m.next_move(Position { x: 2, y: 0 }), Some(Down));
+        // dead end of the bottom row leads back right
+        assert_eq!(m.next_move(Position { x: 0, y: 1 }), Some(Right));
+        assert_eq!(m.next_move(m.exit()), None);
+    }
+
+    #[test]
+    fn test_backtracker() {
+        let opts = Opts {
+            algorithm: "backtracker".parse().unwrap(),
+            seed: Some(5),
+            ..Default::default()
+        };
+        let m = Maze::generate(16, 8, &opts).unwrap();
+        // a perfect maze reaching every cell
+        assert_eq!(m.walls.len(), (16 - 1) * 8 + 16 * (8 - 1) - (16 * 8 - 1));
+        assert!(m.distance_map(m.entrance()).iter().all(|d| d.is_some()));
+        let dead_ends = |m: &Maze| {
+            (0..m.size)
+                .map(|c| m.cell_to_pos(c))
+                .filter(|p| {
+                    DIRECTIONS
+                        .iter()
+                        .filter(|d| m.move_pos(*p, d).is_some())
+                        .count()
+                        == 1
+                })
+                .count()
+        };
+        let kruskal = Maze::generate(
+            16,
+            8,
+            &Opts {
+                algorithm: Algorithm::RandomKruskal,
+                ..opts
+            },
+        )
+        .unwrap();
+        assert!(dead_ends(&m) < dead_ends(&kruskal));
+        assert!("eller".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn test_prim_wilson() {
+        for algorithm in &["prim", "wilson"] {
+            let opts = Opts {
+                algorithm: algorithm.parse().unwrap(),
+                seed: Some(9),
+                ..Default::default()
+            };
+            let m = Maze::generate(12, 6, &opts).unwrap();
+            assert_eq!(
+                m.walls.len(),
+                11 * 6 + 12 * 5 - (12 * 6 - 1),
+                "{}",
+                algorithm
+            );
+            assert!(m.distance_map(m.entrance()).iter().all(|d| d.is_some()));
+            assert_eq!(
+                m.fingerprint(),
+                Maze::generate(12, 6, &opts).unwrap().fingerprint()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fog() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut j = m.joystick();
+        assert_eq!(j.sight(1), [true, true, false, true, false, false]);
+        j.right();
+        j.right();
+        // the entrance stays in sight once visited
+        assert_eq!(j.sight(0), [true, true, true, false, false, false]);
+        let board: Vec<String> = m
+            .ui()
+            .draw_visible(&j.sight(0))
+            .iter()
+            .map(|r| r.iter().collect())
+            .collect();
+        assert_eq!(
+            board,
+            [
+                "┌───────────┐",
+                "│           │",
+                "├───────╴   │",
+                "             ",
+                "             "
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_segment() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let (a, b, c) = (
+            Position { x: 1, y: 0 },
+            Position { x: 2, y: 0 },
+            Position { x: 2, y: 1 },
+        );
+        for size in [CellSize::Small, CellSize::Huge].iter() {
+            let ui = m.ui_sized(*size);
+            let across = ui.path_segment(&a, &b);
+            assert_eq!(across.len(), size.width() as usize);
+            assert_eq!(across.last(), Some(&ui.locate(&b)));
+            let down = ui.path_segment(&b, &c);
+            assert_eq!(down.len(), size.height() as usize);
+            assert_eq!(down.last(), Some(&ui.locate(&c)));
+            assert!(ui.path_segment(&a, &c).is_empty());
+            assert_eq!(ui.trail_of(&[a, b, c]).len(), 1 + across.len() + down.len());
+        }
+    }
+
+    #[test]
+    fn test_huge() {
+        let m = Maze::create(2, 2, vec![(0, 2)]).unwrap();
+        let ui = m.ui_sized(CellSize::Huge);
+        assert_eq!(ui.dimensions(), (16, 8));
+        assert_eq!(
+            ui.to_string(),
+            "┌───────────────┐\n\
+             │               │\n\
+             │               │\n\
+             │               │\n\
+             ├───────╴       │\n\
+             │               │\n\
+             │         Exit  │\n\
+             │               │\n\
+             └───────────────┘\n"
+        );
+    }
+
+    #[test]
+    fn test_braid() {
+        let opts = Opts {
+            seed: Some(6),
+            ..Default::default()
+        };
+        let perfect = Maze::generate(12, 8, &opts).unwrap();
+        let dead_ends = perfect.dead_ends().len();
+        assert!(dead_ends > 0);
+        let braided = |braid| {
+            Maze::generate(
+                12,
+                8,
+                &Opts {
+                    braid,
+                    ..opts.clone()
+                },
+            )
+            .unwrap()
+        };
+        assert_eq!(braided(0.0).fingerprint(), perfect.fingerprint());
+        let half = braided(0.5);
+        assert!(half.dead_ends().len() <= dead_ends / 2);
+        assert!(!half.dead_ends().is_empty());
+        assert!(half.solution_length() <= perfect.solution_length());
+        assert!(braided(1.0).dead_ends().is_empty());
+    }
+
+    #[test]
+    fn test_placement() {
+        let opts = Opts {
+            seed: Some(9),
+            ..Default::default()
+        };
+        let corners = Maze::generate(10, 6, &opts).unwrap();
+        let farthest = Maze::generate(
+            10,
+            6,
+            &Opts {
+                placement: Placement::Farthest,
+                ..opts.clone()
+            },
+        )
+        .unwrap();
+        // the same maze, only the ends move
+        assert_eq!(farthest.walls(), corners.walls());
+        let longest = farthest.solution_length().unwrap();
+        assert!(longest >= corners.solution_length().unwrap());
+        for c in 0..farthest.size {
+            let dist = farthest.distance_map(farthest.cell_to_pos(c));
+            assert!(dist.iter().all(|d| d.unwrap() <= longest));
+        }
+
+        for seed in 0..10 {
+            let m = Maze::generate(
+                10,
+                6,
+                &Opts {
+                    seed: Some(seed),
+                    difficulty: Difficulty::Normal,
+                    placement: Placement::Borders,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let (a, b) = (m.entrance(), m.exit());
+            let (left_right, top_bottom) = (
+                a.x.max(b.x) == 9 && a.x.min(b.x) == 0,
+                a.y.max(b.y) == 5 && a.y.min(b.y) == 0,
+            );
+            assert!(left_right || top_bottom, "{:?} {:?}", a, b);
+            assert!(m.solution_length().is_some());
+        }
+        assert_eq!(
+            "farthest".parse::<Placement>().unwrap(),
+            Placement::Farthest
+        );
+    }
+
+    #[test]
+    fn test_exits() {
+        let opts = Opts {
+            seed: Some(5),
+            exits: 3,
+            ..Default::default()
+        };
+        let m = Maze::generate(9, 7, &opts).unwrap();
+        let exits = m.exits();
+        assert_eq!(exits.len(), 3);
+        assert_eq!(exits[0], m.exit());
+        for e in exits.iter() {
+            assert!(m.is_exit(*e));
+            assert!(e.x == 0 || e.y == 0 || e.x == 8 || e.y == 6);
+            assert_ne!(*e, m.entrance());
+        }
+        assert!(!m.is_exit(m.entrance()));
+
+        // the way out leads to the nearest exit
+        let dist = m.distance_map(m.entrance());
+        let nearest = exits
+            .iter()
+            .map(|e| dist[m.pos_to_cell(*e) as usize].unwrap())
+            .min();
+        assert_eq!(m.solution_length(), nearest);
+        let mut j = m.joystick();
+        assert!(j.follow(&m.solve()));
+        assert!(j.is_exit());
+        assert_eq!(
+            m.exit_distance_map()[m.pos_to_cell(m.entrance()) as usize],
+            nearest
+        );
+
+        let board = m.ui().to_string();
+        assert!(board.contains("Exit") && board.contains("Ex 2") && board.contains("Ex 3"));
+        assert_eq!(m.rotate(Rotation::new(1)).exits().len(), 3);
+        assert_eq!(m.pack().unwrap().unpack().unwrap().exits(), exits);
+        // a single exit by default
+        assert_eq!(
+            Maze::generate(9, 7, &Opts::default())
+                .unwrap()
+                .exits()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_pack() {
+        for opts in [
+            Opts::default(),
+            Opts {
+                wrap: true,
+                ..Default::default()
+            },
+            Opts {
+                mask: Some(".##.\n....\n#..#".parse().unwrap()),
+                ..Default::default()
+            },
+        ]
+        .iter()
+        {
+            for (w, h) in [(7, 5), (3, 3), (2, 6)].iter() {
+                let m = Maze::generate(*w, *h, opts).unwrap();
+                let packed = m.pack().unwrap();
+                assert_eq!(
+                    packed.walls.len(),
+                    m.size as usize / 2 + m.size as usize % 2
+                );
+                let unpacked = packed.unpack().unwrap();
+                assert_eq!(unpacked.walls(), m.walls());
+                assert_eq!(unpacked.fingerprint(), m.fingerprint());
+            }
+        }
+        // walls of created mazes may run either way
+        let m = Maze::create(3, 2, vec![(3, 0), (1, 2)]).unwrap();
+        assert_eq!(m.pack().unwrap().walls, "600");
+        assert_eq!(
+            m.pack().unwrap().unpack().unwrap().fingerprint(),
+            m.fingerprint()
+        );
+        assert!(Maze::create(3, 2, vec![(0, 5)]).unwrap().pack().is_none());
+        let bad = PackedMaze {
+            walls: "4x".to_string(),
+            ..m.pack().unwrap()
+        };
+        assert!(bad.unpack().is_err());
+    }
+
+    #[test]
+    fn test_wrap() {
+        let opts = Opts {
+            seed: Some(3),
+            wrap: true,
+            ..Default::default()
+        };
+        let m = Maze::generate(8, 5, &opts).unwrap();
+        assert!(m.is_wrapped());
+        assert_ne!(
+            m.fingerprint(),
+            Maze::generate(
+                8,
+                5,
+                &Opts {
+                    wrap: false,
+                    ..opts.clone()
+                }
+            )
+            .unwrap()
+            .fingerprint()
+        );
+        // every cell has four neighbours and a perfect maze knocks down one wall less
+        // than there are cells
+        assert_eq!(m.walls().len(), 2 * 40 - 39);
+        assert_eq!(
+            m.neighbour(Position { x: 0, y: 2 }, &Left),
+            Some(Position { x: 7, y: 2 })
+        );
+        assert_eq!(
+            m.neighbour(Position { x: 3, y: 4 }, &Down),
+            Some(Position { x: 3, y: 0 })
+        );
+        // the way out leaves column A across the left edge into the exit
+        let mut j = m.joystick();
+        assert!(j.follow(&m.solve()));
+        assert!(j.is_exit());
+        assert_eq!(m.solve().last(), Some(&Left));
+        let board = m.ui().draw();
+        assert_eq!(board[9][0], '←');
+        assert_eq!(board[9][32], '→');
+
+        // narrow mazes only wrap where the edges are far enough apart
+        let narrow = Maze::generate(2, 5, &opts).unwrap();
+        assert_eq!(narrow.neighbour(Position { x: 0, y: 0 }, &Left), None);
+        assert_eq!(
+            narrow.neighbour(Position { x: 0, y: 0 }, &Up),
+            Some(Position { x: 0, y: 4 })
+        );
+    }
+
+    #[test]
+    fn test_variations() {
+        let opts = Opts {
+            seed: Some(7),
+            ..Default::default()
+        };
+        let base = Maze::generate(12, 8, &opts).unwrap();
+        let length = base.solution_length().unwrap();
+        let family = base.variations(5, 3, 1);
+        assert_eq!(family.len(), 5);
+        let mut prints: Vec<u64> = family.iter().map(Maze::fingerprint).collect();
+        prints.push(base.fingerprint());
+        prints.sort_unstable();
+        prints.dedup();
+        assert_eq!(prints.len(), 6);
+        for m in &family {
+            assert!(m.distance_map(m.entrance()).iter().all(Option::is_some));
+            assert_eq!(m.walls.len(), base.walls.len());
+            let len = m.solution_length().unwrap();
+            assert!(len + length / 10 >= length && len <= length + length / 10);
+        }
+        // the same seed gives the same family
+        let again: Vec<u64> = base
+            .variations(5, 3, 1)
+            .iter()
+            .map(Maze::fingerprint)
+            .collect();
+        assert_eq!(
+            again,
+            family.iter().map(Maze::fingerprint).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4), (4, 5)]).unwrap();
+        let same = Maze::create(3, 2, vec![(5, 4), (0, 3), (4, 1)]).unwrap();
+        let other = Maze::create(3, 2, vec![(0, 3), (1, 4), (2, 5)]).unwrap();
+        assert_eq!(m.fingerprint(), same.fingerprint());
+        assert_ne!(m.fingerprint(), other.fingerprint());
+        assert_eq!(m.short_fingerprint().len(), 8);
+        assert_eq!(m.short_fingerprint(), same.short_fingerprint());
+    }
 }
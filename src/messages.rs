@@ -0,0 +1,72 @@
+use std::collections::VecDeque;
+
+/// Number of messages kept in the history.
+const HISTORY: usize = 100;
+
+/// Scrolling log of game events ("Checkpoint reached", "New best time!", ...).
+#[derive(Debug)]
+pub struct MessageLog {
+    messages: VecDeque<String>,
+    capacity: usize,
+}
+
+impl Default for MessageLog {
+    fn default() -> Self {
+        MessageLog::with_capacity(HISTORY)
+    }
+}
+
+impl MessageLog {
+    pub fn with_capacity(capacity: usize) -> MessageLog {
+        MessageLog {
+            messages: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// add a message, dropping the oldest one when the history is full
+    pub fn push<S: Into<String>>(&mut self, msg: S) {
+        if self.messages.len() == self.capacity {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(msg.into());
+    }
+
+    /// the last n messages, oldest first
+    pub fn recent(&self, n: usize) -> impl Iterator<Item = &String> {
+        self.messages
+            .iter()
+            .skip(self.messages.len().saturating_sub(n))
+    }
+
+    /// all the messages in the history, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.messages.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let mut log = MessageLog::with_capacity(3);
+        assert!(log.is_empty());
+        for m in ["one", "two", "three", "four"].iter() {
+            log.push(*m);
+        }
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.iter().collect::<Vec<_>>(), ["two", "three", "four"]);
+        assert_eq!(log.recent(2).collect::<Vec<_>>(), ["three", "four"]);
+        assert_eq!(log.recent(5).count(), 3);
+    }
+}
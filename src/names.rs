@@ -0,0 +1,87 @@
+const ADJECTIVES: &[&str] = &[
+    "Winding",
+    "Forgotten",
+    "Silent",
+    "Crooked",
+    "Endless",
+    "Hollow",
+    "Tangled",
+    "Sunken",
+    "Whispering",
+    "Gilded",
+    "Shadowed",
+    "Frozen",
+    "Broken",
+    "Ancient",
+    "Restless",
+    "Verdant",
+];
+
+const PLACES: &[&str] = &[
+    "Halls",
+    "Passages",
+    "Catacombs",
+    "Corridors",
+    "Gardens",
+    "Depths",
+    "Tunnels",
+    "Vaults",
+    "Cloisters",
+    "Warrens",
+    "Galleries",
+    "Ruins",
+];
+
+const ONSETS: &[&str] = &[
+    "K", "Br", "T", "M", "Z", "Th", "V", "Gr", "L", "S", "D", "Qu",
+];
+const VOWELS: &[&str] = &["a", "e", "i", "o", "u", "ae", "y"];
+const CODAS: &[&str] = &["rst", "n", "th", "x", "l", "m", "rk", "s", "nd", "r"];
+
+/// Small splitmix64 step, the name must not depend on the rand crate version.
+fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn pick<'a>(state: &mut u64, words: &[&'a str]) -> &'a str {
+    words[(next(state) % words.len() as u64) as usize]
+}
+
+/// Deterministic name derived from the seed, e.g. "The Winding Halls of Karst", giving
+/// shared mazes a memorable identity.
+pub fn name(seed: u64) -> String {
+    let mut state = seed;
+    let adjective = pick(&mut state, ADJECTIVES);
+    let place = pick(&mut state, PLACES);
+    let mut proper = String::new();
+    for _ in 0..1 + next(&mut state) % 2 {
+        proper.push_str(pick(&mut state, ONSETS));
+        proper.push_str(pick(&mut state, VOWELS));
+    }
+    proper.push_str(pick(&mut state, CODAS));
+    // only the first letter is capitalized, onsets of later syllables are lowered
+    let proper = proper
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i == 0 { c } else { c.to_ascii_lowercase() })
+        .collect::<String>();
+    format!("The {} {} of {}", adjective, place, proper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name() {
+        assert_eq!(name(42), name(42));
+        assert_ne!(name(1), name(2));
+        let n = name(7);
+        assert!(n.starts_with("The "));
+        assert!(n.contains(" of "));
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use crate::maze::{Direction, Joystick};
+
+/// Longest run of moves in one direction, as many as a maze has cells at most.
+pub const MAX_RUN: usize = u16::MAX as usize;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum NotationError {
+    #[error("invalid move `{0}`, expected a direction L, R, U or D and an optional count")]
+    Parse(String),
+    #[error("move {0} runs into a wall")]
+    Blocked(usize),
+    #[error("run `{0}` is longer than {} moves", MAX_RUN)]
+    Run(String),
+}
+
+fn letter(d: &Direction) -> char {
+    match d {
+        Direction::Left => 'L',
+        Direction::Right => 'R',
+        Direction::Up => 'U',
+        Direction::Down => 'D',
+    }
+}
+
+/// Write moves as runs of a direction letter and a count, e.g. "R3 D2 L1".
+pub fn encode(moves: &[Direction]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < moves.len() {
+        let run = moves[i..].iter().take_while(|d| **d == moves[i]).count();
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        write!(out, "{}{}", letter(&moves[i]), run).unwrap();
+        i += run;
+    }
+    out
+}
+
+/// Read moves written by `encode`, letters are case insensitive and a missing count
+/// means one move.
+pub fn decode(s: &str) -> Result<Vec<Direction>, NotationError> {
+    let mut moves = Vec::new();
+    for run in s.split_whitespace() {
+        let err = || NotationError::Parse(run.to_string());
+        let mut chars = run.chars();
+        let d = match chars.next().map(|c| c.to_ascii_uppercase()) {
+            Some('L') => Direction::Left,
+            Some('R') => Direction::Right,
+            Some('U') => Direction::Up,
+            Some('D') => Direction::Down,
+            _ => return Err(err()),
+        };
+        let count = match chars.as_str() {
+            "" => 1,
+            n => n.parse::<usize>().map_err(|_| err())?,
+        };
+        if count > MAX_RUN {
+            return Err(NotationError::Run(run.to_string()));
+        }
+        moves.resize(moves.len() + count, d);
+    }
+    Ok(moves)
+}
+
+impl Joystick<'_> {
+    /// moves made since the start or last reset in notation
+    pub fn notation(&self) -> String {
+        let moves: Vec<Direction> = self.history.iter().filter_map(|h| h.1).collect();
+        encode(&moves)
+    }
+
+    /// Play the moves written in notation, stopping at the first one that runs into a
+    /// wall.
+    pub fn play_notation(&mut self, s: &str) -> Result<(), NotationError> {
+        for (i, d) in decode(s)?.iter().enumerate() {
+            if !self.mv(d) {
+                return Err(NotationError::Blocked(i + 1));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Maze;
+
+    #[test]
+    fn test_notation() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut j = m.joystick();
+        j.play_notation("R2 d").unwrap();
+        assert!(j.is_exit());
+        assert_eq!(j.notation(), "R2 D1");
+        assert_eq!(decode("R2 D1").unwrap(), decode("r R d1").unwrap());
+
+        assert_eq!(decode("X2"), Err(NotationError::Parse("X2".to_string())));
+        assert_eq!(decode("R-1"), Err(NotationError::Parse("R-1".to_string())));
+        let huge = "R18446744073709551615";
+        assert_eq!(decode(huge), Err(NotationError::Run(huge.to_string())));
+        assert!(matches!(
+            decode("R18446744073709551616"),
+            Err(NotationError::Parse(_))
+        ));
+        assert_eq!(decode(&format!("U{}", MAX_RUN)).unwrap().len(), MAX_RUN);
+        let mut j = m.joystick();
+        assert_eq!(j.play_notation("D1"), Err(NotationError::Blocked(1)));
+    }
+}
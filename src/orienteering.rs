@@ -0,0 +1,155 @@
+use rand::seq::SliceRandom;
+
+use crate::maze::{Maze, Position};
+
+/// Checkpoint counts up to this are toured exactly, larger courses are estimated greedily.
+const EXACT_TOUR: usize = 10;
+
+/// Checkpoints to visit in any order before the exit unlocks.
+#[derive(Clone, Debug, Default)]
+pub struct Course {
+    checkpoints: Vec<Position>,
+    visited: Vec<bool>,
+}
+
+impl Course {
+    pub fn new(checkpoints: Vec<Position>) -> Course {
+        Course {
+            visited: vec![false; checkpoints.len()],
+            checkpoints,
+        }
+    }
+
+    /// Place checkpoints on random reachable cells, never on the entrance or an exit.
+    pub fn random(maze: &Maze, count: usize) -> Course {
+        let dist = maze.distance_map(maze.entrance());
+        let mut cells: Vec<Position> = (0..dist.len() as u16)
+            .filter(|c| dist[*c as usize].is_some())
+            .map(|c| maze.cell_to_pos(c))
+            .filter(|p| *p != maze.entrance() && !maze.is_exit(*p))
+            .collect();
+        cells.shuffle(&mut rand::thread_rng());
+        cells.truncate(count);
+        Course::new(cells)
+    }
+
+    /// add a checkpoint to visit, e.g. an exit when all of them have to be reached
+    pub fn add(&mut self, p: Position) {
+        self.checkpoints.push(p);
+        self.visited.push(false);
+    }
+
+    pub fn checkpoints(&self) -> &[Position] {
+        &self.checkpoints
+    }
+
+    /// Mark the checkpoint at the position as visited. Returns its index when it wasn't
+    /// visited before.
+    pub fn visit(&mut self, p: Position) -> Option<usize> {
+        let i = self.checkpoints.iter().position(|c| *c == p)?;
+        if self.visited[i] {
+            return None;
+        }
+        self.visited[i] = true;
+        Some(i)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.visited.iter().filter(|v| !**v).count()
+    }
+
+    /// check if every checkpoint was visited, unlocking the exit
+    pub fn is_complete(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Length of the shortest tour from the entrance through every checkpoint to the
+    /// nearest exit. Exact for small courses (Held-Karp), nearest neighbour estimate
+    /// otherwise.
+    pub fn optimal_tour(&self, maze: &Maze) -> Option<usize> {
+        // stops: entrance, checkpoints..., and last the way out to the nearest exit
+        let mut stops = vec![maze.entrance()];
+        stops.extend(self.checkpoints.iter().copied());
+        let to_exit = maze.exit_distance_map();
+        let mut dist = vec![vec![0; stops.len() + 1]; stops.len()];
+        for (i, from) in stops.iter().enumerate() {
+            let map = maze.distance_map(*from);
+            for (j, to) in stops.iter().enumerate() {
+                dist[i][j] = map[maze.pos_to_cell(*to) as usize]?;
+            }
+            dist[i][stops.len()] = to_exit[maze.pos_to_cell(*from) as usize]?;
+        }
+
+        let n = self.checkpoints.len();
+        let exit = n + 1;
+        if n == 0 {
+            return Some(dist[0][exit]);
+        }
+        if n <= EXACT_TOUR {
+            // best[mask][i]: shortest walk from the entrance visiting mask, ending at checkpoint i
+            let mut best = vec![vec![usize::MAX; n]; 1 << n];
+            for i in 0..n {
+                best[1 << i][i] = dist[0][i + 1];
+            }
+            for mask in 1..(1usize << n) {
+                for i in 0..n {
+                    let cost = best[mask][i];
+                    if cost == usize::MAX || mask & (1 << i) == 0 {
+                        continue;
+                    }
+                    for j in 0..n {
+                        if mask & (1 << j) == 0 {
+                            let next = &mut best[mask | (1 << j)][j];
+                            *next = (*next).min(cost + dist[i + 1][j + 1]);
+                        }
+                    }
+                }
+            }
+            (0..n)
+                .map(|i| best[(1 << n) - 1][i] + dist[i + 1][exit])
+                .min()
+        } else {
+            let mut left: Vec<usize> = (1..=n).collect();
+            let (mut at, mut total) = (0, 0);
+            while !left.is_empty() {
+                let (k, &next) = left
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, s)| dist[at][**s])
+                    .unwrap();
+                total += dist[at][next];
+                at = next;
+                left.remove(k);
+            }
+            Some(total + dist[at][exit])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tour() {
+        // two corridors only joined on the right
+        let m = Maze::create(4, 2, vec![(0, 4), (1, 5), (2, 6)]).unwrap();
+        let mut course = Course::new(vec![Position { x: 0, y: 1 }, Position { x: 3, y: 0 }]);
+        // entrance -> (3,0) -> (0,1) -> back to the exit at (3,1)
+        assert_eq!(course.optimal_tour(&m), Some(3 + 1 + 3 + 3));
+        assert_eq!(Course::default().optimal_tour(&m), m.solution_length());
+
+        assert!(!course.is_complete());
+        assert_eq!(course.visit(Position { x: 3, y: 0 }), Some(1));
+        assert_eq!(course.visit(Position { x: 3, y: 0 }), None);
+        assert_eq!(course.visit(Position { x: 1, y: 0 }), None);
+        assert_eq!(course.remaining(), 1);
+        assert_eq!(course.visit(Position { x: 0, y: 1 }), Some(0));
+        assert!(course.is_complete());
+
+        let m = Maze::generate(6, 6, &Default::default()).unwrap();
+        let course = Course::random(&m, 12);
+        assert_eq!(course.checkpoints().len(), 12);
+        assert!(course.optimal_tour(&m).unwrap() >= m.solution_length().unwrap());
+    }
+}
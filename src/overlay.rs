@@ -0,0 +1,137 @@
+use crate::maze::{Direction, Joystick, MazeUI, Position};
+
+/// Layer changing the walls of the board as drawn on screen, like the fog or the memory
+/// mode.
+pub trait Overlay {
+    /// the board drawn by `ui` once the layer is applied
+    fn apply(&self, ui: &MazeUI, board: Vec<Vec<char>>) -> Vec<Vec<char>>;
+}
+
+/// Fog hiding the cells out of sight of the player, see `Joystick::sight`.
+#[derive(Clone, Debug, Default)]
+pub struct Fog {
+    radius: u16,
+    /// Cells in sight on the maze as drawn, None while the fog is lifted.
+    visible: Option<Vec<bool>>,
+}
+
+impl Fog {
+    /// Fog with the sight radius, 0 for none. `view` is the player on the maze as drawn.
+    pub fn new(radius: u16, view: &Joystick) -> Fog {
+        let mut fog = Fog {
+            radius,
+            visible: None,
+        };
+        fog.look(view, radius);
+        fog
+    }
+
+    pub fn radius(&self) -> u16 {
+        self.radius
+    }
+
+    /// Look around the player as far as the radius, which may differ from the one of the
+    /// fog like on the way back of a return trip, and 0 sees nothing new. Returns true
+    /// when the cells in sight changed.
+    pub fn look(&mut self, view: &Joystick, radius: u16) -> bool {
+        if radius == 0 {
+            return false;
+        }
+        let sight = view.sight(radius);
+        if self.visible.as_ref() == Some(&sight) {
+            return false;
+        }
+        self.visible = Some(sight);
+        true
+    }
+
+    /// Show every cell until the next look. Returns true when some were hidden.
+    pub fn lift(&mut self) -> bool {
+        self.visible.take().is_some()
+    }
+}
+
+impl Overlay for Fog {
+    fn apply(&self, ui: &MazeUI, board: Vec<Vec<char>>) -> Vec<Vec<char>> {
+        match &self.visible {
+            Some(v) => ui.hide_unseen(board, v),
+            None => board,
+        }
+    }
+}
+
+/// Walls hidden in memory mode, and the ones the player bumped into since.
+#[derive(Clone, Debug, Default)]
+pub struct Memory {
+    hidden: bool,
+    /// by cell and side
+    bumped: Vec<(Position, Direction)>,
+}
+
+impl Memory {
+    pub fn hide(&mut self) {
+        self.hidden = true;
+    }
+
+    /// Show the walls again. Returns true when they were hidden.
+    pub fn reveal(&mut self) -> bool {
+        std::mem::replace(&mut self.hidden, false)
+    }
+
+    /// Remember a move into a hidden wall. Returns true the first time it is bumped into.
+    pub fn bump(&mut self, p: Position, d: Direction) -> bool {
+        if !self.hidden || self.bumped.contains(&(p, d)) {
+            return false;
+        }
+        self.bumped.push((p, d));
+        true
+    }
+
+    /// walls bumped into, by cell and side
+    pub fn bumps(&self) -> &[(Position, Direction)] {
+        &self.bumped
+    }
+}
+
+impl Overlay for Memory {
+    fn apply(&self, _: &MazeUI, board: Vec<Vec<char>>) -> Vec<Vec<char>> {
+        if !self.hidden {
+            return board;
+        }
+        board.into_iter().map(|r| vec![' '; r.len()]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Maze;
+
+    #[test]
+    fn test_overlays() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let ui = m.ui();
+        let mut j = m.joystick();
+        let mut fog = Fog::new(1, &j);
+        assert_ne!(fog.apply(&ui, ui.draw()), ui.draw());
+        assert!(!fog.look(&j, 1));
+        j.pos = Position { x: 2, y: 1 };
+        assert!(fog.look(&j, 1) && !fog.look(&j, 0));
+        assert!(fog.lift() && !fog.lift());
+        assert_eq!(fog.apply(&ui, ui.draw()), ui.draw());
+        assert!(!Fog::new(0, &j).lift());
+
+        let mut memory = Memory::default();
+        assert!(!memory.bump(j.pos, Direction::Up));
+        memory.hide();
+        assert!(memory
+            .apply(&ui, ui.draw())
+            .iter()
+            .flatten()
+            .all(|c| *c == ' '));
+        assert!(memory.bump(j.pos, Direction::Up) && !memory.bump(j.pos, Direction::Up));
+        assert_eq!(memory.bumps(), [(j.pos, Direction::Up)]);
+        assert!(memory.reveal() && !memory.reveal());
+        assert_eq!(memory.apply(&ui, ui.draw()), ui.draw());
+    }
+}
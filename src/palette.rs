@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// Most cells of a maze, they are numbered with u16.
+pub const MAX_CELLS: u32 = u16::MAX as u32;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CommandError {
+    #[error("unknown command `{0}`, try save, seed, theme or size")]
+    Unknown(String),
+    #[error("usage: {0}")]
+    Usage(&'static str),
+}
+
+/// Command typed after `:` while playing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// save the game, to the default file without a path
+    Save(Option<PathBuf>),
+    /// show the seed of the maze, or play the maze of the given seed
+    Seed(Option<u64>),
+    Theme(String),
+    /// play a new maze of the given width and height, of `MAX_CELLS` at most
+    Size(u16, u16),
+}
+
+impl FromStr for Command {
+    type Err = CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches(':');
+        let mut words = s.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let arg = words.next();
+        if words.next().is_some() {
+            return Err(CommandError::Usage("one argument at most"));
+        }
+        match (name, arg) {
+            ("save" | "w", path) => Ok(Command::Save(path.map(PathBuf::from))),
+            ("seed", None) => Ok(Command::Seed(None)),
+            ("seed", Some(n)) => n
+                .parse()
+                .map(|n| Command::Seed(Some(n)))
+                .map_err(|_| CommandError::Usage("seed [NUMBER]")),
+            ("theme", Some(t)) => Ok(Command::Theme(t.to_string())),
+            ("theme", None) => Err(CommandError::Usage("theme NAME")),
+            ("size", Some(size)) => {
+                let usage = CommandError::Usage("size WIDTHxHEIGHT, 65535 cells at most");
+                let mut dims = size.split('x').map(|d| d.parse::<u16>());
+                match (dims.next(), dims.next(), dims.next()) {
+                    (Some(Ok(w)), Some(Ok(h)), None)
+                        if w >= 2 && h >= 2 && w as u32 * h as u32 <= MAX_CELLS =>
+                    {
+                        Ok(Command::Size(w, h))
+                    }
+                    _ => Err(usage),
+                }
+            }
+            ("size", None) => Err(CommandError::Usage("size WIDTHxHEIGHT")),
+            (name, _) => Err(CommandError::Unknown(name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ":save foo.ron".parse(),
+            Ok(Command::Save(Some(PathBuf::from("foo.ron"))))
+        );
+        assert_eq!("save".parse(), Ok(Command::Save(None)));
+        assert_eq!("seed".parse(), Ok(Command::Seed(None)));
+        assert_eq!("seed 42".parse(), Ok(Command::Seed(Some(42))));
+        assert_eq!(
+            "theme matrix".parse(),
+            Ok(Command::Theme("matrix".to_string()))
+        );
+        assert_eq!(" size 40x30 ".parse(), Ok(Command::Size(40, 30)));
+        assert!("size 40".parse::<Command>().is_err());
+        assert!("size 1x30".parse::<Command>().is_err());
+        assert_eq!("size 255x257".parse(), Ok(Command::Size(255, 257)));
+        assert!("size 256x256".parse::<Command>().is_err());
+        assert_eq!(
+            "fly".parse::<Command>(),
+            Err(CommandError::Unknown("fly".to_string()))
+        );
+    }
+}
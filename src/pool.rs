@@ -0,0 +1,75 @@
+use std::thread::{self, JoinHandle};
+
+use crate::maze::{Maze, MazeError, Opts};
+
+/// Maze being generated on a background thread along with what it was asked for.
+struct Pending {
+    width: u16,
+    height: u16,
+    opts: Opts,
+    handle: JoinHandle<Result<Maze, MazeError>>,
+}
+
+/// Generates the next maze in the background while the current one is played, so big
+/// Hard mazes don't keep the player waiting.
+#[derive(Default)]
+pub struct MazePool {
+    next: Option<Pending>,
+}
+
+impl MazePool {
+    /// Start generating a maze for a later `take`, replacing any pending one.
+    pub fn prefetch(&mut self, width: u16, height: u16, opts: &Opts) {
+        let thread_opts = opts.clone();
+        self.next = Some(Pending {
+            width,
+            height,
+            opts: opts.clone(),
+            handle: thread::spawn(move || Maze::generate(width, height, &thread_opts)),
+        });
+    }
+
+    /// The prefetched maze when it was made with the same size and options, otherwise a
+    /// freshly generated one.
+    pub fn take(&mut self, width: u16, height: u16, opts: &Opts) -> Result<Maze, MazeError> {
+        match self.next.take() {
+            Some(p) if (p.width, p.height, &p.opts) == (width, height, opts) => p
+                .handle
+                .join()
+                .unwrap_or_else(|_| Maze::generate(width, height, opts)),
+            _ => Maze::generate(width, height, opts),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool() {
+        let opts = Opts {
+            seed: Some(3),
+            ..Default::default()
+        };
+        let mut pool = MazePool::default();
+        pool.prefetch(20, 10, &opts);
+        let m = pool.take(20, 10, &opts).unwrap();
+        assert_eq!(
+            m.fingerprint(),
+            Maze::generate(20, 10, &opts).unwrap().fingerprint()
+        );
+
+        // a pending maze of another size isn't handed out
+        pool.prefetch(20, 10, &opts);
+        let m = pool.take(8, 4, &opts).unwrap();
+        assert_eq!((m.width, m.height), (8, 4));
+        assert!(pool.next.is_none());
+
+        // too many cells is an error rather than a panic on the way
+        assert!(matches!(
+            pool.take(300, 300, &opts),
+            Err(MazeError::TooLarge(300, 300))
+        ));
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use termion::color;
+use thiserror::Error;
+
+use crate::maze::{Direction, Locate, Maze, MazeUI, Position, DIRECTIONS};
+
+#[derive(Error, Debug)]
+pub enum RaceError {
+    #[error("invalid solver, expected bfs, dfs or wall")]
+    SolverParseError,
+}
+
+/// Ways of searching for the exit, raced against each other in the demo.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Solver {
+    /// breadth first search, explores the maze in rings around the entrance
+    Bfs,
+    /// depth first search, follows a passage to its end before backing up
+    Dfs,
+    /// keeps its right hand on the wall
+    Wall,
+}
+
+impl FromStr for Solver {
+    type Err = RaceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bfs" => Ok(Solver::Bfs),
+            "dfs" => Ok(Solver::Dfs),
+            "wall" | "wall-follower" => Ok(Solver::Wall),
+            _ => Err(RaceError::SolverParseError),
+        }
+    }
+}
+
+impl Solver {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Solver::Bfs => "bfs",
+            Solver::Dfs => "dfs",
+            Solver::Wall => "wall",
+        }
+    }
+}
+
+/// A solver searching the maze one cell at a time.
+pub struct Racer {
+    pub solver: Solver,
+    /// cells explored so far, indexed by cell
+    explored: Vec<bool>,
+    /// cells found but not explored yet, the wall follower only has the one it's on
+    frontier: VecDeque<Position>,
+    heading: Direction,
+    steps: usize,
+    done: bool,
+}
+
+impl Racer {
+    pub fn new(solver: Solver, maze: &Maze) -> Racer {
+        let mut frontier = VecDeque::new();
+        frontier.push_back(maze.entrance());
+        Racer {
+            solver,
+            explored: vec![false; (maze.width * maze.height) as usize],
+            frontier,
+            heading: Direction::Right,
+            steps: 0,
+            done: false,
+        }
+    }
+
+    /// Explore one more cell, false once the exit was reached or the search gave up.
+    pub fn step(&mut self, maze: &Maze) -> bool {
+        if self.done {
+            return false;
+        }
+        let p = match self.solver {
+            Solver::Bfs => self.frontier.pop_front(),
+            Solver::Dfs => self.frontier.pop_back(),
+            Solver::Wall => self.frontier.front().copied(),
+        };
+        let p = match p {
+            Some(p) => p,
+            None => {
+                self.done = true;
+                return false;
+            }
+        };
+        self.steps += 1;
+        self.explored[maze.pos_to_cell(p) as usize] = true;
+        // a wall follower stuck walking around a loop never gets there
+        if maze.is_exit(p) || self.steps > 4 * self.explored.len() {
+            self.done = true;
+            return true;
+        }
+        match self.solver {
+            Solver::Bfs | Solver::Dfs => {
+                for n in DIRECTIONS.iter().filter_map(|d| maze.move_pos(p, d)) {
+                    if !self.explored[maze.pos_to_cell(n) as usize] && !self.frontier.contains(&n) {
+                        self.frontier.push_back(n);
+                    }
+                }
+            }
+            Solver::Wall => {
+                let h = self.heading;
+                let turn = [h.turn_right(), h, h.turn_left(), h.opposite()]
+                    .iter()
+                    .find_map(|d| maze.move_pos(p, d).map(|n| (*d, n)));
+                if let Some((d, n)) = turn {
+                    self.heading = d;
+                    self.frontier[0] = n;
+                }
+            }
+        }
+        true
+    }
+
+    /// cells explored so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn is_explored(&self, maze: &Maze, p: Position) -> bool {
+        self.explored[maze.pos_to_cell(p) as usize]
+    }
+}
+
+/// Two solvers searching the same maze side by side.
+pub struct Race<'a> {
+    maze: &'a Maze,
+    pub racers: [Racer; 2],
+}
+
+impl Race<'_> {
+    pub fn new(maze: &Maze, a: Solver, b: Solver) -> Race<'_> {
+        Race {
+            maze,
+            racers: [Racer::new(a, maze), Racer::new(b, maze)],
+        }
+    }
+
+    /// Move both racers on by a cell, false once both are done.
+    pub fn step(&mut self) -> bool {
+        let maze = self.maze;
+        let a = self.racers[0].step(maze);
+        let b = self.racers[1].step(maze);
+        a || b
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.racers.iter().all(Racer::is_done)
+    }
+
+    /// The maze with the cells explored by the first racer in blue, the second in red and
+    /// both in magenta. Frontier cells are dotted.
+    pub fn render(&self, ui: &MazeUI) -> Vec<String> {
+        let mut board = ui.draw();
+        for r in self.racers.iter() {
+            for p in r.frontier.iter() {
+                let c = ui.locate(p);
+                board[c.y as usize][c.x as usize] = '•';
+            }
+        }
+        let colors: Vec<Option<&str>> = self.racers[0]
+            .explored
+            .iter()
+            .zip(self.racers[1].explored.iter())
+            .map(|explored| match explored {
+                (true, true) => Some(color::Magenta.bg_str()),
+                (true, false) => Some(color::Blue.bg_str()),
+                (false, true) => Some(color::Red.bg_str()),
+                (false, false) => None,
+            })
+            .collect();
+        ui.paint_cells(board, &colors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Opts;
+
+    #[test]
+    fn test_race() {
+        let opts = Opts {
+            seed: Some(3),
+            ..Default::default()
+        };
+        let m = Maze::generate(10, 6, &opts).unwrap();
+        let mut race = Race::new(&m, Solver::Bfs, Solver::Wall);
+        while race.step() {}
+        assert!(race.is_done());
+        for r in race.racers.iter() {
+            assert!(r.is_explored(&m, m.exit()));
+        }
+        // breadth first search never explores a cell twice
+        assert!(race.racers[0].steps() <= 60);
+        assert!(race.racers[0].steps() > m.solution_length().unwrap());
+        assert_eq!("wall-follower".parse::<Solver>().unwrap(), Solver::Wall);
+        assert!("astar".parse::<Solver>().is_err());
+
+        let lines = race.render(&m.ui());
+        assert_eq!(lines.len(), m.ui().draw().len());
+        assert!(lines[1].contains(color::Magenta.bg_str()));
+    }
+}
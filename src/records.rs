@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Best completion of a single maze.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub moves: usize,
+    pub millis: u64,
+    /// score of the runs scored by a treasure or a return trip, see `treasure::score`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<usize>,
+}
+
+impl Record {
+    pub fn new(moves: usize, elapsed: Duration) -> Record {
+        Record {
+            moves,
+            millis: elapsed.as_millis() as u64,
+            score: None,
+        }
+    }
+
+    /// the same run with its score
+    pub fn scored(self, score: usize) -> Record {
+        Record {
+            score: Some(score),
+            ..self
+        }
+    }
+
+    /// elapsed time of the record
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.millis)
+    }
+}
+
+/// How close a run came to the shortest path.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Rating {
+    Perfect,
+    Good,
+    Wandering,
+}
+
+impl Rating {
+    /// rate the moves of a run against the optimal number, up to half again as many is good
+    pub fn of(moves: usize, optimal: usize) -> Rating {
+        if moves <= optimal {
+            Rating::Perfect
+        } else if moves * 2 <= optimal * 3 {
+            Rating::Good
+        } else {
+            Rating::Wandering
+        }
+    }
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rating::Perfect => write!(f, "perfect"),
+            Rating::Good => write!(f, "good"),
+            Rating::Wandering => write!(f, "wandering"),
+        }
+    }
+}
+
+/// Where the records are kept, `rusty-maze/records.ron` in the data directory of the
+/// platform next to the statistics. None when there is no such directory.
+pub fn records_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("records.ron"))
+}
+
+/// Personal best records keyed by maze fingerprint.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct Records {
+    best: HashMap<u64, Record>,
+}
+
+impl Records {
+    /// Load records from the given file. A missing or unreadable file yields no records.
+    pub fn load<P: AsRef<Path>>(path: P) -> Records {
+        File::open(path)
+            .ok()
+            .and_then(|f| ron::de::from_reader(BufReader::new(f)).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write records to the given file, creating its directory.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let out = File::create(path)?;
+        ron::ser::to_writer(out, self)?;
+        Ok(())
+    }
+
+    /// lookup the best record for the maze fingerprint
+    pub fn get(&self, fingerprint: u64) -> Option<&Record> {
+        self.best.get(&fingerprint)
+    }
+
+    /// Submit a completed run. Returns true if the run is a new best, fewer moves wins
+    /// and time breaks ties.
+    pub fn update(&mut self, fingerprint: u64, r: Record) -> bool {
+        match self.best.get(&fingerprint) {
+            Some(b) if (b.moves, b.millis) <= (r.moves, r.millis) => false,
+            _ => {
+                self.best.insert(fingerprint, r);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let mut records = Records::default();
+        assert_eq!(records.get(1), None);
+        assert!(records.update(1, Record::new(20, Duration::from_secs(5))));
+        assert!(!records.update(1, Record::new(24, Duration::from_secs(2))));
+        assert!(records.update(1, Record::new(20, Duration::from_secs(4))));
+        assert!(records.update(1, Record::new(18, Duration::from_secs(9))));
+        assert_eq!(
+            records.get(1),
+            Some(&Record::new(18, Duration::from_secs(9)))
+        );
+        assert_eq!(records.get(2), None);
+    }
+
+    #[test]
+    fn test_rating() {
+        assert_eq!(Rating::of(10, 10), Rating::Perfect);
+        assert_eq!(Rating::of(15, 10), Rating::Good);
+        assert_eq!(Rating::of(16, 10), Rating::Wandering);
+        assert_eq!(Rating::of(0, 0).to_string(), "perfect");
+    }
+}
@@ -0,0 +1,631 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::maze::{Direction, Joystick, Maze, Position};
+
+/// Version of the replay format, bumped whenever replays recorded before would play back
+/// differently.
+pub const REPLAY_VERSION: u32 = 3;
+
+/// Step times are kept in ticks of this many milliseconds.
+pub const TICK_MS: u32 = 10;
+
+/// Steps between two keyframes.
+pub const KEYFRAME_EVERY: usize = 64;
+
+/// zstd compression level of replay files.
+const LEVEL: i32 = 9;
+
+/// Playback speeds, from slow motion to 16 times faster than real time.
+pub const SPEEDS: [f32; 6] = [0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+/// Steps played per second at 1x, for the replays recorded without times.
+const STEPS_PER_SEC: f32 = 8.0;
+
+/// Where the replays and signed results of the won runs are kept without `--save-dir`,
+/// `rusty-maze/runs` in the data directory of the platform next to the statistics. None
+/// when there is no such directory.
+pub fn runs_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("runs"))
+}
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+    #[error("replay format {found} is newer than the {supported} this version plays")]
+    Version { found: u32, supported: u32 },
+    #[error("replay was recorded on maze {expected:016x} but holds maze {found:016x}")]
+    Fingerprint { expected: u64, found: u64 },
+    #[error("replay goes out of sync at step {0}, it is damaged or was recorded with other movement rules")]
+    Desync(usize),
+}
+
+/// Player input recorded in a replay.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Step {
+    Move(Direction),
+    Reset,
+    Undo,
+}
+
+/// Snapshot of the player state so playback can start from it instead of the beginning.
+/// The history is kept as a change from the one of the keyframe before, or from none for
+/// the first, so long runs don't store their whole history over and over.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// number of steps applied before the snapshot
+    at: usize,
+    pos: Position,
+    /// length of the history kept from the keyframe before
+    #[serde(default)]
+    keep: usize,
+    /// moves of the history after the kept ones, the whole history before version 3
+    #[serde(alias = "history")]
+    added: Vec<(Position, Option<Direction>)>,
+}
+
+impl Keyframe {
+    /// snapshot of the player after `at` steps, `before` is the history of the keyframe
+    /// before
+    fn new(at: usize, j: &Joystick, before: &[(Position, Option<Direction>)]) -> Keyframe {
+        let keep = before
+            .iter()
+            .zip(&j.history)
+            .take_while(|(a, b)| a == b)
+            .count();
+        Keyframe {
+            at,
+            pos: j.pos,
+            keep,
+            added: j.history[keep..].to_vec(),
+        }
+    }
+
+    /// the history of the snapshot from the one of the keyframe before
+    fn history(
+        &self,
+        mut before: Vec<(Position, Option<Direction>)>,
+    ) -> Vec<(Position, Option<Direction>)> {
+        before.truncate(self.keep);
+        before.extend_from_slice(&self.added);
+        before
+    }
+}
+
+/// Recorded run: the maze, every step taken with its time, and a keyframe every
+/// `KEYFRAME_EVERY` steps. Stored as zstd compressed RON.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    /// format the replay was recorded in, 1 for the ones from before it was kept
+    #[serde(default = "Replay::first_version")]
+    version: u32,
+    /// fingerprint of the maze when recorded, None before version 2
+    #[serde(default)]
+    fingerprint: Option<u64>,
+    /// milliseconds per tick of the step times
+    #[serde(default = "Replay::tick_ms")]
+    tick_ms: u32,
+    maze: Maze,
+    /// player state the recording started from, None for the entrance as before version 3
+    #[serde(default)]
+    start: Option<Keyframe>,
+    steps: Vec<Step>,
+    /// ticks from the start of the recording to every step, empty before version 2
+    #[serde(default)]
+    times: Vec<u32>,
+    keyframes: Vec<Keyframe>,
+    /// history at the last keyframe recorded, the base of the next one
+    #[serde(skip)]
+    last: Vec<(Position, Option<Direction>)>,
+}
+
+impl Replay {
+    fn first_version() -> u32 {
+        1
+    }
+
+    fn tick_ms() -> u32 {
+        TICK_MS
+    }
+
+    /// recording of a run from the entrance of the maze
+    pub fn new(maze: &Maze) -> Replay {
+        Replay::starting_at(&maze.joystick())
+    }
+
+    /// Recording of a run from where the player stands, with the moves that got it there,
+    /// like a restored game.
+    pub fn starting_at(j: &Joystick) -> Replay {
+        let start = Keyframe::new(0, j, &[]);
+        Replay {
+            version: REPLAY_VERSION,
+            fingerprint: Some(j.maze.fingerprint()),
+            tick_ms: TICK_MS,
+            maze: j.maze.clone(),
+            start: Some(start),
+            steps: Vec::new(),
+            times: Vec::new(),
+            keyframes: Vec::new(),
+            last: Vec::new(),
+        }
+    }
+
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Time of the step from the start of the recording, to the tick. None for replays
+    /// recorded without times.
+    pub fn time_at(&self, step: usize) -> Option<Duration> {
+        self.times
+            .get(step)
+            .map(|t| Duration::from_millis(*t as u64 * self.tick_ms as u64))
+    }
+
+    /// Append a step made `at` from the start of the recording, `j` is the player state
+    /// once the step is applied.
+    pub fn record(&mut self, step: Step, j: &Joystick, at: Duration) {
+        self.steps.push(step);
+        self.times
+            .push((at.as_millis() / self.tick_ms as u128) as u32);
+        if self.steps.len() == (self.keyframes.len() + 1) * KEYFRAME_EVERY {
+            self.keyframes
+                .push(Keyframe::new(self.steps.len(), j, &self.last));
+            self.last = j.history.clone();
+        }
+    }
+
+    /// player state the recording started from
+    fn start(&self) -> Joystick<'_> {
+        let mut j = self.maze.joystick();
+        if let Some(k) = &self.start {
+            j.pos = k.pos;
+            j.history = k.history(Vec::new());
+        }
+        j
+    }
+
+    /// step count of the last keyframe at or before the given step, 0 for the start
+    pub fn keyframe_before(&self, step: usize) -> usize {
+        self.keyframes
+            .iter()
+            .rev()
+            .map(|k| k.at)
+            .find(|at| *at <= step)
+            .unwrap_or(0)
+    }
+
+    /// Check the replay plays back as it was recorded: in a format this version knows, on
+    /// the maze it was recorded on, and reaching every keyframe by playing the steps.
+    pub fn verify(&self) -> Result<(), ReplayError> {
+        if self.version > REPLAY_VERSION {
+            return Err(ReplayError::Version {
+                found: self.version,
+                supported: REPLAY_VERSION,
+            });
+        }
+        match self.fingerprint {
+            Some(expected) if expected != self.maze.fingerprint() => {
+                return Err(ReplayError::Fingerprint {
+                    expected,
+                    found: self.maze.fingerprint(),
+                })
+            }
+            _ => (),
+        }
+        let mut j = self.start();
+        let mut from = 0;
+        let mut history = Vec::new();
+        for k in &self.keyframes {
+            apply(&mut j, &self.steps[from..k.at.min(self.steps.len())]);
+            from = k.at;
+            history = k.history(history);
+            if j.pos != k.pos || j.history != history {
+                return Err(ReplayError::Desync(k.at));
+            }
+        }
+        Ok(())
+    }
+
+    /// Player state after the given number of steps, replayed from the closest keyframe.
+    pub fn joystick_at(&self, step: usize) -> Joystick<'_> {
+        let step = step.min(self.steps.len());
+        let mut j = self.start();
+        let from = self.keyframe_before(step);
+        let mut history = Vec::new();
+        for k in self.keyframes.iter().take_while(|k| k.at <= from) {
+            history = k.history(history);
+            j.pos = k.pos;
+        }
+        if from > 0 {
+            j.history = history;
+        }
+        apply(&mut j, &self.steps[from..step]);
+        j
+    }
+
+    /// Positions of the player at the start and after every step, undone moves included.
+    pub fn positions(&self) -> Vec<Position> {
+        let mut j = self.start();
+        let mut out = vec![j.pos];
+        for step in self.steps.chunks(1) {
+            apply(&mut j, step);
+            out.push(j.pos);
+        }
+        out
+    }
+
+    /// write the compressed replay
+    pub fn write<W: Write>(&self, out: W) -> Result<(), ReplayError> {
+        let mut enc = zstd::Encoder::new(out, LEVEL)?;
+        ron::ser::to_writer(&mut enc, self)?;
+        enc.finish()?;
+        Ok(())
+    }
+
+    /// read a compressed replay, failing on ones that wouldn't play back as recorded
+    pub fn read<R: Read>(input: R) -> Result<Replay, ReplayError> {
+        let replay: Replay = ron::de::from_reader(zstd::Decoder::new(input)?)?;
+        replay.verify()?;
+        Ok(replay)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.write(File::create(path)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        Replay::read(File::open(path)?)
+    }
+}
+
+/// play the steps on the player
+fn apply(j: &mut Joystick, steps: &[Step]) {
+    for s in steps {
+        match s {
+            Step::Move(d) => {
+                j.mv(d);
+            }
+            Step::Reset => {
+                j.reset();
+            }
+            Step::Undo => {
+                j.undo();
+            }
+        }
+    }
+}
+
+/// Playback position and controls of a replay.
+#[derive(Clone, Debug)]
+pub struct Playback {
+    step: usize,
+    len: usize,
+    speed: usize,
+    paused: bool,
+    /// time of every step from the start of the recording, empty when it has none
+    times: Vec<Duration>,
+    /// time of the recording played so far
+    clock: Duration,
+    /// fraction of a step carried over to the next tick, without times
+    carry: f32,
+}
+
+impl Playback {
+    pub fn new(replay: &Replay) -> Playback {
+        Playback {
+            step: 0,
+            len: replay.len(),
+            speed: 1,
+            paused: false,
+            times: (0..replay.len()).map_while(|i| replay.time_at(i)).collect(),
+            clock: Duration::default(),
+            carry: 0.0,
+        }
+    }
+
+    /// number of steps played
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    pub fn speed(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.step == self.len
+    }
+
+    /// Advance by the time elapsed since the last tick, playing the steps at the times
+    /// they were recorded. Returns true when the step changed.
+    pub fn tick(&mut self, elapsed: Duration) -> bool {
+        if self.paused || self.is_done() {
+            return false;
+        }
+        let before = self.step;
+        if self.times.len() == self.len {
+            self.clock += elapsed.mul_f32(self.speed());
+            while self.step < self.len && self.times[self.step] <= self.clock {
+                self.step += 1;
+            }
+        } else {
+            self.carry += elapsed.as_secs_f32() * STEPS_PER_SEC * self.speed();
+            let steps = self.carry as usize;
+            self.carry -= steps as f32;
+            self.seek(self.step + steps);
+        }
+        self.step != before
+    }
+
+    /// skip ahead to the next keyframe interval
+    pub fn fast_forward(&mut self) {
+        self.seek((self.step / KEYFRAME_EVERY + 1) * KEYFRAME_EVERY);
+    }
+
+    /// go back to the keyframe before the current step
+    pub fn rewind(&mut self, replay: &Replay) {
+        self.seek(replay.keyframe_before(self.step.saturating_sub(1)));
+    }
+
+    /// jump to a percentage of the replay
+    pub fn jump(&mut self, percent: usize) {
+        self.seek(self.len * percent.min(100) / 100);
+    }
+
+    fn seek(&mut self, step: usize) {
+        self.step = step.min(self.len);
+        self.carry = 0.0;
+        // the clock goes on from the last step played
+        self.clock = match self.step {
+            0 => Duration::default(),
+            n => self.times.get(n - 1).copied().unwrap_or_default(),
+        };
+    }
+
+    /// Progress bar of the given width followed by the step count and speed.
+    pub fn progress_bar(&self, width: usize) -> String {
+        let filled = (width * self.step).checked_div(self.len).unwrap_or(width);
+        format!(
+            "[{}{}] {}/{} {}x{}",
+            "=".repeat(filled),
+            " ".repeat(width - filled),
+            self.step,
+            self.len,
+            self.speed(),
+            if self.paused { " paused" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Direction::{Down, Left, Right, Up};
+    use crate::maze::Opts;
+
+    #[test]
+    fn test_replay() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut replay = Replay::new(&m);
+        let mut j = m.joystick();
+        let route = [Right, Right, Down, Left, Up];
+        for i in 0..KEYFRAME_EVERY * 2 + 3 {
+            let step = if i % 40 == 39 {
+                Step::Reset
+            } else if i % 13 == 12 {
+                Step::Undo
+            } else {
+                Step::Move(route[i % route.len()])
+            };
+            match step {
+                Step::Move(d) => {
+                    j.mv(&d);
+                }
+                Step::Reset => {
+                    j.reset();
+                }
+                Step::Undo => {
+                    j.undo();
+                }
+            }
+            replay.record(step, &j, Duration::from_millis(i as u64 * 250 + 4));
+        }
+        assert_eq!(replay.len(), KEYFRAME_EVERY * 2 + 3);
+        assert_eq!(replay.keyframe_before(KEYFRAME_EVERY - 1), 0);
+        assert_eq!(
+            replay.keyframe_before(KEYFRAME_EVERY * 2 + 1),
+            KEYFRAME_EVERY * 2
+        );
+
+        let mut buf = Vec::new();
+        replay.write(&mut buf).unwrap();
+        let restored = Replay::read(&buf[..]).unwrap();
+        assert_eq!(restored.steps(), replay.steps());
+        let end = restored.joystick_at(restored.len());
+        assert_eq!(end.pos, j.pos);
+        assert_eq!(end.history, j.history);
+        assert_eq!(restored.joystick_at(1).pos, Position { x: 1, y: 0 });
+        // the second keyframe only holds the moves made since the reset before it
+        let second = &restored.keyframes[1];
+        assert!(second.keep > 0 && second.added.len() < KEYFRAME_EVERY);
+        assert_eq!(
+            second.keep + second.added.len(),
+            restored.joystick_at(KEYFRAME_EVERY * 2).history.len()
+        );
+        // times are kept to the tick
+        assert_eq!(restored.time_at(2), Some(Duration::from_millis(500)));
+
+        // steps play at the times they were made, at 0, 250, 500ms...
+        let mut playback = Playback::new(&restored);
+        assert!(playback.tick(Duration::from_millis(500)));
+        assert_eq!(playback.step(), 3);
+        assert!(!playback.tick(Duration::from_millis(3)));
+        playback.faster();
+        playback.tick(Duration::from_millis(500));
+        assert_eq!(playback.step(), 7);
+        playback.fast_forward();
+        assert_eq!(playback.step(), KEYFRAME_EVERY);
+        playback.rewind(&restored);
+        assert_eq!(playback.step(), 0);
+        playback.jump(100);
+        assert!(playback.is_done());
+        assert!(!playback.tick(Duration::from_secs(1)));
+        playback.jump(50);
+        assert_eq!(playback.progress_bar(4), "[=   ] 65/131 2x");
+        playback.tick(Duration::from_millis(125));
+        assert_eq!(playback.step(), 66);
+
+        // without times the steps play at a steady pace
+        let mut untimed = restored.clone();
+        untimed.times.clear();
+        let mut playback = Playback::new(&untimed);
+        playback.tick(Duration::from_millis(500));
+        assert_eq!(playback.step(), 4);
+    }
+
+    #[test]
+    fn test_replay_verify() {
+        let m = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let mut replay = Replay::new(&m);
+        let mut j = m.joystick();
+        for i in 0..KEYFRAME_EVERY {
+            let d = if i % 2 == 0 { Right } else { Left };
+            j.mv(&d);
+            replay.record(Step::Move(d), &j, Duration::from_secs(i as u64));
+        }
+        assert!(replay.verify().is_ok());
+
+        let mut newer = replay.clone();
+        newer.version = REPLAY_VERSION + 1;
+        assert!(matches!(newer.verify(), Err(ReplayError::Version { .. })));
+        let mut other = replay.clone();
+        other.maze = Maze::create(3, 2, vec![(0, 3)]).unwrap();
+        assert!(matches!(
+            other.verify(),
+            Err(ReplayError::Fingerprint { .. })
+        ));
+        let mut desynced = replay.clone();
+        desynced.steps[0] = Step::Move(Down);
+        assert!(matches!(
+            desynced.verify(),
+            Err(ReplayError::Desync(KEYFRAME_EVERY))
+        ));
+
+        // replays from before the format was versioned still play
+        let old = ron::ser::to_string(&replay)
+            .unwrap()
+            .replace(&format!("version:{},", REPLAY_VERSION), "")
+            .replace("keep:0,added:", "history:")
+            .replace(&format!("fingerprint:Some({}),", m.fingerprint()), "");
+        let old: Replay = ron::de::from_str(&old).unwrap();
+        assert_eq!(old.version, 1);
+        assert!(old.verify().is_ok());
+    }
+
+    #[test]
+    fn test_replay_resumed() {
+        // junctions and loops, so undo and the way back differ from the way in
+        let m = Maze::generate(
+            8,
+            6,
+            &Opts {
+                seed: Some(3),
+                braid: 1.0,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(m.dead_ends().is_empty());
+        let turns = [Right, Down, Down, Left, Up, Right, Right, Down];
+        let mut j = m.joystick();
+        for d in turns.iter().cycle().take(40) {
+            j.mv(d);
+        }
+        j.undo();
+        // the game is saved here and resumed later on
+        let (start, moved) = (j.pos, j.history.len());
+        assert!(moved > 10 && start != m.entrance());
+        let mut replay = Replay::starting_at(&j);
+        for (i, d) in turns
+            .iter()
+            .rev()
+            .cycle()
+            .take(KEYFRAME_EVERY * 2 + 5)
+            .enumerate()
+        {
+            let step = if i % 9 == 8 {
+                Step::Undo
+            } else {
+                Step::Move(*d)
+            };
+            match step {
+                Step::Move(d) => {
+                    j.mv(&d);
+                }
+                _ => {
+                    j.undo();
+                }
+            }
+            replay.record(step, &j, Duration::from_millis(i as u64 * 100));
+        }
+
+        let mut buf = Vec::new();
+        replay.write(&mut buf).unwrap();
+        let restored = Replay::read(&buf[..]).unwrap();
+        assert_eq!(restored.positions()[0], start);
+        assert_eq!(restored.joystick_at(0).history.len(), moved);
+        let end = restored.joystick_at(restored.len());
+        assert_eq!((end.pos, &end.history), (j.pos, &j.history));
+        let middle = restored.joystick_at(KEYFRAME_EVERY + 3);
+        assert!(middle.history.len() > moved);
+
+        // played from the entrance the same steps don't reach the keyframes
+        let mut entrance = replay.clone();
+        entrance.start = None;
+        assert!(matches!(
+            entrance.verify(),
+            Err(ReplayError::Desync(KEYFRAME_EVERY))
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::maze::MazeError;
+
+#[derive(Error, Debug)]
+pub enum SaveError {
+    #[error("invalid save format, expected ron or json")]
+    FormatParseError,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("saved by a newer version of the game, save version {0}")]
+    NewerVersion(u32),
+    #[error("the save has no maze in its {0:?} encoding")]
+    MissingMaze(MazeEncoding),
+    #[error("the maze was saved as a seed of generator {0}, it generates another maze now")]
+    Generator(u32),
+    #[error(transparent)]
+    Maze(#[from] MazeError),
+}
+
+/// How the maze is written in a save, the smallest one is picked when saving.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MazeEncoding {
+    /// every wall as a pair of cells, saves from before encodings were picked
+    Walls,
+    /// walls packed into bits, see `PackedMaze`
+    Packed,
+    /// the seed and options the maze is generated from again, see `MazeSeed`
+    Seed,
+}
+
+/// Encoding of save files.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SaveFormat {
+    Ron,
+    /// for web frontends and other tools
+    Json,
+}
+
+impl FromStr for SaveFormat {
+    type Err = SaveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ron" => Ok(SaveFormat::Ron),
+            "json" => Ok(SaveFormat::Json),
+            _ => Err(SaveError::FormatParseError),
+        }
+    }
+}
+
+impl SaveFormat {
+    /// format of the file going by its extension, RON unless it ends in .json
+    pub fn of<P: AsRef<Path>>(path: P) -> SaveFormat {
+        match path.as_ref().extension() {
+            Some(ext) if ext == "json" => SaveFormat::Json,
+            _ => SaveFormat::Ron,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SaveFormat::Ron => "ron",
+            SaveFormat::Json => "json",
+        }
+    }
+
+    pub fn write<W: Write, T: Serialize>(&self, out: W, value: &T) -> Result<(), SaveError> {
+        match self {
+            SaveFormat::Ron => ron::ser::to_writer(out, value)?,
+            SaveFormat::Json => serde_json::to_writer(out, value)?,
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read, T: DeserializeOwned>(&self, input: R) -> Result<T, SaveError> {
+        Ok(match self {
+            SaveFormat::Ron => ron::de::from_reader(input)?,
+            SaveFormat::Json => serde_json::from_reader(input)?,
+        })
+    }
+}
+
+/// Where the game in progress is kept when quitting, `rusty-maze/last.ron` in the data
+/// directory of the platform, e.g. `$XDG_DATA_HOME` on Linux. None when there is no such
+/// directory.
+pub fn last_game() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("last.ron"))
+}
+
+/// Write the value to the file, in the given format or the one of the file extension.
+pub fn save<P: AsRef<Path>, T: Serialize>(
+    path: P,
+    value: &T,
+    format: Option<SaveFormat>,
+) -> Result<(), SaveError> {
+    let format = format.unwrap_or_else(|| SaveFormat::of(&path));
+    if let Some(dir) = path.as_ref().parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut out = BufWriter::new(File::create(path)?);
+    format.write(&mut out, value)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Read a value written by `save`.
+pub fn load<P: AsRef<Path>, T: DeserializeOwned>(
+    path: P,
+    format: Option<SaveFormat>,
+) -> Result<T, SaveError> {
+    let format = format.unwrap_or_else(|| SaveFormat::of(&path));
+    format.read(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(SaveFormat::of("maze.json"), SaveFormat::Json);
+        assert_eq!(SaveFormat::of("maze.ron"), SaveFormat::Ron);
+        assert_eq!(SaveFormat::of("maze"), SaveFormat::Ron);
+        assert!("yaml".parse::<SaveFormat>().is_err());
+
+        let walls = vec![(0u16, 3u16), (1, 4)];
+        for format in [SaveFormat::Ron, SaveFormat::Json].iter() {
+            let mut out = Vec::new();
+            format.write(&mut out, &walls).unwrap();
+            let read: Vec<(u16, u16)> = format.read(&out[..]).unwrap();
+            assert_eq!(read, walls);
+        }
+        let mut json = Vec::new();
+        SaveFormat::Json.write(&mut json, &walls).unwrap();
+        assert_eq!(json, b"[[0,3],[1,4]]");
+
+        // the save directory is created on the first save
+        let dir = std::env::temp_dir().join(format!("rusty_maze_saves_{}", std::process::id()));
+        let path = dir.join("slots").join("slot1.json");
+        save(&path, &walls, None).unwrap();
+        let read: Vec<(u16, u16)> = load(&path, None).unwrap();
+        assert_eq!(read, walls);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
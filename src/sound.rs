@@ -0,0 +1,87 @@
+#[cfg(feature = "sound")]
+use std::time::Duration;
+
+#[cfg(feature = "sound")]
+use rodio::{source::SineWave, OutputStream, OutputStreamHandle, Sink, Source};
+
+/// Game event with a sound.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Cue {
+    Step,
+    Bump,
+    /// checkpoint or treasure
+    Pickup,
+    Victory,
+}
+
+impl Cue {
+    /// tones of the cue as frequency in Hz and length in milliseconds
+    pub fn notes(&self) -> &'static [(f32, u64)] {
+        match self {
+            Cue::Step => &[(660.0, 25)],
+            Cue::Bump => &[(110.0, 70)],
+            Cue::Pickup => &[(880.0, 60), (1320.0, 90)],
+            Cue::Victory => &[(523.0, 100), (659.0, 100), (784.0, 100), (1047.0, 250)],
+        }
+    }
+}
+
+/// Audio output for the cues, only built with the `sound` feature.
+#[cfg(feature = "sound")]
+pub struct Sound {
+    // the stream stops playing once dropped
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    volume: f32,
+}
+
+#[cfg(feature = "sound")]
+impl Sound {
+    /// Open the default audio device, None if there is none.
+    pub fn new(volume: f32) -> Option<Sound> {
+        let (stream, handle) = OutputStream::try_default().ok()?;
+        Some(Sound {
+            _stream: stream,
+            handle,
+            volume,
+        })
+    }
+
+    /// play the cue without waiting for it to end
+    pub fn play(&self, cue: Cue) {
+        if let Ok(sink) = Sink::try_new(&self.handle) {
+            sink.set_volume(self.volume);
+            for (freq, millis) in cue.notes() {
+                sink.append(SineWave::new(*freq).take_duration(Duration::from_millis(*millis)));
+            }
+            sink.detach();
+        }
+    }
+}
+
+/// Silent stand in when built without the `sound` feature.
+#[cfg(not(feature = "sound"))]
+pub struct Sound;
+
+#[cfg(not(feature = "sound"))]
+impl Sound {
+    pub fn new(_volume: f32) -> Option<Sound> {
+        None
+    }
+
+    pub fn play(&self, _cue: Cue) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cues() {
+        // steps come with every move, they must not pile up
+        let length = |c: Cue| c.notes().iter().map(|n| n.1).sum::<u64>();
+        assert!(length(Cue::Step) < 50);
+        assert!(length(Cue::Victory) < 1000);
+        assert!(Cue::Bump.notes()[0].0 < Cue::Step.notes()[0].0);
+    }
+}
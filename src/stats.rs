@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::records::Record;
+
+#[derive(Error, Debug)]
+pub enum StatsError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+}
+
+/// Where the statistics are kept, `rusty-maze/stats.ron` in the data directory of the
+/// platform. None when there is no such directory.
+pub fn stats_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("stats.ron"))
+}
+
+/// Games played over every session.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// games with at least one move
+    pub played: usize,
+    pub wins: usize,
+    /// moves of every game played, won or not
+    pub moves: usize,
+    /// games won in a row, up to the last one begun
+    pub streak: usize,
+    /// best time in milliseconds by maze width and height
+    pub best: BTreeMap<(u16, u16), u64>,
+    /// best score of the scored runs by maze width and height
+    #[serde(default)]
+    pub best_score: BTreeMap<(u16, u16), usize>,
+    /// the last game begun wasn't won, the next one breaks the streak
+    unfinished: bool,
+}
+
+impl Stats {
+    /// Load the statistics, a missing file yields none.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Stats, StatsError> {
+        match File::open(path) {
+            Ok(f) => Ok(ron::de::from_reader(BufReader::new(f))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Stats::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Load the statistics, a damaged file is moved aside to `stats.ron.corrupt` and the
+    /// counts start over rather than the game failing.
+    pub fn load_or_recover<P: AsRef<Path>>(path: P) -> Stats {
+        Stats::load(&path).unwrap_or_else(|_| {
+            let _ = std::fs::rename(&path, path.as_ref().with_extension("ron.corrupt"));
+            Stats::default()
+        })
+    }
+
+    /// Write the statistics next to the file, then move them over it so a crash while
+    /// writing leaves the previous ones.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StatsError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("ron.tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        ron::ser::to_writer(&mut out, self)?;
+        out.flush()?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// count a game on its first move, leaving the one before unwon breaks the streak
+    pub fn begin(&mut self) {
+        if self.unfinished {
+            self.streak = 0;
+        }
+        self.unfinished = true;
+        self.played += 1;
+    }
+
+    /// count the run of a won game on a maze of the given size
+    pub fn win(&mut self, width: u16, height: u16, run: Record) {
+        self.unfinished = false;
+        self.wins += 1;
+        self.streak += 1;
+        let best = self.best.entry((width, height)).or_insert(run.millis);
+        *best = run.millis.min(*best);
+        if let Some(score) = run.score {
+            let best = self.best_score.entry((width, height)).or_insert(score);
+            *best = score.max(*best);
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let won = (self.wins * 100).checked_div(self.played).unwrap_or(0);
+        writeln!(f, "games played: {}", self.played)?;
+        writeln!(f, "wins: {} ({}%)", self.wins, won)?;
+        writeln!(f, "total moves: {}", self.moves)?;
+        write!(f, "current streak: {}", self.streak)?;
+        for ((w, h), millis) in &self.best {
+            write!(f, "\nbest {}x{}: {:.1}s", w, h, *millis as f32 / 1000.0)?;
+        }
+        for ((w, h), score) in &self.best_score {
+            write!(f, "\nbest score {}x{}: {}", w, h, score)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let mut stats = Stats::default();
+        stats.begin();
+        stats.moves += 20;
+        stats.win(
+            20,
+            10,
+            Record {
+                moves: 20,
+                millis: 9000,
+                score: None,
+            },
+        );
+        stats.begin();
+        stats.win(
+            20,
+            10,
+            Record {
+                moves: 24,
+                millis: 7500,
+                score: None,
+            },
+        );
+        assert_eq!(stats.streak, 2);
+        // the third game is left unwon, the fourth starts a new streak
+        stats.begin();
+        stats.begin();
+        stats.win(
+            8,
+            8,
+            Record {
+                moves: 12,
+                millis: 3000,
+                score: Some(1500),
+            },
+        );
+        assert_eq!((stats.played, stats.wins, stats.streak), (4, 3, 1));
+        assert_eq!(stats.best[&(20, 10)], 7500);
+        assert_eq!(
+            stats.to_string(),
+            "games played: 4\nwins: 3 (75%)\ntotal moves: 20\ncurrent streak: 1\n\
+             best 8x8: 3.0s\nbest 20x10: 7.5s\nbest score 8x8: 1500"
+        );
+
+        let dir = std::env::temp_dir().join(format!("rusty_maze_stats_{}", std::process::id()));
+        let path = dir.join("stats.ron");
+        stats.save(&path).unwrap();
+        assert_eq!(Stats::load_or_recover(&path), stats);
+        std::fs::write(&path, "(played: 4, wins:").unwrap();
+        assert!(Stats::load(&path).is_err());
+        assert_eq!(Stats::load_or_recover(&path), Stats::default());
+        assert!(dir.join("stats.ron.corrupt").exists());
+        assert!(!path.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+use thiserror::Error;
+
+use crate::maze::Direction::{Down, Right};
+use crate::maze::{Maze, Position};
+
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum StegoError {
+    #[error("the message is {0} bytes but the maze only holds {1}")]
+    TooLong(usize, usize),
+    #[error("no message hidden in the maze")]
+    NoMessage,
+}
+
+/// Bytes of message a maze of the given size can hide. Every cell off the last row and
+/// column holds a bit, the first byte holds the length.
+pub fn capacity(width: u16, height: u16) -> usize {
+    let bits = width.saturating_sub(1) as usize * height.saturating_sub(1) as usize;
+    (bits / 8).saturating_sub(1).min(u8::MAX as usize)
+}
+
+/// cells holding a bit, in order
+fn bit_cells(width: u16, height: u16) -> impl Iterator<Item = Position> {
+    (0..height.saturating_sub(1))
+        .flat_map(move |y| (0..width.saturating_sub(1)).map(move |x| Position { x, y }))
+}
+
+/// Generate a maze hiding the message. Every cell carves the wall to its right or below
+/// it, like the binary tree algorithm, so any choice of bits gives a perfect maze: cells
+/// holding a 1 open to the right and the ones holding a 0 downwards. Bits past the
+/// message are random.
+pub fn hide(width: u16, height: u16, message: &str) -> Result<Maze, StegoError> {
+    let max = capacity(width, height);
+    if message.len() > max {
+        return Err(StegoError::TooLong(message.len(), max));
+    }
+    let mut bytes = vec![message.len() as u8];
+    bytes.extend(message.bytes());
+    let mut bits = bytes
+        .iter()
+        .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1 == 1));
+    let mut rng = rand::thread_rng();
+
+    let cell = |p: Position| p.y * width + p.x;
+    let mut open = HashSet::new();
+    for p in bit_cells(width, height) {
+        let right = bits.next().unwrap_or_else(|| rng.gen());
+        if right {
+            open.insert((cell(p), cell(p) + 1));
+        } else {
+            open.insert((cell(p), cell(p) + width));
+        }
+    }
+    // the last row and column have a single way out
+    for x in 0..width.saturating_sub(1) {
+        let c = cell(Position { x, y: height - 1 });
+        open.insert((c, c + 1));
+    }
+    for y in 0..height.saturating_sub(1) {
+        let c = cell(Position { x: width - 1, y });
+        open.insert((c, c + width));
+    }
+
+    let mut walls = Vec::new();
+    for c in 0..width * height {
+        if c % width != width - 1 {
+            walls.push((c, c + 1));
+        }
+        if c + width < width * height {
+            walls.push((c, c + width));
+        }
+    }
+    walls.retain(|w| !open.contains(w));
+    Ok(Maze::create(width, height, walls).expect("walls inside the maze"))
+}
+
+/// Read the message hidden by `hide`.
+pub fn reveal(maze: &Maze) -> Result<String, StegoError> {
+    let mut bits = Vec::new();
+    for p in bit_cells(maze.width, maze.height) {
+        let right = maze.move_pos(p, &Right).is_some();
+        let down = maze.move_pos(p, &Down).is_some();
+        // mazes not made by `hide` open both ways or neither somewhere
+        if right == down {
+            return Err(StegoError::NoMessage);
+        }
+        bits.push(right);
+    }
+    let bytes: Vec<u8> = bits
+        .chunks_exact(8)
+        .map(|c| c.iter().fold(0, |b, bit| (b << 1) | *bit as u8))
+        .collect();
+    let len = *bytes.first().ok_or(StegoError::NoMessage)? as usize;
+    if len > capacity(maze.width, maze.height) {
+        return Err(StegoError::NoMessage);
+    }
+    String::from_utf8(bytes[1..=len].to_vec()).map_err(|_| StegoError::NoMessage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hide() {
+        assert_eq!(capacity(9, 9), 7);
+        let m = hide(20, 10, "meet at the exit").unwrap();
+        assert!(m.distance_map(m.entrance()).iter().all(Option::is_some));
+        assert_eq!(reveal(&m), Ok("meet at the exit".to_string()));
+        assert_eq!(
+            hide(20, 10, "ok").map(|m| reveal(&m)),
+            Ok(Ok("ok".to_string()))
+        );
+        assert_eq!(
+            hide(9, 9, "too long").unwrap_err(),
+            StegoError::TooLong(8, 7)
+        );
+
+        // a maze with loops
+        let open = Maze::create(3, 3, vec![]).unwrap();
+        assert_eq!(reveal(&open), Err(StegoError::NoMessage));
+    }
+}
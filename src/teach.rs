@@ -0,0 +1,135 @@
+use termion::color;
+
+use crate::disjset::DisjSet;
+use crate::disjset::Roots::DisJoint;
+use crate::maze::{Generation, GenerationEvent, Maze, MazeUI, Opts};
+
+/// Two sets of cells merged by knocking down the wall between them.
+#[derive(Clone, Debug)]
+pub struct Union {
+    pub wall: (u16, u16),
+    /// cells of the set on each side of the wall
+    pub sets: (Vec<u16>, Vec<u16>),
+    /// sets left once merged
+    pub distinct: usize,
+}
+
+/// Maze generation stepped one union at a time, to show how the disjoint set of cells
+/// joins them into a maze.
+pub struct Lesson {
+    generation: Generation,
+    /// the cells as seen by the generation, rebuilt from the walls it knocks down
+    cells: DisjSet,
+    walls: Vec<(u16, u16)>,
+    /// the maze before the generation, every wall standing
+    closed: Maze,
+    connected: bool,
+}
+
+impl Lesson {
+    pub fn new(width: u16, height: u16, opts: &Opts) -> Lesson {
+        let closed = Maze::closed(width, height, opts);
+        Lesson {
+            generation: Maze::generate_iter(width, height, opts),
+            cells: DisjSet::new((closed.width * closed.height) as usize),
+            walls: closed.walls().to_vec(),
+            closed,
+            connected: false,
+        }
+    }
+
+    /// Run the generation up to its next union, None once it's done. Walls knocked down
+    /// inside a set, the loops of easy mazes, are passed over.
+    pub fn next_union(&mut self) -> Option<Union> {
+        for event in &mut self.generation {
+            let w = match event {
+                GenerationEvent::WallRemoved(w) => w,
+                GenerationEvent::WallKept(_) => continue,
+                GenerationEvent::Connected => {
+                    self.connected = true;
+                    continue;
+                }
+                GenerationEvent::Done(_) => return None,
+            };
+            self.walls.retain(|&x| x != w && x != (w.1, w.0));
+            if let DisJoint(r1, r2) = self.cells.find_roots(w.0 as usize, w.1 as usize) {
+                let sets = (self.members(r1), self.members(r2));
+                self.cells.union(r1, r2);
+                return Some(Union {
+                    wall: w,
+                    sets,
+                    distinct: self.cells.distinct_sets(),
+                });
+            }
+        }
+        None
+    }
+
+    /// cells of the set with the given root
+    fn members(&self, root: usize) -> Vec<u16> {
+        (0..self.cells.len())
+            .filter(|&c| self.cells.lookup(c) == root)
+            .map(|c| c as u16)
+            .collect()
+    }
+
+    /// sets of cells not joined yet
+    pub fn distinct_sets(&self) -> usize {
+        self.cells.distinct_sets()
+    }
+
+    /// whether the entrance and the exit are in the same set
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// the maze as generated so far
+    pub fn maze(&self) -> Maze {
+        self.closed.with_walls(self.walls.clone())
+    }
+
+    /// The maze so far with the two sets of the union in blue and red.
+    pub fn render(&self, ui: &MazeUI, union: Option<&Union>) -> Vec<String> {
+        let mut colors = vec![None; (self.closed.width * self.closed.height) as usize];
+        if let Some(u) = union {
+            for &c in u.sets.0.iter() {
+                colors[c as usize] = Some(color::Blue.bg_str());
+            }
+            for &c in u.sets.1.iter() {
+                colors[c as usize] = Some(color::Red.bg_str());
+            }
+        }
+        ui.paint_cells(ui.draw(), &colors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lesson() {
+        let opts = Opts {
+            seed: Some(5),
+            ..Default::default()
+        };
+        let mut lesson = Lesson::new(6, 4, &opts);
+        assert_eq!(lesson.distinct_sets(), 24);
+        let first = lesson.next_union().unwrap();
+        assert_eq!(first.sets.0.len() + first.sets.1.len(), 2);
+        assert_eq!(first.distinct, 23);
+
+        let mut unions = 1;
+        while let Some(u) = lesson.next_union() {
+            unions += 1;
+            assert_eq!(u.distinct, 24 - unions);
+        }
+        // a perfect maze joins every cell with one union less than there are cells
+        assert_eq!(unions, 23);
+        assert!(lesson.is_connected());
+        assert_eq!(
+            lesson.maze().fingerprint(),
+            Maze::generate(6, 4, &opts).unwrap().fingerprint()
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use chrono::Timelike;
+
+/// How long the terminal has to answer the background query, over a slow link too.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Brightness of the terminal background, to pick a theme that reads well on it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Background {
+    Light,
+    Dark,
+}
+
+impl Background {
+    /// Ask the terminal for its background color. Terminals that know the OSC 11 query
+    /// answer it with the color, the cursor position report asked right after is answered
+    /// by most others. The ones answering neither within `QUERY_TIMEOUT` get None. Needs
+    /// raw mode and must run before anything else reads the terminal.
+    pub fn query<W: Write>(stdout: &mut W) -> Option<Background> {
+        let mut tty = File::open("/dev/tty").ok()?;
+        write!(stdout, "\x1b]11;?\x07\x1b[6n").ok()?;
+        stdout.flush().ok()?;
+        let (tx, rx) = mpsc::channel();
+        // reads block, a terminal that never answers leaves the thread waiting until the
+        // first key pressed
+        std::thread::spawn(move || {
+            let mut reply = Vec::new();
+            let mut byte = [0];
+            // the cursor position report ends with R, the color comes before it
+            while reply.last() != Some(&b'R') && reply.len() < 256 {
+                match tty.read(&mut byte) {
+                    Ok(1) => reply.push(byte[0]),
+                    _ => break,
+                }
+            }
+            let _ = tx.send(reply);
+        });
+        let reply = rx.recv_timeout(QUERY_TIMEOUT).ok()?;
+        parse_color_reply(&reply)
+    }
+
+    /// The background from `COLORFGBG`, set by some terminals to the foreground and
+    /// background colors like `15;0`.
+    pub fn from_env() -> Option<Background> {
+        let colors = std::env::var("COLORFGBG").ok()?;
+        match colors.rsplit(';').next()?.parse::<u8>().ok()? {
+            7 | 9..=15 => Some(Background::Light),
+            _ => Some(Background::Dark),
+        }
+    }
+
+    /// light from 7 in the morning to 7 in the evening, local time
+    pub fn at_hour(hour: u32) -> Background {
+        if (7..19).contains(&hour) {
+            Background::Light
+        } else {
+            Background::Dark
+        }
+    }
+
+    /// `at_hour` for the local time now
+    pub fn now() -> Background {
+        Background::at_hour(chrono::Local::now().hour())
+    }
+}
+
+/// Background of an OSC 11 reply like `ESC ] 11 ; rgb:ffff/ffff/ffff BEL`, None when the
+/// reply holds no color.
+fn parse_color_reply(reply: &[u8]) -> Option<Background> {
+    let reply = String::from_utf8_lossy(reply);
+    let start = reply.find("rgb:")? + 4;
+    let channels: Vec<f32> = reply[start..]
+        .split(['/', '\x07', '\x1b'])
+        .take(3)
+        .map(|hex| {
+            let max = 16f32.powi(hex.len() as i32) - 1.0;
+            u32::from_str_radix(hex, 16).ok().map(|v| v as f32 / max)
+        })
+        .collect::<Option<_>>()?;
+    if channels.len() < 3 {
+        return None;
+    }
+    // perceived brightness
+    let luma = 0.299 * channels[0] + 0.587 * channels[1] + 0.114 * channels[2];
+    Some(if luma > 0.5 {
+        Background::Light
+    } else {
+        Background::Dark
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background() {
+        assert_eq!(
+            parse_color_reply(b"\x1b]11;rgb:ffff/ffff/dddd\x07\x1b[12;1R"),
+            Some(Background::Light)
+        );
+        assert_eq!(
+            parse_color_reply(b"\x1b]11;rgb:1e/1e/2e\x1b\\\x1b[3;4R"),
+            Some(Background::Dark)
+        );
+        // only the cursor position came back
+        assert_eq!(parse_color_reply(b"\x1b[3;4R"), None);
+        assert_eq!(Background::at_hour(12), Background::Light);
+        assert_eq!(Background::at_hour(22), Background::Dark);
+        assert_eq!(Background::at_hour(6), Background::Dark);
+    }
+}
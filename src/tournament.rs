@@ -0,0 +1,170 @@
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::maze::{Difficulty, Maze, MazeError, Opts};
+use crate::replay::Replay;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Environment variable holding the tournament key, kept off the command line so it
+/// doesn't show up in the process list.
+pub const KEY_ENV: &str = "RUSTY_MAZE_TOURNAMENT_KEY";
+
+/// Size of every tournament maze, so the seed alone picks the maze.
+pub const WIDTH: u16 = 30;
+pub const HEIGHT: u16 = 15;
+/// Difficulty of every tournament maze.
+pub const DIFFICULTY: Difficulty = Difficulty::Hard;
+
+/// Tournament round played on a server provided seed, results are signed with the
+/// tournament key.
+#[derive(Clone)]
+pub struct Tournament {
+    pub seed: u64,
+    key: Vec<u8>,
+}
+
+impl fmt::Debug for Tournament {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tournament")
+            .field("seed", &self.seed)
+            .finish()
+    }
+}
+
+/// Outcome of a tournament run.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RunResult {
+    pub seed: u64,
+    /// fingerprint of the maze played
+    pub fingerprint: u64,
+    pub moves: usize,
+    pub millis: u64,
+    /// sha256 of the recorded steps
+    pub replay_hash: String,
+}
+
+/// Run result along with its HMAC-SHA256 signature, hex encoded.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SignedResult {
+    pub result: RunResult,
+    pub signature: String,
+}
+
+impl SignedResult {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let out = File::create(path)?;
+        ron::ser::to_writer(out, self)?;
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    // an odd length leaves a last slice out of bounds
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// hex sha256 of the steps of a replay
+pub fn replay_hash(replay: &Replay) -> String {
+    let steps = ron::ser::to_string(&replay.steps()).unwrap();
+    hex(&Sha256::digest(steps.as_bytes()))
+}
+
+impl Tournament {
+    pub fn new(seed: u64, key: &[u8]) -> Tournament {
+        Tournament {
+            seed,
+            key: key.to_vec(),
+        }
+    }
+
+    /// generation options of the tournament maze, the defaults but for the seed
+    pub fn opts(&self) -> Opts {
+        Opts {
+            difficulty: DIFFICULTY,
+            seed: Some(self.seed),
+            ..Default::default()
+        }
+    }
+
+    /// the maze every player of the tournament plays
+    pub fn maze(&self) -> Result<Maze, MazeError> {
+        Maze::generate(WIDTH, HEIGHT, &self.opts())
+    }
+
+    fn mac(&self, result: &RunResult) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(ron::ser::to_string(result).unwrap().as_bytes());
+        mac
+    }
+
+    pub fn sign(&self, result: RunResult) -> SignedResult {
+        let signature = hex(&self.mac(&result).finalize().into_bytes());
+        SignedResult { result, signature }
+    }
+
+    /// Check the signature was made with the tournament key for this seed, on the maze
+    /// generated from it.
+    pub fn verify(&self, signed: &SignedResult) -> bool {
+        signed.result.seed == self.seed
+            && matches!(self.maze(), Ok(m) if m.fingerprint() == signed.result.fingerprint)
+            && unhex(&signed.signature)
+                .map(|sig| self.mac(&signed.result).verify_slice(&sig).is_ok())
+                .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        let t = Tournament::new(42, b"secret");
+        let m = t.maze().unwrap();
+        assert_eq!((m.width, m.height), (WIDTH, HEIGHT));
+        let result = RunResult {
+            seed: 42,
+            fingerprint: m.fingerprint(),
+            moves: 3,
+            millis: 1200,
+            replay_hash: replay_hash(&Replay::new(&m)),
+        };
+        let signed = t.sign(result);
+        assert_eq!(signed.signature.len(), 64);
+        assert!(t.verify(&signed));
+        assert!(!Tournament::new(42, b"guess").verify(&signed));
+        assert!(!Tournament::new(43, b"secret").verify(&signed));
+
+        let mut forged = signed.clone();
+        forged.result.moves = 2;
+        assert!(!t.verify(&forged));
+        forged = signed.clone();
+        forged.signature.truncate(63);
+        assert!(!t.verify(&forged));
+
+        // a signed run of another maze, e.g. a reroll or another size, isn't the tournament
+        let small = Maze::create(3, 2, vec![(0, 3), (1, 4)]).unwrap();
+        let other = t.sign(RunResult {
+            fingerprint: small.fingerprint(),
+            ..signed.result
+        });
+        assert!(!t.verify(&other));
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+
+use rand::seq::IteratorRandom;
+
+use crate::maze::{Maze, Position};
+
+/// Moves between two hints.
+pub const HINT_EVERY: usize = 5;
+
+/// How the distance to the treasure changed since the last hint.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Hint {
+    Warmer,
+    Colder,
+    Same,
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hint::Warmer => write!(f, "You feel warmer"),
+            Hint::Colder => write!(f, "You feel colder"),
+            Hint::Same => write!(f, "Nothing changes"),
+        }
+    }
+}
+
+/// Hidden treasure cell, only hinted by the walking distance to it.
+#[derive(Clone, Debug)]
+pub struct Treasure {
+    pos: Position,
+    /// walking distance from the treasure to every cell
+    dist: Vec<Option<usize>>,
+    /// distance at the last hint
+    last: Option<usize>,
+    found: bool,
+}
+
+impl Treasure {
+    pub fn new(maze: &Maze, pos: Position) -> Treasure {
+        Treasure {
+            pos,
+            dist: maze.distance_map(pos),
+            last: None,
+            found: false,
+        }
+    }
+
+    /// Hide the treasure on a random reachable cell, never on the entrance or the exit.
+    pub fn random(maze: &Maze) -> Option<Treasure> {
+        let dist = maze.distance_map(maze.entrance());
+        (0..dist.len() as u16)
+            .filter(|c| dist[*c as usize].is_some())
+            .map(|c| maze.cell_to_pos(c))
+            .filter(|p| *p != maze.entrance() && !maze.is_exit(*p))
+            .choose(&mut rand::thread_rng())
+            .map(|p| Treasure::new(maze, p))
+    }
+
+    pub fn pos(&self) -> Position {
+        self.pos
+    }
+
+    pub fn is_found(&self) -> bool {
+        self.found
+    }
+
+    /// Dig at the position. Returns true when the treasure is found there for the first time.
+    pub fn dig(&mut self, p: Position) -> bool {
+        if self.found || p != self.pos {
+            return false;
+        }
+        self.found = true;
+        true
+    }
+
+    /// Compare the distance from the position to the one at the previous hint.
+    pub fn hint(&mut self, maze: &Maze, p: Position) -> Option<Hint> {
+        let d = self.dist[maze.pos_to_cell(p) as usize]?;
+        let hint = match self.last {
+            Some(l) if d < l => Hint::Warmer,
+            Some(l) if d > l => Hint::Colder,
+            Some(_) => Hint::Same,
+            None => {
+                self.last = Some(d);
+                return None;
+            }
+        };
+        self.last = Some(d);
+        Some(hint)
+    }
+}
+
+/// Score of a run, 1000 for the shortest path and lower for every extra move. Finding the
+/// treasure before the exit doubles it.
+pub fn score(shortest: usize, moves: usize, treasure: bool) -> usize {
+    let score = shortest * 1000 / moves.max(1);
+    if treasure {
+        score * 2
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint() {
+        // two corridors only joined on the right
+        let m = Maze::create(4, 2, vec![(0, 4), (1, 5), (2, 6)]).unwrap();
+        let mut t = Treasure::new(&m, Position { x: 0, y: 1 });
+        assert_eq!(t.hint(&m, Position { x: 0, y: 0 }), None);
+        assert_eq!(t.hint(&m, Position { x: 2, y: 0 }), Some(Hint::Warmer));
+        assert_eq!(t.hint(&m, Position { x: 3, y: 1 }), Some(Hint::Warmer));
+        assert_eq!(t.hint(&m, Position { x: 3, y: 0 }), Some(Hint::Colder));
+        assert_eq!(t.hint(&m, Position { x: 3, y: 0 }), Some(Hint::Same));
+
+        assert!(!t.dig(Position { x: 1, y: 1 }));
+        assert!(t.dig(Position { x: 0, y: 1 }));
+        assert!(!t.dig(Position { x: 0, y: 1 }));
+        assert!(t.is_found());
+
+        assert_eq!(score(7, 7, false), 1000);
+        assert_eq!(score(7, 14, true), 1000);
+        assert!(Treasure::random(&m).is_some());
+    }
+}
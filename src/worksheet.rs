@@ -0,0 +1,243 @@
+use crate::maze::Direction::{Down, Right};
+use crate::maze::{Maze, MazeError, Opts, Position};
+
+/// A4 page in PDF points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+/// Blank space around the page and between the mazes.
+const PAGE_MARGIN: f32 = 36.0;
+const GAP: f32 = 12.0;
+/// Height of the caption under every maze.
+const CAPTION: f32 = 14.0;
+
+/// Small mazes laid out in a grid on printable pages, captioned with their seeds so any
+/// of them can be generated again.
+pub struct Worksheet {
+    /// the mazes with their seeds, in reading order
+    pub mazes: Vec<(u64, Maze)>,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Worksheet {
+    /// Generate `count` mazes, the first from `seed` and every next one from the next seed.
+    pub fn generate(
+        count: usize,
+        width: u16,
+        height: u16,
+        opts: &Opts,
+        seed: u64,
+        columns: u32,
+        rows: u32,
+    ) -> Result<Worksheet, MazeError> {
+        let mazes = (0..count as u64)
+            .map(|i| {
+                let seed = seed.wrapping_add(i);
+                let opts = Opts {
+                    seed: Some(seed),
+                    ..opts.clone()
+                };
+                Ok((seed, Maze::generate(width, height, &opts)?))
+            })
+            .collect::<Result<_, MazeError>>()?;
+        Ok(Worksheet {
+            mazes,
+            columns: columns.max(1),
+            rows: rows.max(1),
+        })
+    }
+
+    /// The worksheet as a PDF document, with the solutions drawn in red on the answer
+    /// sheet.
+    pub fn pdf(&self, answers: bool) -> Vec<u8> {
+        let per_page = (self.columns * self.rows) as usize;
+        let pages: Vec<String> = self
+            .mazes
+            .chunks(per_page)
+            .enumerate()
+            .map(|(i, mazes)| self.page(mazes, i * per_page, answers))
+            .collect();
+        pdf(&pages)
+    }
+
+    /// drawing operators of a page, `first` is the index of its first maze
+    fn page(&self, mazes: &[(u64, Maze)], first: usize, answers: bool) -> String {
+        let slot_width = (PAGE_WIDTH - 2.0 * PAGE_MARGIN) / self.columns as f32;
+        let slot_height = (PAGE_HEIGHT - 2.0 * PAGE_MARGIN) / self.rows as f32;
+        let mut out = String::new();
+        for (i, (seed, m)) in mazes.iter().enumerate() {
+            let (column, row) = (i as u32 % self.columns, i as u32 / self.columns);
+            let cell = ((slot_width - GAP) / m.width as f32)
+                .min((slot_height - GAP - CAPTION) / m.height as f32);
+            // top left corner of the maze, centered in its slot
+            let left = PAGE_MARGIN
+                + column as f32 * slot_width
+                + (slot_width - cell * m.width as f32) / 2.0;
+            let top = PAGE_MARGIN + row as f32 * slot_height;
+            let path = if answers {
+                m.solution().unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            out.push_str(&maze(m, &path, left, top, cell));
+            let caption = if answers {
+                format!("{}. seed {}, answer", first + i + 1, seed)
+            } else {
+                format!("{}. seed {}", first + i + 1, seed)
+            };
+            out.push_str(&format!(
+                "BT /F1 9 Tf {:.1} {:.1} Td ({}) Tj ET\n",
+                left,
+                PAGE_HEIGHT - (top + cell * m.height as f32 + CAPTION - 2.0),
+                caption
+            ));
+        }
+        out
+    }
+}
+
+/// Drawing operators of the maze walls and the path through it, `left` and `top` are
+/// measured from the top left of the page like the rest of the layout.
+fn maze(m: &Maze, path: &[Position], left: f32, top: f32, cell: f32) -> String {
+    // PDF measures from the bottom of the page
+    let point = |x: f32, y: f32| (left + x * cell, PAGE_HEIGHT - (top + y * cell));
+    let (w, h) = (m.width as f32, m.height as f32);
+    let mut out = String::from("0 0 0 RG 1 w 2 J\n");
+    let (x0, y0) = point(0.0, 0.0);
+    out.push_str(&format!(
+        "{:.1} {:.1} {:.1} {:.1} re S\n",
+        x0,
+        y0 - h * cell,
+        w * cell,
+        h * cell
+    ));
+    for c in 0..m.width * m.height {
+        let p = Position {
+            x: c % m.width,
+            y: c / m.width,
+        };
+        let (x, y) = (p.x as f32, p.y as f32);
+        if m.is_blocked(p) {
+            let (bx, by) = point(x, y + 1.0);
+            out.push_str(&format!(
+                "{:.1} {:.1} {:.1} {:.1} re f\n",
+                bx, by, cell, cell
+            ));
+        }
+        if p.x + 1 < m.width && m.move_pos(p, &Right).is_none() {
+            out.push_str(&line(point(x + 1.0, y), point(x + 1.0, y + 1.0)));
+        }
+        if p.y + 1 < m.height && m.move_pos(p, &Down).is_none() {
+            out.push_str(&line(point(x, y + 1.0), point(x + 1.0, y + 1.0)));
+        }
+    }
+    if let Some((first, rest)) = path.split_first() {
+        let center = |p: &Position| point(p.x as f32 + 0.5, p.y as f32 + 0.5);
+        let (x, y) = center(first);
+        out.push_str(&format!(
+            "1 0 0 RG {:.1} w {:.1} {:.1} m\n",
+            cell / 4.0,
+            x,
+            y
+        ));
+        for p in rest {
+            let (x, y) = center(p);
+            out.push_str(&format!("{:.1} {:.1} l\n", x, y));
+        }
+        out.push_str("S\n");
+    }
+    out
+}
+
+fn line(from: (f32, f32), to: (f32, f32)) -> String {
+    format!(
+        "{:.1} {:.1} m {:.1} {:.1} l S\n",
+        from.0, from.1, to.0, to.1
+    )
+}
+
+/// A PDF document of A4 pages drawn by the given operators, with Helvetica as font F1.
+fn pdf(pages: &[String]) -> Vec<u8> {
+    // catalog, page tree and font come first, then every page and its contents
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|i| format!("{} 0 R", 4 + 2 * i))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+    for (i, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            5 + 2 * i
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = out.len();
+    out.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worksheet() {
+        let sheet = Worksheet::generate(7, 8, 8, &Opts::default(), 40, 2, 3).unwrap();
+        assert_eq!(sheet.mazes.len(), 7);
+        assert_eq!(sheet.mazes[6].0, 46);
+        let opts = Opts {
+            seed: Some(46),
+            ..Default::default()
+        };
+        assert_eq!(
+            sheet.mazes[6].1.fingerprint(),
+            Maze::generate(8, 8, &opts).unwrap().fingerprint()
+        );
+
+        let pdf = String::from_utf8(sheet.pdf(false)).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.contains("/Count 2 >>"));
+        assert!(pdf.contains("(7. seed 46) Tj"));
+        assert!(!pdf.contains("1 0 0 RG"));
+        // the cross reference table points at the objects
+        let xref: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n0 8\n"));
+        let offset: usize = pdf[xref..].lines().nth(5).unwrap()[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with("3 0 obj"));
+
+        let answers = String::from_utf8(sheet.pdf(true)).unwrap();
+        assert!(answers.contains("1 0 0 RG"));
+        assert!(answers.contains("(1. seed 40, answer) Tj"));
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::maze::{Direction, Position, DIRECTIONS};
+use crate::records::Record;
+
+/// Where the world map is kept, `rusty-maze/world.ron` in the data directory of the
+/// platform next to the statistics. None when there is no such directory.
+pub fn world_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rusty-maze").join("world.ron"))
+}
+
+/// A maze of the world map.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Node {
+    /// seed generating the maze of the node
+    pub seed: u64,
+    pub unlocked: bool,
+    /// best completion, None until the maze is solved
+    pub best: Option<Record>,
+}
+
+impl Node {
+    pub fn is_completed(&self) -> bool {
+        self.best.is_some()
+    }
+}
+
+/// Overworld of connected mazes. Play starts in the center and completing a maze unlocks
+/// its neighbours.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldMap {
+    pub width: u16,
+    pub height: u16,
+    nodes: Vec<Node>,
+    /// node selected on the map screen
+    pub cursor: Position,
+}
+
+impl WorldMap {
+    pub fn new(width: u16, height: u16) -> WorldMap {
+        let mut rng = rand::thread_rng();
+        let center = Position {
+            x: width / 2,
+            y: height / 2,
+        };
+        let nodes = (0..width * height)
+            .map(|i| Node {
+                seed: rng.gen(),
+                unlocked: i == center.y * width + center.x,
+                best: None,
+            })
+            .collect();
+        WorldMap {
+            width,
+            height,
+            nodes,
+            cursor: center,
+        }
+    }
+
+    /// Load the map from the given file. A missing or unreadable file yields None.
+    pub fn load<P: AsRef<Path>>(path: P) -> Option<WorldMap> {
+        File::open(path)
+            .ok()
+            .and_then(|f| ron::de::from_reader(BufReader::new(f)).ok())
+    }
+
+    /// Write the map with its unlock state and best times to the given file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let out = File::create(path)?;
+        ron::ser::to_writer(out, self)?;
+        Ok(())
+    }
+
+    fn index(&self, p: Position) -> usize {
+        (p.y * self.width + p.x) as usize
+    }
+
+    pub fn node(&self, p: Position) -> &Node {
+        &self.nodes[self.index(p)]
+    }
+
+    /// neighbour of the position on the map, if any
+    fn neighbour(&self, p: Position, d: &Direction) -> Option<Position> {
+        p.checked_mv(d, 1)
+            .filter(|n| n.x < self.width && n.y < self.height)
+    }
+
+    /// move the cursor, it stays put at the edges of the map
+    pub fn move_cursor(&mut self, d: &Direction) {
+        if let Some(p) = self.neighbour(self.cursor, d) {
+            self.cursor = p;
+        }
+    }
+
+    /// Record a completion of the node, keeping the best run and unlocking the neighbours.
+    /// Returns true for a new best.
+    pub fn complete(&mut self, p: Position, run: Record) -> bool {
+        let i = self.index(p);
+        let best = match self.nodes[i].best {
+            Some(b) if (b.moves, b.millis) <= (run.moves, run.millis) => false,
+            _ => {
+                self.nodes[i].best = Some(run);
+                true
+            }
+        };
+        for d in DIRECTIONS.iter() {
+            if let Some(n) = self.neighbour(p, d) {
+                let j = self.index(n);
+                self.nodes[j].unlocked = true;
+            }
+        }
+        best
+    }
+
+    /// Map screen lines: completed nodes as ■, unlocked ones as □ and locked ones as ·,
+    /// the cursor is bracketed.
+    pub fn render(&self) -> Vec<String> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        let p = Position { x, y };
+                        let n = self.node(p);
+                        let c = if n.is_completed() {
+                            '■'
+                        } else if n.unlocked {
+                            '□'
+                        } else {
+                            '·'
+                        };
+                        if p == self.cursor {
+                            format!("[{}]", c)
+                        } else {
+                            format!(" {} ", c)
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_unlock() {
+        let mut map = WorldMap::new(3, 3);
+        let center = Position { x: 1, y: 1 };
+        assert!(map.node(center).unlocked);
+        assert!(!map.node(Position { x: 0, y: 1 }).unlocked);
+
+        assert!(map.complete(center, Record::new(9, Duration::from_secs(3))));
+        assert!(!map.complete(center, Record::new(12, Duration::from_secs(1))));
+        assert!(map.node(Position { x: 0, y: 1 }).unlocked);
+        assert!(!map.node(Position { x: 0, y: 0 }).unlocked);
+
+        map.move_cursor(&Direction::Up);
+        map.move_cursor(&Direction::Up);
+        assert_eq!(map.cursor, Position { x: 1, y: 0 });
+        assert_eq!(map.render(), [" · [□] · ", " □  ■  □ ", " ·  □  · "]);
+    }
+}